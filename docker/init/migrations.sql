@@ -398,4 +398,70 @@ CREATE INDEX IF NOT EXISTS index_temp_request_count ON temp_incoming_request(inc
 CREATE INDEX IF NOT EXISTS index_iru_path_id ON incoming_request_url (incoming_request_url_path_id);
 
 \echo "update RHO municipality"
-UPDATE airport_municipality am SET municipality = 'Rhodes Island' WHERE am.municipality = 'Rodes Island';
\ No newline at end of file
+UPDATE airport_municipality am SET municipality = 'Rhodes Island' WHERE am.municipality = 'Rodes Island';
+
+-- v0.7.0
+
+\echo "Create scrape_attempt table"
+CREATE TABLE IF NOT EXISTS scrape_attempt (
+    scrape_attempt_id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    timestamp TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    scrape_kind TEXT NOT NULL CHECK (scrape_kind IN ('callsign', 'photo')),
+    search TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('success', 'timeout', 'request', 'circuit_open', 'parse', 'not_found', 'airport_clash', 'unknown_airport', 'unknown_airline')),
+    reason TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    attempted_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    next_eligible TIMESTAMPTZ,
+    UNIQUE (scrape_kind, search)
+);
+
+GRANT ALL ON scrape_attempt TO adsbdb;
+GRANT USAGE, SELECT ON SEQUENCE scrape_attempt_scrape_attempt_id_seq TO adsbdb;
+
+CREATE INDEX IF NOT EXISTS index_scrape_attempt_next_eligible ON scrape_attempt (next_eligible);
+
+\echo "Add validated_at to flightroute, only set for scraped flightroutes, so that they can be re-scraped when stale"
+-- Flightroutes inserted before this migration can't be told apart from seeded, or admin corrected, flightroutes, so are left NULL, and never revalidated
+ALTER TABLE flightroute ADD COLUMN IF NOT EXISTS validated_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS index_flightroute_validated_at ON flightroute (validated_at);
+
+\echo "Add photographer credit, and source page, to aircraft_photo"
+ALTER TABLE aircraft_photo ADD COLUMN IF NOT EXISTS photographer TEXT;
+ALTER TABLE aircraft_photo ADD COLUMN IF NOT EXISTS url_page TEXT;
+
+\echo "Create aircraft_gallery table, multiple ordered photos per aircraft, aircraft.aircraft_photo_id is kept as the first photo"
+CREATE TABLE IF NOT EXISTS aircraft_gallery (
+    aircraft_gallery_id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    timestamp TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    aircraft_id BIGINT REFERENCES aircraft(aircraft_id) NOT NULL,
+    aircraft_photo_id BIGINT REFERENCES aircraft_photo(aircraft_photo_id) NOT NULL,
+    position SMALLINT NOT NULL,
+    UNIQUE (aircraft_id, position)
+);
+
+GRANT ALL ON aircraft_gallery TO adsbdb;
+GRANT USAGE, SELECT ON SEQUENCE aircraft_gallery_aircraft_gallery_id_seq TO adsbdb;
+
+CREATE INDEX IF NOT EXISTS index_aircraft_gallery_aircraft_photo_id ON aircraft_gallery (aircraft_photo_id);
+
+\echo "Copy existing aircraft photos into aircraft_gallery"
+INSERT INTO aircraft_gallery (aircraft_id, aircraft_photo_id, position)
+SELECT aircraft_id, aircraft_photo_id, 0 FROM aircraft WHERE aircraft_photo_id IS NOT NULL
+ON CONFLICT (aircraft_id, position) DO NOTHING;
+
+\echo "Create pending_flightroute table, scraped flightroutes that couldn't be inserted, waiting to be accepted or rejected"
+CREATE TABLE IF NOT EXISTS pending_flightroute (
+    pending_flightroute_id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    timestamp TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    callsign_icao TEXT NOT NULL UNIQUE,
+    callsign_iata TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    midpoint TEXT,
+    destination TEXT NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('airport_clash', 'unknown_airport', 'unknown_airline')),
+    rejected_at TIMESTAMPTZ
+);
+
+GRANT ALL ON pending_flightroute TO adsbdb;
+GRANT USAGE, SELECT ON SEQUENCE pending_flightroute_pending_flightroute_id_seq TO adsbdb;
@@ -82,7 +82,8 @@ make_db_data_directories() {
 	local pg_data="${BASE_DIR}/databases.d/${APP_NAME}/pg_data"
 	local redis_data="${BASE_DIR}/databases.d/${APP_NAME}/redis_data"
 	local backups="${BASE_DIR}/databases.d/${APP_NAME}/backups"
-	for DIRECTORY in $pg_data $redis_data $backups; do
+	local photo_cache="${BASE_DIR}/databases.d/${APP_NAME}/photo_cache"
+	for DIRECTORY in $pg_data $redis_data $backups $photo_cache; do
 		if [[ ! -d "$DIRECTORY" ]]; then
 			echo -e "${GREEN}making directory:${RESET} \"$DIRECTORY\""
 			mkdir -p "$DIRECTORY"
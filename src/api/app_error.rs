@@ -9,23 +9,40 @@ use tracing::error;
 
 use crate::S;
 
-use super::response::ResponseJson;
+use super::{
+    AircraftSearch,
+    response::{ResponseAllocation, ResponseJson, UnknownAircraftJson},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum UnknownAC {
-    Aircraft,
+    Aircraft(Option<ResponseAllocation>),
     Callsign,
     Airline,
     Airport(String),
+    PendingFlightroute,
+    Photo,
 }
 
 impl fmt::Display for UnknownAC {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Aircraft => write!(f, "aircraft"),
+            Self::Aircraft(_) => write!(f, "aircraft"),
             Self::Airline => write!(f, "airline"),
             Self::Callsign => write!(f, "callsign"),
             Self::Airport(icao) => write!(f, "airport: {icao}"),
+            Self::PendingFlightroute => write!(f, "pending flightroute"),
+            Self::Photo => write!(f, "photo"),
+        }
+    }
+}
+
+impl UnknownAC {
+    /// Unknown aircraft, if searched by mode_s then include the ICAO allocation of that mode_s
+    pub fn aircraft(aircraft_search: &AircraftSearch) -> Self {
+        match aircraft_search {
+            AircraftSearch::ModeS(mode_s) => Self::Aircraft(Some(ResponseAllocation::from(mode_s))),
+            AircraftSearch::Registration(_) => Self::Aircraft(None),
         }
     }
 }
@@ -36,6 +53,8 @@ pub enum AppError {
     AircraftSearch(String),
     #[error("invalid airline:")]
     Airline(String),
+    #[error("invalid airport:")]
+    Airport(String),
     #[error("invalid authorization")]
     Authorization,
     #[error("Axum")]
@@ -52,6 +71,8 @@ pub enum AppError {
     ModeS(String),
     #[error("invalid n_number:")]
     NNumber(String),
+    #[error("invalid query param:")]
+    Query(String),
     #[error("parse int")]
     ParseInt(#[from] ParseIntError),
     #[error("rate limited for")]
@@ -68,6 +89,8 @@ pub enum AppError {
     SqlxError(#[from] sqlx::Error),
     #[error("unknown")]
     UnknownInDb(UnknownAC),
+    #[error("upstream error:")]
+    Upstream(String),
 }
 
 /// Return the internal server error, with a basic { response: "$prefix" }
@@ -89,6 +112,16 @@ impl IntoResponse for AppError {
         };
 
         let prefix = self.to_string();
+        if let Self::UnknownInDb(UnknownAC::Aircraft(Some(allocation))) = self {
+            return (
+                StatusCode::NOT_FOUND,
+                axum::Json(UnknownAircraftJson {
+                    response: format!("{prefix} {}", UnknownAC::Aircraft(None)),
+                    allocation,
+                }),
+            )
+                .into_response();
+        }
         let (status, body) = match self {
             Self::AxumExtension(e) => {
                 error!("{e:?}");
@@ -101,9 +134,11 @@ impl IntoResponse for AppError {
             Self::Callsign(err)
             | Self::AircraftSearch(err)
             | Self::Airline(err)
+            | Self::Airport(err)
             | Self::ModeS(err)
             | Self::NNumber(err)
             | Self::Body(err)
+            | Self::Query(err)
             | Self::Registration(err) => (
                 StatusCode::BAD_REQUEST,
                 ResponseJson::new(format!("{prefix} {err}")),
@@ -161,6 +196,10 @@ impl IntoResponse for AppError {
                 StatusCode::NOT_FOUND,
                 ResponseJson::new(format!("{prefix} {variety}")),
             ),
+            Self::Upstream(e) => (
+                StatusCode::BAD_GATEWAY,
+                ResponseJson::new(format!("{prefix} {e}")),
+            ),
         };
 
         (status, body).into_response()
@@ -0,0 +1,243 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{
+        HeaderMap, HeaderValue, Method, Request, StatusCode,
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, VARY},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::db_redis::{LOOKUP_TTL, ONE_MINUTE_AS_SEC};
+
+/// Max age of a lookup that the update routes can change, after which it's revalidated with its ETag
+const UPDATABLE_MAX_AGE: i64 = ONE_MINUTE_AS_SEC * 5;
+
+/// Cache-Control policy of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheControl {
+    /// Response changes on every request, e.g. random and stats
+    NoStore,
+    /// Response can be stored, but must be revalidated with the ETag before each use
+    NoCache,
+    /// Response is a cached lookup, so can be re-used for as long as it would live in redis
+    Lookup,
+    /// Response is a cached lookup that the update routes can change, so is only re-used briefly
+    Updatable,
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoStore => write!(f, "no-store"),
+            Self::NoCache => write!(f, "no-cache"),
+            Self::Lookup => write!(f, "public, max-age={LOOKUP_TTL}"),
+            Self::Updatable => write!(f, "public, max-age={UPDATABLE_MAX_AGE}"),
+        }
+    }
+}
+
+/// Strong ETag of a response body, the first 128 bits of its sha256 as hex
+fn etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex = digest
+        .iter()
+        .take(16)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("\"{hex}\"")
+}
+
+/// If-None-Match uses the weak comparison, so a W/ prefix is ignored, and * matches any ETag
+pub fn none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Cache-Control, and ETag, headers for a response whose ETag is known without reading its body, e.g. a streamed photo
+pub fn cache_headers(cache_control: CacheControl, etag: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&cache_control.to_string()) {
+        headers.insert(CACHE_CONTROL, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(ETAG, value);
+    }
+    headers
+}
+
+/// Add Cache-Control, and an ETag, to successful GET responses, answering 304 Not Modified when If-None-Match matches the ETag
+pub async fn conditional_get(
+    State(cache_control): State<CacheControl>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let headers = req.headers().clone();
+    let get = req.method() == Method::GET;
+    let response = next.run(req).await;
+
+    if !get || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let mut cache_headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&cache_control.to_string()) {
+        cache_headers.insert(CACHE_CONTROL, value);
+    }
+    // The response format can be negotiated with the Accept header
+    cache_headers.insert(VARY, HeaderValue::from_static("accept"));
+
+    let (mut parts, body) = response.into_parts();
+    if cache_control == CacheControl::NoStore {
+        parts.headers.extend(cache_headers);
+        return Response::from_parts(parts, body);
+    }
+
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = etag(&bytes);
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        cache_headers.insert(ETAG, value);
+    }
+
+    if none_match(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    parts.headers.extend(cache_headers);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// cargo watch -q -c -w src/ -x 'test mod_api_cache_control -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use axum::{Router, middleware, routing::get};
+    use tower::ServiceExt;
+
+    const BODY: &str = r#"{"response":"ok"}"#;
+
+    fn router(cache_control: CacheControl) -> Router {
+        Router::new()
+            .route("/ok", get(|| async { BODY }))
+            .route("/missing", get(|| async { (StatusCode::NOT_FOUND, BODY) }))
+            .layer(middleware::from_fn_with_state(
+                cache_control,
+                conditional_get,
+            ))
+    }
+
+    async fn send(router: Router, uri: &str, if_none_match: Option<&str>) -> Response {
+        let mut req = Request::builder().uri(uri);
+        if let Some(if_none_match) = if_none_match {
+            req = req.header(IF_NONE_MATCH, if_none_match);
+        }
+        router
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn mod_api_cache_control_display() {
+        assert_eq!(CacheControl::NoStore.to_string(), "no-store");
+        assert_eq!(CacheControl::NoCache.to_string(), "no-cache");
+        assert_eq!(CacheControl::Lookup.to_string(), "public, max-age=604800");
+        assert_eq!(CacheControl::Updatable.to_string(), "public, max-age=300");
+    }
+
+    #[test]
+    fn mod_api_cache_control_etag() {
+        let result = etag(BODY.as_bytes());
+        assert_eq!(result, etag(BODY.as_bytes()));
+        assert_ne!(result, etag(b"{}"));
+        assert_eq!(result.len(), 34);
+        assert!(result.starts_with('"') && result.ends_with('"'));
+    }
+
+    #[test]
+    fn mod_api_cache_control_none_match() {
+        let etag = etag(BODY.as_bytes());
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+
+        assert!(none_match(&headers(&etag), &etag));
+        assert!(none_match(&headers(&format!("W/{etag}")), &etag));
+        assert!(none_match(&headers(&format!("\"abc\", {etag}")), &etag));
+        assert!(none_match(&headers("*"), &etag));
+        assert!(!none_match(&headers("\"abc\""), &etag));
+        assert!(!none_match(&HeaderMap::new(), &etag));
+    }
+
+    #[tokio::test]
+    async fn mod_api_cache_control_lookup() {
+        let response = send(router(CacheControl::Lookup), "/ok", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=604800"
+        );
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept");
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(etag, super::etag(BODY.as_bytes()));
+        assert_eq!(body(response).await, BODY);
+
+        // Matching ETag
+        let response = send(router(CacheControl::Lookup), "/ok", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(ETAG).unwrap(), etag.as_str());
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=604800"
+        );
+        assert!(body(response).await.is_empty());
+
+        // Stale ETag
+        let response = send(router(CacheControl::Lookup), "/ok", Some("\"abc\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, BODY);
+    }
+
+    #[tokio::test]
+    async fn mod_api_cache_control_no_store() {
+        let response = send(router(CacheControl::NoStore), "/ok", Some("*")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+        assert!(response.headers().get(ETAG).is_none());
+        assert_eq!(body(response).await, BODY);
+    }
+
+    #[tokio::test]
+    async fn mod_api_cache_control_error_untouched() {
+        let response = send(router(CacheControl::Lookup), "/missing", Some("*")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+        assert!(response.headers().get(ETAG).is_none());
+        assert_eq!(body(response).await, BODY);
+    }
+}
@@ -1,15 +1,17 @@
-use std::fmt;
+use std::{collections::HashMap, fmt};
 
 use axum::{extract::FromRequestParts, http::request::Parts};
 
 use crate::{
     S,
+    db_postgres::PendingFlightrouteId,
+    geodesy::Coordinate,
     n_number::{ALLCHARS, n_number_to_mode_s},
 };
 
-use super::AppError;
+use super::{AppError, UnknownAC};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AircraftSearch {
     ModeS(ModeS),
     Registration(Registration),
@@ -70,6 +72,37 @@ impl Validate for AirlineCode {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AirportCode {
+    Iata(String),
+    Icao(String),
+}
+
+impl fmt::Display for AirportCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Iata(x) | Self::Icao(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl Validate for AirportCode {
+    /// Make sure that input is a valid airport code, [a-z0-9]{3-4}, and return as uppercase
+    fn validate(input: &str) -> Result<Self, AppError> {
+        let input = input.to_uppercase();
+        let count = input.chars().count();
+        if (3..=4).contains(&count) && input.chars().all(|c| valid_char(c, 'z')) {
+            if count == 3 {
+                Ok(Self::Iata(input))
+            } else {
+                Ok(Self::Icao(input))
+            }
+        } else {
+            Err(AppError::Airport(input))
+        }
+    }
+}
+
 /// Make unit structs, StructName(String), and impl display on it
 macro_rules! unit_struct {
     ($struct_name:ident) => {
@@ -118,20 +151,285 @@ where
     type Rejection = AppError;
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         match axum::extract::Path::<String>::from_request_parts(parts, state).await {
-            Ok(value) => {
-                if let Ok(mode_s) = ModeS::validate(&value.0) {
-                    return Ok(Self::ModeS(mode_s));
-                }
-                if let Ok(registration) = Registration::validate(&value.0) {
-                    return Ok(Self::Registration(registration));
+            Ok(value) => Self::validate(&value.0),
+            Err(_) => Err(AppError::AircraftSearch(S!())),
+        }
+    }
+}
+
+/// A pending flightroute id that isn't a number can't exist, so is also a 404
+impl<S> FromRequestParts<S> for PendingFlightrouteId
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Path::<String>::from_request_parts(parts, state)
+            .await
+            .ok()
+            .and_then(|value| value.0.parse::<i64>().ok())
+            .map(Self::from)
+            .ok_or(AppError::UnknownInDb(UnknownAC::PendingFlightroute))
+    }
+}
+
+impl Validate for AircraftSearch {
+    /// Valid mode_s take precedence over registrations
+    fn validate(input: &str) -> Result<Self, AppError> {
+        if let Ok(mode_s) = ModeS::validate(input) {
+            return Ok(Self::ModeS(mode_s));
+        }
+        if let Ok(registration) = Registration::validate(input) {
+            return Ok(Self::Registration(registration));
+        }
+        Err(AppError::AircraftSearch(input.to_owned()))
+    }
+}
+
+/// Which end, or ends, of a flightroute an airport should be matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RouteDirection {
+    Origin,
+    Destination,
+    #[default]
+    Any,
+}
+
+impl Validate for RouteDirection {
+    /// Make sure that input is one of origin, destination, or any
+    fn validate(input: &str) -> Result<Self, AppError> {
+        match input.to_lowercase().as_str() {
+            "origin" => Ok(Self::Origin),
+            "destination" => Ok(Self::Destination),
+            "any" => Ok(Self::Any),
+            _ => Err(AppError::Query(format!("direction {input}"))),
+        }
+    }
+}
+
+/// Output format of a response, from either the format query param, or the Accept header, with the query param taking precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    GeoJson,
+    Csv,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    pub const GEOJSON_MIME: &str = "application/geo+json";
+    pub const CSV_MIME: &str = "text/csv";
+    pub const MESSAGEPACK_MIME: &str = "application/msgpack";
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::GeoJson => "geojson",
+            Self::Csv => "csv",
+            Self::MessagePack => "msgpack",
+        }
+    }
+
+    /// Known media type with the highest q-value in an Accept header, ties go to the first listed,
+    /// a q-value of 0 is not acceptable, and a header without a known media type is Json
+    fn from_accept(accept: &str) -> Self {
+        let mut output = None;
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';');
+            let format = match params
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase()
+                .as_str()
+            {
+                Self::GEOJSON_MIME => Self::GeoJson,
+                Self::CSV_MIME => Self::Csv,
+                Self::MESSAGEPACK_MIME | "application/x-msgpack" | "application/vnd.msgpack" => {
+                    Self::MessagePack
                 }
-                Err(AppError::AircraftSearch(value.0))
+                "application/json" => Self::Json,
+                _ => continue,
+            };
+            let q = params
+                .filter_map(|i| i.trim().split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+                .map_or(Some(1.0), |(_, q)| q.trim().parse::<f32>().ok())
+                .unwrap_or_default();
+            if q > 0.0 && output.is_none_or(|(_, best)| q > best) {
+                output = Some((format, q));
             }
-            Err(_) => Err(AppError::AircraftSearch(S!())),
+        }
+        output.map(|(format, _)| format).unwrap_or_default()
+    }
+}
+
+impl Validate for ResponseFormat {
+    /// Make sure that input is one of json, geojson, csv, or msgpack
+    fn validate(input: &str) -> Result<Self, AppError> {
+        match input.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "geojson" => Ok(Self::GeoJson),
+            "csv" => Ok(Self::Csv),
+            "msgpack" => Ok(Self::MessagePack),
+            _ => Err(AppError::Query(format!("format {input}"))),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let queries = axum::extract::Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map(|i| i.0)
+            .unwrap_or_default();
+        if let Some(format) = queries.get("format") {
+            return Self::validate(format);
+        }
+        Ok(parts
+            .headers
+            .get(axum::http::header::ACCEPT)
+            .and_then(|i| i.to_str().ok())
+            .map(Self::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// Airport types, as used by ourairports.com
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AirportType {
+    Balloonport,
+    Closed,
+    Heliport,
+    LargeAirport,
+    MediumAirport,
+    SeaplaneBase,
+    SmallAirport,
+}
+
+impl AirportType {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Balloonport => "balloonport",
+            Self::Closed => "closed",
+            Self::Heliport => "heliport",
+            Self::LargeAirport => "large_airport",
+            Self::MediumAirport => "medium_airport",
+            Self::SeaplaneBase => "seaplane_base",
+            Self::SmallAirport => "small_airport",
+        }
+    }
+}
+
+impl Validate for AirportType {
+    /// Make sure that input is one of the known airport types
+    fn validate(input: &str) -> Result<Self, AppError> {
+        match input.to_lowercase().as_str() {
+            "balloonport" => Ok(Self::Balloonport),
+            "closed" => Ok(Self::Closed),
+            "heliport" => Ok(Self::Heliport),
+            "large_airport" => Ok(Self::LargeAirport),
+            "medium_airport" => Ok(Self::MediumAirport),
+            "seaplane_base" => Ok(Self::SeaplaneBase),
+            "small_airport" => Ok(Self::SmallAirport),
+            _ => Err(AppError::Query(format!("airport_type {input}"))),
         }
     }
 }
 
+pub const NEAREST_RADIUS_KM_DEFAULT: f64 = 50.0;
+pub const NEAREST_RADIUS_KM_MAX: f64 = 500.0;
+pub const NEAREST_LIMIT_DEFAULT: usize = 10;
+pub const NEAREST_LIMIT_MAX: usize = 100;
+
+/// Query params for a nearest airports search, lat & lon are required, radius_km, limit, and airport_type are optional
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NearestAirports {
+    pub coordinate: Coordinate,
+    pub radius_km: f64,
+    pub limit: usize,
+    pub airport_type: Option<AirportType>,
+}
+
+impl NearestAirports {
+    /// Parse a query param as an f64 that is within the given range
+    fn parse_range(
+        queries: &HashMap<String, String>,
+        key: &str,
+        range: std::ops::RangeInclusive<f64>,
+    ) -> Result<Option<f64>, AppError> {
+        queries
+            .get(key)
+            .map(|i| {
+                i.parse::<f64>()
+                    .ok()
+                    .filter(|x| range.contains(x))
+                    .ok_or_else(|| AppError::Query(format!("{key} {i}")))
+            })
+            .transpose()
+    }
+
+    pub fn validate(queries: &HashMap<String, String>) -> Result<Self, AppError> {
+        let latitude = Self::parse_range(queries, "lat", -90.0..=90.0)?
+            .ok_or_else(|| AppError::Query(S!("lat missing")))?;
+        let longitude = Self::parse_range(queries, "lon", -180.0..=180.0)?
+            .ok_or_else(|| AppError::Query(S!("lon missing")))?;
+        let radius_km = Self::parse_range(queries, "radius_km", 0.0..=NEAREST_RADIUS_KM_MAX)?
+            .unwrap_or(NEAREST_RADIUS_KM_DEFAULT);
+        let limit = queries
+            .get("limit")
+            .map(|i| {
+                i.parse::<usize>()
+                    .ok()
+                    .filter(|x| (1..=NEAREST_LIMIT_MAX).contains(x))
+                    .ok_or_else(|| AppError::Query(format!("limit {i}")))
+            })
+            .transpose()?
+            .unwrap_or(NEAREST_LIMIT_DEFAULT);
+        let airport_type = queries
+            .get("airport_type")
+            .map(|i| AirportType::validate(i))
+            .transpose()?;
+
+        Ok(Self {
+            coordinate: Coordinate::new(latitude, longitude),
+            radius_km,
+            limit,
+            airport_type,
+        })
+    }
+}
+
+/// Max number of items that can be searched for in a single batch request
+pub const BATCH_MAX: usize = 100;
+
+/// A de-duplicated collection of AircraftSearch, from a JSON array of strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AircraftSearchBatch(pub Vec<AircraftSearch>);
+
+impl AircraftSearchBatch {
+    /// Make sure that input contains between 1 and BATCH_MAX valid aircraft searches
+    pub fn validate(input: &[String]) -> Result<Self, AppError> {
+        if input.is_empty() || input.len() > BATCH_MAX {
+            return Err(AppError::Body(format!(
+                "between 1 and {BATCH_MAX} aircraft"
+            )));
+        }
+        let mut output: Vec<AircraftSearch> = Vec::with_capacity(input.len());
+        for i in input {
+            let aircraft_search = AircraftSearch::validate(i)?;
+            if !output.contains(&aircraft_search) {
+                output.push(aircraft_search);
+            }
+        }
+        Ok(Self(output))
+    }
+}
+
 /// from_request_parts macro, to run Self::validate
 macro_rules! from_request_parts {
     ($struct_name:ident, AppError::$variant:ident) => {
@@ -175,6 +473,8 @@ from_request_parts!(ModeS);
 from_request_parts!(NNumber, AppError::NNumber);
 from_request_parts!(Callsign, AppError::AircraftSearch);
 from_request_parts!(AirlineCode);
+from_request_parts!(AirportCode, AppError::Airport);
+from_request_parts!(Registration, AppError::Registration);
 
 impl Validate for Registration {
     /// Make sure that input is a valid registration, less than 16 chars, and convert to uppercase
@@ -228,6 +528,17 @@ pub enum Callsign {
     Other(String),
 }
 
+/// How a flightroute's callsign relates to the callsign that was searched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CallsignVariant {
+    Exact,
+    /// Leading zeros removed from the flight number, BAW012 to BAW12
+    Normalised,
+    /// Operational suffix letters removed from the flight number, EZY45KP to EZY45
+    BaseFlight,
+}
+
 impl Callsign {
     pub fn get_suffix(&self) -> Option<String> {
         match self {
@@ -235,6 +546,36 @@ impl Callsign {
             Self::Other(_) => None,
         }
     }
+
+    /// Alternative callsigns to search for, in order, when there isn't an exact match
+    /// Only ICAO and IATA callsigns have a flight number that can be normalised
+    pub fn fallbacks(&self) -> Vec<(Self, CallsignVariant)> {
+        let (Self::Icao((prefix, suffix)) | Self::Iata((prefix, suffix))) = self else {
+            return vec![];
+        };
+        let with_suffix = |suffix: &str| match self {
+            Self::Icao(_) => Self::Icao((prefix.clone(), S!(suffix))),
+            _ => Self::Iata((prefix.clone(), S!(suffix))),
+        };
+
+        let mut output = vec![];
+        let trimmed = suffix.trim_start_matches('0');
+        let normalised = if trimmed != suffix && trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            output.push((with_suffix(trimmed), CallsignVariant::Normalised));
+            trimmed
+        } else {
+            suffix.as_str()
+        };
+
+        let base = normalised.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        if base != normalised
+            && base.starts_with(|c: char| ('1'..='9').contains(&c))
+            && base.chars().all(|c| c.is_ascii_digit())
+        {
+            output.push((with_suffix(base), CallsignVariant::BaseFlight));
+        }
+        output
+    }
 }
 
 impl fmt::Display for Callsign {
@@ -275,6 +616,41 @@ impl Validate for Callsign {
     }
 }
 
+/// A collection of inputs, each paired with either a valid Callsign, or the validation error
+#[derive(Debug)]
+pub struct CallsignBatch(pub Vec<(String, Result<Callsign, AppError>)>);
+
+impl CallsignBatch {
+    /// Make sure that input contains between 1 and BATCH_MAX items, invalid callsigns don't invalidate the whole batch
+    pub fn validate(input: Vec<String>) -> Result<Self, AppError> {
+        if input.is_empty() || input.len() > BATCH_MAX {
+            return Err(AppError::Body(format!(
+                "between 1 and {BATCH_MAX} callsigns"
+            )));
+        }
+        Ok(Self(
+            input
+                .into_iter()
+                .map(|i| {
+                    let callsign = Callsign::validate(&i);
+                    (i, callsign)
+                })
+                .collect(),
+        ))
+    }
+
+    /// De-duplicated valid callsigns
+    pub fn callsigns(&self) -> Vec<Callsign> {
+        let mut output = vec![];
+        for callsign in self.0.iter().filter_map(|i| i.1.as_ref().ok()) {
+            if !output.contains(callsign) {
+                output.push(callsign.clone());
+            }
+        }
+        output
+    }
+}
+
 /// cargo watch -q -c -w src/ -x 'test mod_api_input -- --nocapture'
 #[cfg(test)]
 #[allow(clippy::pedantic, clippy::unwrap_used)]
@@ -357,6 +733,86 @@ mod tests {
         test("aaa124*");
     }
 
+    #[test]
+    fn mod_api_input_callsign_fallbacks() {
+        let test = |input: &str| {
+            Callsign::validate(input)
+                .unwrap()
+                .fallbacks()
+                .into_iter()
+                .map(|(callsign, variant)| (callsign.to_string(), variant))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            test("baw012"),
+            vec![(S!("BAW12"), CallsignVariant::Normalised)]
+        );
+        assert_eq!(
+            test("BAW12A"),
+            vec![(S!("BAW12"), CallsignVariant::BaseFlight)]
+        );
+        assert_eq!(
+            test("EZY045KP"),
+            vec![
+                (S!("EZY45KP"), CallsignVariant::Normalised),
+                (S!("EZY45"), CallsignVariant::BaseFlight)
+            ]
+        );
+        assert_eq!(
+            test("BA0012"),
+            vec![(S!("BA12"), CallsignVariant::Normalised)]
+        );
+        match &Callsign::validate("BA012").unwrap().fallbacks()[0].0 {
+            Callsign::Iata(x) => assert_eq!(x, &(S!("BA"), S!("12"))),
+            _ => unreachable!(),
+        }
+
+        // Nothing to normalise
+        assert!(test("BAW12").is_empty());
+        assert!(test("BAW000").is_empty());
+        assert!(test("BAW0A").is_empty());
+        assert!(test("BAWABC").is_empty());
+        assert!(test("BAW1A2").is_empty());
+        assert!(test("N1111").is_empty());
+    }
+
+    #[test]
+    fn mod_api_input_airport_code_ok() {
+        let result = AirportCode::validate("egll");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), AirportCode::Icao(S!("EGLL")));
+
+        let result = AirportCode::validate("lhr");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), AirportCode::Iata(S!("LHR")));
+
+        let result = AirportCode::validate("K1g4");
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), AirportCode::Icao(S!("K1G4")));
+    }
+
+    #[test]
+    fn mod_api_input_airport_code_err() {
+        let test = |input: &str| {
+            let result = AirportCode::validate(input);
+            assert!(result.is_err());
+            match result.unwrap_err() {
+                AppError::Airport(err) => assert_eq!(err, input.to_uppercase()),
+                _ => unreachable!(),
+            };
+        };
+
+        // Empty
+        test("");
+        // Too short
+        test("eg");
+        // Too long
+        test("egllx");
+        // contains invalid char
+        test("eg$l");
+    }
+
     #[test]
     fn mod_api_input_n_number_ok() {
         let test = |input: &str| {
@@ -463,4 +919,223 @@ mod tests {
         test(r"!abc\");
         test("[cbd]");
     }
+
+    #[test]
+    fn mod_api_input_aircraft_search_batch_ok() {
+        let input = ["a1b2c3", "G-ABCD", "A1B2C3", "g-abcd", "N12345"].map(String::from);
+        let result = AircraftSearchBatch::validate(&input).unwrap();
+        assert_eq!(
+            result.0,
+            vec![
+                AircraftSearch::ModeS(ModeS(S!("A1B2C3"))),
+                AircraftSearch::Registration(Registration(S!("G-ABCD"))),
+                AircraftSearch::Registration(Registration(S!("N12345"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn mod_api_input_aircraft_search_batch_err() {
+        let result = AircraftSearchBatch::validate(&[]);
+        assert!(matches!(result.unwrap_err(), AppError::Body(_)));
+
+        let input = vec![S!("a1b2c3"); BATCH_MAX + 1];
+        let result = AircraftSearchBatch::validate(&input);
+        assert!(matches!(result.unwrap_err(), AppError::Body(_)));
+
+        let input = [S!("a1b2c3"), S!("abc?/")];
+        match AircraftSearchBatch::validate(&input).unwrap_err() {
+            AppError::AircraftSearch(err) => assert_eq!(err, "abc?/"),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn mod_api_input_callsign_batch() {
+        let result = CallsignBatch::validate(vec![]);
+        assert!(matches!(result.unwrap_err(), AppError::Body(_)));
+
+        let result = CallsignBatch::validate(vec![S!("BAW123"); BATCH_MAX + 1]);
+        assert!(matches!(result.unwrap_err(), AppError::Body(_)));
+
+        let input = ["baw123", "BAW123", "ba123", "abc"].map(String::from);
+        let result = CallsignBatch::validate(input.to_vec()).unwrap();
+        assert_eq!(result.0.len(), 4);
+        match &result.0[3].1 {
+            Err(AppError::Callsign(err)) => assert_eq!(err, "ABC"),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            result.callsigns(),
+            vec![
+                Callsign::Icao((S!("BAW"), S!("123"))),
+                Callsign::Iata((S!("BA"), S!("123")))
+            ]
+        );
+    }
+
+    #[test]
+    fn mod_api_input_route_direction() {
+        assert_eq!(
+            RouteDirection::validate("origin").unwrap(),
+            RouteDirection::Origin
+        );
+        assert_eq!(
+            RouteDirection::validate("DESTINATION").unwrap(),
+            RouteDirection::Destination
+        );
+        assert_eq!(
+            RouteDirection::validate("any").unwrap(),
+            RouteDirection::Any
+        );
+        assert_eq!(RouteDirection::default(), RouteDirection::Any);
+        match RouteDirection::validate("midpoint").unwrap_err() {
+            AppError::Query(err) => assert_eq!(err, "direction midpoint"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn mod_api_input_response_format() {
+        let extract = |uri: &str, accept: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(uri);
+            if let Some(accept) = accept {
+                request = request.header(axum::http::header::ACCEPT, accept);
+            }
+            request.body(()).unwrap().into_parts().0
+        };
+
+        let mut parts = extract("/callsign/BAW1", None);
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        let mut parts = extract("/callsign/BAW1?format=GeoJSON", None);
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::GeoJson);
+
+        let mut parts = extract("/callsign/BAW1", Some("application/geo+json, */*"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::GeoJson);
+
+        let mut parts = extract("/stats", Some("text/csv; charset=utf-8"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Csv);
+
+        let mut parts = extract("/stats", Some("application/x-msgpack"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::MessagePack);
+
+        let mut parts = extract("/stats?format=msgpack", None);
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::MessagePack);
+
+        // First known media type is used
+        let mut parts = extract("/stats", Some("text/html, application/json, text/csv"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        // Highest q-value is used, and a q-value of 0 is never used
+        let mut parts = extract("/stats", Some("text/csv;q=0.1, application/json"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        let mut parts = extract(
+            "/stats",
+            Some("application/json; q=0.5, application/msgpack;q=0.9, text/csv;q=0.9"),
+        );
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::MessagePack);
+
+        let mut parts = extract("/stats", Some("text/csv;q=0"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        let mut parts = extract("/stats", Some("text/html, */*"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        // Query param takes precedence over the Accept header
+        let mut parts = extract("/callsign/BAW1?format=json", Some("application/geo+json"));
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        assert_eq!(result.unwrap(), ResponseFormat::Json);
+
+        let mut parts = extract("/callsign/BAW1?format=kml", None);
+        let result = ResponseFormat::from_request_parts(&mut parts, &()).await;
+        match result.unwrap_err() {
+            AppError::Query(err) => assert_eq!(err, "format kml"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn mod_api_input_nearest_airports_ok() {
+        let queries = |x: &[(&str, &str)]| {
+            x.iter()
+                .map(|(k, v)| (S!(*k), S!(*v)))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let result = NearestAirports::validate(&queries(&[("lat", "51.47"), ("lon", "-0.46")]));
+        assert_eq!(
+            result.unwrap(),
+            NearestAirports {
+                coordinate: Coordinate::new(51.47, -0.46),
+                radius_km: NEAREST_RADIUS_KM_DEFAULT,
+                limit: NEAREST_LIMIT_DEFAULT,
+                airport_type: None,
+            }
+        );
+
+        let result = NearestAirports::validate(&queries(&[
+            ("lat", "-90"),
+            ("lon", "180"),
+            ("radius_km", "500"),
+            ("limit", "1"),
+            ("airport_type", "LARGE_AIRPORT"),
+        ]));
+        assert_eq!(
+            result.unwrap(),
+            NearestAirports {
+                coordinate: Coordinate::new(-90.0, 180.0),
+                radius_km: 500.0,
+                limit: 1,
+                airport_type: Some(AirportType::LargeAirport),
+            }
+        );
+    }
+
+    #[test]
+    fn mod_api_input_nearest_airports_err() {
+        let test = |x: &[(&str, &str)], expected: &str| {
+            let queries = x
+                .iter()
+                .map(|(k, v)| (S!(*k), S!(*v)))
+                .collect::<HashMap<_, _>>();
+            match NearestAirports::validate(&queries).unwrap_err() {
+                AppError::Query(err) => assert_eq!(err, expected),
+                _ => unreachable!(),
+            }
+        };
+
+        test(&[("lon", "0")], "lat missing");
+        test(&[("lat", "0")], "lon missing");
+        test(&[("lat", "90.1"), ("lon", "0")], "lat 90.1");
+        test(&[("lat", "0"), ("lon", "-180.1")], "lon -180.1");
+        test(&[("lat", "abc"), ("lon", "0")], "lat abc");
+        test(&[("lat", "NaN"), ("lon", "0")], "lat NaN");
+        test(
+            &[("lat", "0"), ("lon", "0"), ("radius_km", "501")],
+            "radius_km 501",
+        );
+        test(
+            &[("lat", "0"), ("lon", "0"), ("radius_km", "-1")],
+            "radius_km -1",
+        );
+        test(&[("lat", "0"), ("lon", "0"), ("limit", "0")], "limit 0");
+        test(&[("lat", "0"), ("lon", "0"), ("limit", "101")], "limit 101");
+        test(
+            &[("lat", "0"), ("lon", "0"), ("airport_type", "spaceport")],
+            "airport_type spaceport",
+        );
+    }
 }
@@ -7,10 +7,11 @@ use axum::{
     Router,
     body::Body,
     extract::{ConnectInfo, DefaultBodyLimit, State},
+    handler::Handler,
     http::Request,
     middleware::{self, Next},
     response::Response,
-    routing::{get, patch},
+    routing::{MethodRouter, get, patch, post},
 };
 use std::{
     net::{IpAddr, SocketAddr, ToSocketAddrs},
@@ -22,20 +23,29 @@ use tower::ServiceBuilder;
 use tracing::info;
 
 mod app_error;
+mod cache_control;
 mod input;
+mod openapi;
+mod photo_cache;
 mod response;
 mod router;
 mod update_routes;
 
 use crate::{
     S,
+    argon::ArgonHash,
     db_postgres::{MsgIncomingRequest, UriMethod},
     db_redis::ratelimit::RateLimit,
     parse_env::AppEnv,
-    scraper::MsgScraper,
+    scraper::{MsgScraper, ScraperShared},
 };
 pub use app_error::*;
-pub use input::{AircraftSearch, AirlineCode, Callsign, ModeS, NNumber, Registration, Validate};
+use cache_control::CacheControl;
+pub use input::{
+    AircraftSearch, AirlineCode, AirportCode, AirportType, Callsign, CallsignVariant, ModeS,
+    NNumber, Registration, ResponseFormat, RouteDirection, Validate,
+};
+use photo_cache::PhotoCache;
 pub use response::{ResponseAircraft, Stats, StatsEntry};
 
 const X_REAL_IP: &str = "x-real-ip";
@@ -47,8 +57,10 @@ pub struct ApplicationState {
     redis: Pool,
     uptime: Instant,
     scraper_tx: async_channel::Sender<MsgScraper>,
+    scraper_shared: ScraperShared,
     stats_tx: async_channel::Sender<MsgIncomingRequest>,
     url_prefix: String,
+    photo_cache: PhotoCache,
 }
 
 impl ApplicationState {
@@ -57,6 +69,7 @@ impl ApplicationState {
         postgres: PgPool,
         redis: Pool,
         scraper_tx: async_channel::Sender<MsgScraper>,
+        scraper_shared: ScraperShared,
         stats_tx: async_channel::Sender<MsgIncomingRequest>,
     ) -> Self {
         Self {
@@ -64,8 +77,13 @@ impl ApplicationState {
             redis,
             uptime: Instant::now(),
             scraper_tx,
+            scraper_shared,
             stats_tx,
             url_prefix: app_env.url_photo_prefix.clone(),
+            photo_cache: PhotoCache::new(
+                &app_env.location_photo_cache,
+                u64::from(app_env.photo_cache_max_mb) * 1024 * 1024,
+            ),
         }
     }
 }
@@ -139,25 +157,90 @@ macro_rules! define_routes {
                 };
                 format!("/{}", route_name)
             }
+
+            #[cfg(test)]
+            fn iter() -> impl Iterator<Item = Self> {
+                [$(Self::$variant,)*].into_iter()
+            }
         }
     };
 }
 
 define_routes!(
     Routes,
+    AircraftBatch => "aircraft",
     AircraftRandom => "aircraft/random",
     Aircraft => "aircraft/{mode_s}",
+    AircraftPhoto => "aircraft/{mode_s}/photo",
+    AircraftPhotoThumbnail => "aircraft/{mode_s}/photo/thumbnail",
     AirlineRandom => "airline/random",
     Airline => "airline/{airline}",
+    AirlineFlightroutes => "airline/{airline}/routes",
+    AirportNearest => "airport/nearest",
+    Airport => "airport/{airport}",
+    AirportFlightroutes => "airport/{airport}/routes",
+    CallsignBatch => "callsign",
     CallsignRandom => "callsign/random",
     Callsign => "callsign/{callsign}",
     Online => "online",
     NNumber => "n-number/{n-number}",
+    OpenApi => "openapi.json",
     ModeS => "mode-s/{mode_s}",
+    ModeSAllocation => "mode-s/{mode_s}/allocation",
+    ModeSRegistration => "mode-s/{mode_s}/registration",
+    RegistrationModeS => "registration/{registration}/mode-s",
+    ScraperPending => "scraper/pending",
+    ScraperPendingId => "scraper/pending/{pending_flightroute_id}",
+    ScraperStatus => "scraper/status",
     Stats => "stats"
 
 );
 
+impl Routes {
+    /// Random, stats, and online change on every request, lookups follow the redis ttl, unless an update route can change them,
+    /// and everything else, such as the uncached route lists, is revalidated with its ETag
+    const fn cache_control(&self) -> CacheControl {
+        match self {
+            Self::AircraftRandom
+            | Self::AirlineRandom
+            | Self::CallsignRandom
+            | Self::Online
+            | Self::ScraperPending
+            | Self::ScraperPendingId
+            | Self::ScraperStatus
+            | Self::Stats => CacheControl::NoStore,
+            Self::Aircraft | Self::Callsign => CacheControl::Updatable,
+            Self::AircraftPhoto
+            | Self::AircraftPhotoThumbnail
+            | Self::Airline
+            | Self::Airport
+            | Self::ModeS
+            | Self::ModeSAllocation
+            | Self::ModeSRegistration
+            | Self::NNumber
+            | Self::RegistrationModeS => CacheControl::Lookup,
+            Self::AircraftBatch
+            | Self::AirlineFlightroutes
+            | Self::AirportFlightroutes
+            | Self::AirportNearest
+            | Self::CallsignBatch
+            | Self::OpenApi => CacheControl::NoCache,
+        }
+    }
+
+    /// GET handler for the route, with Cache-Control & ETag headers
+    fn get<H, T>(&self, handler: H) -> MethodRouter<ApplicationState>
+    where
+        H: Handler<T, ApplicationState>,
+        T: 'static,
+    {
+        get(handler).layer(middleware::from_fn_with_state(
+            self.cache_control(),
+            cache_control::conditional_get,
+        ))
+    }
+}
+
 /// Get an useable axum address, from app_env:host+port
 fn get_addr(app_env: &AppEnv) -> Result<SocketAddr, AppError> {
     match (app_env.api_host.clone(), app_env.api_port).to_socket_addrs() {
@@ -170,72 +253,193 @@ fn get_addr(app_env: &AppEnv) -> Result<SocketAddr, AppError> {
     }
 }
 
-/// Serve the app!
-#[allow(clippy::cognitive_complexity)]
-pub async fn serve(
-    app_env: AppEnv,
-    postgres: PgPool,
-    redis: Pool,
-    tx_scraper: async_channel::Sender<MsgScraper>,
-    tx_stats: async_channel::Sender<MsgIncomingRequest>,
-) -> Result<(), AppError> {
-    let application_state = ApplicationState::new(&app_env, postgres, redis, tx_scraper, tx_stats);
+/// Allow any origin, and any header, but only the given methods
+fn cors(allowed_methods: Vec<axum::http::Method>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_headers(Any)
+        .allow_methods(allowed_methods)
+        .allow_origin(Any)
+}
 
+/// Every route, relative to API_VERSION, the update routes are only registered if there's an update hash
+fn api_router(allow_update: Option<&ArgonHash>) -> Router<ApplicationState> {
     let mut api_router = Router::new()
         .route(
             &Routes::AircraftRandom.addr(),
-            get(ApiRoutes::aircraft_random_get),
+            Routes::AircraftRandom.get(ApiRoutes::aircraft_random_get),
+        )
+        .route(
+            &Routes::Aircraft.addr(),
+            Routes::Aircraft.get(ApiRoutes::aircraft_get),
+        )
+        // Photos are streamed, so set their own Cache-Control & ETag, rather than conditional_get reading the body
+        .route(
+            &Routes::AircraftPhoto.addr(),
+            get(ApiRoutes::aircraft_photo_get),
+        )
+        .route(
+            &Routes::AircraftPhotoThumbnail.addr(),
+            get(ApiRoutes::aircraft_photo_thumbnail_get),
         )
-        .route(&Routes::Aircraft.addr(), get(ApiRoutes::aircraft_get))
         .route(
             &Routes::AirlineRandom.addr(),
-            get(ApiRoutes::airline_random_get),
+            Routes::AirlineRandom.get(ApiRoutes::airline_random_get),
+        )
+        .route(
+            &Routes::Airline.addr(),
+            Routes::Airline.get(ApiRoutes::airline_get),
+        )
+        .route(
+            &Routes::AirlineFlightroutes.addr(),
+            Routes::AirlineFlightroutes.get(ApiRoutes::airline_routes_get),
+        )
+        .route(
+            &Routes::AirportNearest.addr(),
+            Routes::AirportNearest.get(ApiRoutes::airport_nearest_get),
+        )
+        .route(
+            &Routes::Airport.addr(),
+            Routes::Airport.get(ApiRoutes::airport_get),
+        )
+        .route(
+            &Routes::AirportFlightroutes.addr(),
+            Routes::AirportFlightroutes.get(ApiRoutes::airport_routes_get),
         )
-        .route(&Routes::Airline.addr(), get(ApiRoutes::airline_get))
         .route(
             &Routes::CallsignRandom.addr(),
-            get(ApiRoutes::callsign_random_get),
+            Routes::CallsignRandom.get(ApiRoutes::callsign_random_get),
+        )
+        .route(
+            &Routes::Callsign.addr(),
+            Routes::Callsign.get(ApiRoutes::callsign_get),
+        )
+        .route(
+            &Routes::Online.addr(),
+            Routes::Online.get(ApiRoutes::online_get),
+        )
+        .route(
+            &Routes::NNumber.addr(),
+            Routes::NNumber.get(ApiRoutes::n_number_get),
+        )
+        .route(
+            &Routes::OpenApi.addr(),
+            Routes::OpenApi.get(ApiRoutes::openapi_get),
+        )
+        .route(
+            &Routes::ModeS.addr(),
+            Routes::ModeS.get(ApiRoutes::mode_s_get),
+        )
+        .route(
+            &Routes::ModeSAllocation.addr(),
+            Routes::ModeSAllocation.get(ApiRoutes::mode_s_allocation_get),
+        )
+        .route(
+            &Routes::ModeSRegistration.addr(),
+            Routes::ModeSRegistration.get(ApiRoutes::mode_s_registration_get),
+        )
+        .route(
+            &Routes::RegistrationModeS.addr(),
+            Routes::RegistrationModeS.get(ApiRoutes::registration_mode_s_get),
         )
-        .route(&Routes::Callsign.addr(), get(ApiRoutes::callsign_get))
-        .route(&Routes::Online.addr(), get(ApiRoutes::online_get))
-        .route(&Routes::NNumber.addr(), get(ApiRoutes::n_number_get))
-        .route(&Routes::ModeS.addr(), get(ApiRoutes::mode_s_get))
-        .route(&Routes::Stats.addr(), get(ApiRoutes::stats_get));
+        .route(
+            &Routes::Stats.addr(),
+            Routes::Stats.get(ApiRoutes::stats_get),
+        );
+
+    // The batch routes are the only public POST routes, so only their preflight allows POST
+    let batch_router = Router::new()
+        .route(
+            &Routes::AircraftBatch.addr(),
+            post(ApiRoutes::aircraft_batch_post).layer(DefaultBodyLimit::max(4096)),
+        )
+        .route(
+            &Routes::CallsignBatch.addr(),
+            post(ApiRoutes::callsign_batch_post).layer(DefaultBodyLimit::max(4096)),
+        )
+        .layer(cors(vec![axum::http::Method::POST]));
 
     // If .env flag is set, enable update routes
     let mut allowed_methods = vec![axum::http::Method::GET];
-    if let Some(update_hash) = &app_env.allow_update {
+    if let Some(update_hash) = allow_update {
         api_router = api_router
             .route(
                 &Routes::Callsign.addr(),
-                patch(update_routes::callsign_patch).layer(middleware::from_fn_with_state(
+                patch(update_routes::callsign_patch).route_layer(middleware::from_fn_with_state(
                     update_hash.clone(),
                     update_routes::auth_header,
                 )),
             )
             .route(
                 &Routes::Aircraft.addr(),
-                patch(update_routes::aircraft_patch).layer(middleware::from_fn_with_state(
+                patch(update_routes::aircraft_patch).route_layer(middleware::from_fn_with_state(
                     update_hash.clone(),
                     update_routes::auth_header,
                 )),
+            )
+            .route(
+                &Routes::ScraperPending.addr(),
+                Routes::ScraperPending
+                    .get(update_routes::scraper_pending_get)
+                    .route_layer(middleware::from_fn_with_state(
+                        update_hash.clone(),
+                        update_routes::auth_header,
+                    )),
+            )
+            .route(
+                &Routes::ScraperPendingId.addr(),
+                post(update_routes::scraper_pending_post)
+                    .delete(update_routes::scraper_pending_delete)
+                    .route_layer(middleware::from_fn_with_state(
+                        update_hash.clone(),
+                        update_routes::auth_header,
+                    )),
+            )
+            .route(
+                &Routes::ScraperStatus.addr(),
+                Routes::ScraperStatus
+                    .get(update_routes::scraper_status_get)
+                    .route_layer(middleware::from_fn_with_state(
+                        update_hash.clone(),
+                        update_routes::auth_header,
+                    )),
             );
         allowed_methods.push(axum::http::Method::PATCH);
+        allowed_methods.push(axum::http::Method::POST);
+        allowed_methods.push(axum::http::Method::DELETE);
     }
 
-    let cors = CorsLayer::new()
-        .allow_headers(Any)
-        .allow_methods(allowed_methods)
-        .allow_origin(Any);
+    api_router.layer(cors(allowed_methods)).merge(batch_router)
+}
+
+/// Serve the app!
+#[allow(clippy::cognitive_complexity)]
+pub async fn serve(
+    app_env: AppEnv,
+    postgres: PgPool,
+    redis: Pool,
+    tx_scraper: async_channel::Sender<MsgScraper>,
+    scraper_shared: ScraperShared,
+    tx_stats: async_channel::Sender<MsgIncomingRequest>,
+) -> Result<(), AppError> {
+    let application_state = ApplicationState::new(
+        &app_env,
+        postgres,
+        redis,
+        tx_scraper,
+        scraper_shared,
+        tx_stats,
+    );
 
     let app = Router::new()
-        .nest(API_VERSION.as_str(), api_router)
+        .nest(
+            API_VERSION.as_str(),
+            api_router(app_env.allow_update.as_ref()),
+        )
         .fallback(ApiRoutes::fallback)
         .with_state(application_state.clone())
         .layer(
             ServiceBuilder::new()
                 .layer(DefaultBodyLimit::max(1024))
-                .layer(cors)
                 .layer(middleware::from_fn_with_state(
                     application_state.clone(),
                     insert_stats,
@@ -303,12 +507,12 @@ pub mod tests {
     use crate::db_redis;
     use crate::parse_env;
     use crate::start_incoming_requests;
-    use crate::start_scraper;
+    use crate::{scraper::Sources, start_scraper};
 
     use fred::interfaces::ClientLike;
     use fred::interfaces::KeysInterface;
     use fred::prelude::HashesInterface;
-    use reqwest::StatusCode;
+    use reqwest::{StatusCode, header};
     use serde::{Deserialize, Serialize};
     use serde_json::Value;
     use tokio::task::JoinHandle;
@@ -380,16 +584,24 @@ pub mod tests {
         let setup = test_setup().await;
 
         let postgres = setup.postgres.clone();
-        let app_env = setup.app_env.clone();
+        let mut app_env = setup.app_env.clone();
         let redis = setup.redis.clone();
 
         // need to set up scrapers here
-        let tx_scraper = start_scraper(&app_env).await.unwrap();
+        let sources = Sources::mock(&mut app_env).await;
+        let (tx_scraper, scraper_shared) = start_scraper(&app_env, sources).await.unwrap();
         let tx_stats = start_incoming_requests(&app_env).await.unwrap();
         let handle = tokio::spawn(async {
-            serve(app_env, postgres, redis, tx_scraper, tx_stats)
-                .await
-                .unwrap();
+            serve(
+                app_env,
+                postgres,
+                redis,
+                tx_scraper,
+                scraper_shared,
+                tx_stats,
+            )
+            .await
+            .unwrap();
         });
         // just sleep to make sure the server is running - 1ms is enough
         // Will seed that stats in redis, so now need 100ms
@@ -978,6 +1190,36 @@ pub mod tests {
         assert_eq!(result["url_photo_thumbnail"].to_string(), "null");
     }
 
+    #[tokio::test]
+    /// Aircraft and callsigns can be changed by the update routes, so are only re-used briefly, and then revalidated with the ETag,
+    /// whereas an airline is re-used for as long as it would live in redis
+    async fn http_mod_get_cache_control() {
+        start_server().await;
+        for (path, cache_control) in [
+            ("aircraft/4CABD2", "public, max-age=300"),
+            ("callsign/ACA959", "public, max-age=300"),
+            ("airline/ACA", "public, max-age=604800"),
+        ] {
+            let url = format!("http://127.0.0.1:8282{}/{path}", API_VERSION.as_str());
+            let resp = CLIENT.get(&url).send().await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(header::CACHE_CONTROL).unwrap(),
+                cache_control,
+                "{path}"
+            );
+            let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+            let resp = CLIENT
+                .get(&url)
+                .header(header::IF_NONE_MATCH, etag)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED, "{path}");
+        }
+    }
+
     #[tokio::test]
     // search via registration when theres no flag
     async fn http_mod_get_aircraft_registration() {
@@ -1392,7 +1634,8 @@ pub mod tests {
     #[tokio::test]
     async fn http_mod_get_aircraft_unknown() {
         start_server().await;
-        let mode_s = "ABABAB";
+        // Unallocated, so can't be derived
+        let mode_s = "F8ABAB";
         let url = format!(
             "http://127.0.0.1:8282{}/aircraft/{}",
             API_VERSION.as_str(),
@@ -1404,6 +1647,90 @@ pub mod tests {
         assert_eq!(result, "unknown aircraft");
     }
 
+    #[tokio::test]
+    /// Unknown, but convertible, mode_s and registration return a derived aircraft, both before and after the unknown is cached
+    async fn http_mod_get_aircraft_derived() {
+        start_server().await;
+        for search in ["ABABAB", "N851GF", "ABABAB", "N851GF"] {
+            let url = format!(
+                "http://127.0.0.1:8282{}/aircraft/{search}",
+                API_VERSION.as_str(),
+            );
+            let resp = CLIENT.get(url).send().await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let result = resp.json::<TestResponseValue>().await.unwrap().response;
+            let result = result.get("aircraft").unwrap();
+            assert_eq!(result["mode_s"], "ABABAB");
+            assert_eq!(result["registration"], "N851GF");
+            assert_eq!(result["registered_owner_country_iso_name"], "US");
+            assert_eq!(result["registered_owner"], Value::Null);
+            assert_eq!(result["photos"], serde_json::json!([]));
+            assert_eq!(result["derived"], true);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_mod_get_aircraft_photo_unknown() {
+        start_server().await;
+        for (mode_s, expected) in [("F8ABAB", "unknown aircraft"), ("4CABD2", "unknown photo")] {
+            for route in ["photo", "photo/thumbnail"] {
+                let url = format!(
+                    "http://127.0.0.1:8282{}/aircraft/{mode_s}/{route}",
+                    API_VERSION.as_str(),
+                );
+                let resp = CLIENT.get(url).send().await.unwrap();
+                assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+                let result = resp.json::<TestResponseValue>().await.unwrap().response;
+                assert_eq!(result, expected);
+            }
+        }
+    }
+
+    #[tokio::test]
+    /// Photo, and thumbnail, proxied from the mock upstream, then answered with a 304 when the ETag matches
+    async fn http_mod_get_aircraft_photo() {
+        let setup = start_server().await;
+        crate::scraper::tests::remove_scraped_data(&setup.postgres).await;
+        for route in ["photo", "photo/thumbnail"] {
+            let url = format!(
+                "http://127.0.0.1:8282{}/aircraft/393C00/{route}",
+                API_VERSION.as_str(),
+            );
+            let resp = CLIENT.get(&url).send().await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let headers = resp.headers().clone();
+            assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "image/jpeg");
+            assert_eq!(
+                headers.get(header::CACHE_CONTROL).unwrap(),
+                "public, max-age=604800"
+            );
+            assert!(headers.get(header::VARY).is_none());
+            let etag = headers.get(header::ETAG).unwrap().to_str().unwrap();
+            assert_eq!(etag.len(), 66);
+            assert!(etag.starts_with('"') && etag.ends_with('"'));
+            assert_eq!(
+                resp.bytes().await.unwrap().as_ref(),
+                crate::scraper::mock_upstream::PHOTO_BYTES
+            );
+
+            let resp = CLIENT
+                .get(&url)
+                .header(header::IF_NONE_MATCH, etag)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag);
+            assert_eq!(
+                resp.headers().get(header::CACHE_CONTROL).unwrap(),
+                "public, max-age=604800"
+            );
+            assert!(resp.bytes().await.unwrap().is_empty());
+        }
+        crate::scraper::tests::remove_scraped_data(&setup.postgres).await;
+    }
+
     #[tokio::test]
     /// /aircraft/random, check response, check cache, clear cache then check against a /aircraft/{mode_s/registration} response
     async fn http_mod_get_aircraft_random() {
@@ -1675,6 +2002,39 @@ pub mod tests {
         assert_eq!(result, format!("unknown endpoint: {version}/{rand_route}"));
     }
 
+    #[tokio::test]
+    // Only the batch routes allow a POST preflight
+    async fn http_mod_cors_preflight() {
+        start_server().await;
+        let version = API_VERSION.as_str();
+
+        for (route, expected) in [
+            ("aircraft", "POST"),
+            ("callsign", "POST"),
+            ("aircraft/A44F3B", "GET"),
+            ("callsign/ANA460", "GET"),
+        ] {
+            let resp = CLIENT
+                .request(
+                    reqwest::Method::OPTIONS,
+                    format!("http://127.0.0.1:8282{version}/{route}"),
+                )
+                .header(header::ORIGIN, "https://www.example.com")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, expected)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers()
+                    .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                    .unwrap(),
+                expected,
+                "{route}"
+            );
+        }
+    }
+
     #[tokio::test]
     // Not rate limited, but rate limit points = number of requests, and ttl 60
     async fn http_mod_rate_limit() {
@@ -0,0 +1,562 @@
+// OpenAPI 3 specification of every route, served at /openapi.json
+// The handlers are associated functions of ApiRoutes, which `utoipa::path` can't be applied to,
+// so each route is described by a stub function here, named after its handler, using the real response types
+#![allow(dead_code)]
+
+use std::{collections::BTreeMap, sync::LazyLock};
+
+use utoipa::{
+    IntoResponses, Modify, OpenApi,
+    openapi::{
+        self, Server,
+        security::{ApiKey, ApiKeyValue, SecurityScheme},
+    },
+};
+
+use super::{
+    API_VERSION,
+    response::{
+        AircraftAndRoute, AirlineFlightroutes, Airport, CallsignResult, FeatureCollection,
+        FlightroutePage, NearestAirport, Online, ResponseAircraft, ResponseAirline,
+        ResponseAllocation, ResponseJson, ResponsePendingFlightroute, Stats, UnknownAircraftJson,
+    },
+    update_routes::{AcceptedPending, UpdatedCallsign},
+};
+use crate::scraper::ScraperStatus;
+
+/// Errors that any route can return, as created by AppError::into_response, each route documents its own 404
+#[derive(IntoResponses)]
+enum Errors {
+    /// Invalid path param, query param, or body, e.g. `invalid callsign: ABC`
+    #[response(status = 400)]
+    BadRequest(#[to_schema] ResponseJson<String>),
+    /// Too many requests, e.g. `rate limited for 60 seconds`
+    #[response(status = 429)]
+    RateLimited(#[to_schema] ResponseJson<String>),
+    /// Internal error
+    #[response(status = 500)]
+    Internal(#[to_schema] ResponseJson<String>),
+}
+
+#[utoipa::path(post, path = "/aircraft", tag = "aircraft",
+    description = "Search for up to 100 aircraft, each input is a key in the response, with a value of either the aircraft, or null",
+    request_body = Vec<String>,
+    params(("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<BTreeMap<String, Option<ResponseAircraft>>> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn aircraft_batch_post() {}
+
+#[utoipa::path(get, path = "/aircraft/random", tag = "aircraft",
+    params(("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<AircraftAndRoute> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn aircraft_random_get() {}
+
+#[utoipa::path(get, path = "/aircraft/{mode_s}", tag = "aircraft",
+    description = "An unknown aircraft, whose registration can be derived from the mode_s, or vice versa, is returned as a partial derived aircraft",
+    params(
+        ("mode_s" = String, Path, description = "Mode S, or registration, of the aircraft"),
+        ("callsign" = Option<String>, Query, description = "Also include the flightroute of this callsign"),
+        ("format" = Option<String>, Query, description = "json, geojson, csv, or msgpack, can also be set with the Accept header"),
+    ),
+    responses(
+        (status = 200, content(
+            (ResponseJson<AircraftAndRoute> = "application/json"),
+            (FeatureCollection = "application/geo+json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        (status = 404, body = UnknownAircraftJson, description = "Unknown aircraft, allocation is only included when searched by mode_s"),
+        Errors
+    )
+)]
+fn aircraft_get() {}
+
+#[utoipa::path(get, path = "/aircraft/{mode_s}/photo", tag = "aircraft",
+    description = "The aircraft's photo, proxied through adsbdb, and cached on disk",
+    params(("mode_s" = String, Path)),
+    responses(
+        (status = 200, description = "Photo bytes, with the content type of the photo", content(
+            ("image/jpeg"),
+            ("image/png"),
+            ("image/gif"),
+            ("image/webp"),
+        )),
+        (status = 404, body = ResponseJson<String>, description = "Unknown aircraft, or photo"),
+        (status = 502, body = ResponseJson<String>, description = "Photo couldn't be requested from the third party host"),
+        Errors
+    )
+)]
+fn aircraft_photo_get() {}
+
+#[utoipa::path(get, path = "/aircraft/{mode_s}/photo/thumbnail", tag = "aircraft",
+    description = "The aircraft's photo thumbnail, proxied through adsbdb, and cached on disk",
+    params(("mode_s" = String, Path)),
+    responses(
+        (status = 200, description = "Thumbnail bytes, with the content type of the thumbnail", content(
+            ("image/jpeg"),
+            ("image/png"),
+            ("image/gif"),
+            ("image/webp"),
+        )),
+        (status = 404, body = ResponseJson<String>, description = "Unknown aircraft, or photo"),
+        (status = 502, body = ResponseJson<String>, description = "Thumbnail couldn't be requested from the third party host"),
+        Errors
+    )
+)]
+fn aircraft_photo_thumbnail_get() {}
+
+#[utoipa::path(patch, path = "/aircraft/{mode_s}", tag = "update",
+    description = "Only available when the updater is enabled",
+    params(("mode_s" = String, Path)),
+    request_body = ResponseAircraft,
+    security(("authorization" = [])),
+    responses(
+        (status = 200),
+        (status = 401, body = ResponseJson<String>),
+        (status = 404, body = UnknownAircraftJson),
+        Errors
+    )
+)]
+fn aircraft_patch() {}
+
+#[utoipa::path(get, path = "/airline/random", tag = "airline",
+    params(("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<Vec<ResponseAirline>> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn airline_random_get() {}
+
+#[utoipa::path(get, path = "/airline/{airline}", tag = "airline",
+    params(
+        ("airline" = String, Path, description = "ICAO or IATA airline code"),
+        ("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header"),
+    ),
+    responses(
+        (status = 200, content(
+            (ResponseJson<Vec<ResponseAirline>> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        (status = 404, body = ResponseJson<String>, description = "Unknown airline"),
+        Errors
+    )
+)]
+fn airline_get() {}
+
+#[utoipa::path(get, path = "/airline/{airline}/routes", tag = "airline",
+    params(
+        ("airline" = String, Path, description = "ICAO or IATA airline code"),
+        ("format" = Option<String>, Query, description = "json, or geojson for a FeatureCollection of route lines"),
+        ("cursor" = Option<i64>, Query, description = "next_cursor of the previous page"),
+    ),
+    responses(
+        (status = 200, body = ResponseJson<AirlineFlightroutes>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown airline"),
+        Errors
+    )
+)]
+fn airline_routes_get() {}
+
+#[utoipa::path(get, path = "/airport/nearest", tag = "airport",
+    params(
+        ("lat" = f64, Query),
+        ("lon" = f64, Query),
+        ("radius_km" = Option<f64>, Query, description = "Default 50, max 500"),
+        ("limit" = Option<usize>, Query, description = "Default 10, max 100"),
+        ("airport_type" = Option<String>, Query, description = "balloonport, closed, heliport, large_airport, medium_airport, seaplane_base, or small_airport"),
+    ),
+    responses((status = 200, body = ResponseJson<Vec<NearestAirport>>), Errors)
+)]
+fn airport_nearest_get() {}
+
+#[utoipa::path(get, path = "/airport/{airport}", tag = "airport",
+    params(("airport" = String, Path, description = "ICAO or IATA airport code")),
+    responses(
+        (status = 200, body = ResponseJson<Airport>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown airport"),
+        Errors
+    )
+)]
+fn airport_get() {}
+
+#[utoipa::path(get, path = "/airport/{airport}/routes", tag = "airport",
+    params(
+        ("airport" = String, Path, description = "ICAO or IATA airport code"),
+        ("direction" = Option<String>, Query, description = "origin, destination, or any"),
+        ("cursor" = Option<i64>, Query, description = "next_cursor of the previous page"),
+    ),
+    responses(
+        (status = 200, body = ResponseJson<FlightroutePage>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown airport"),
+        Errors
+    )
+)]
+fn airport_routes_get() {}
+
+#[utoipa::path(post, path = "/callsign", tag = "callsign",
+    description = "Search for up to 100 flightroutes, each input is a key in the response, with a value of either the flightroute, or an error",
+    request_body = Vec<String>,
+    params(("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<BTreeMap<String, CallsignResult>> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn callsign_batch_post() {}
+
+#[utoipa::path(get, path = "/callsign/random", tag = "callsign",
+    params(("format" = Option<String>, Query, description = "json, geojson, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<AircraftAndRoute> = "application/json"),
+            (FeatureCollection = "application/geo+json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn callsign_random_get() {}
+
+#[utoipa::path(get, path = "/callsign/{callsign}", tag = "callsign",
+    params(
+        ("callsign" = String, Path),
+        ("format" = Option<String>, Query, description = "json, geojson, csv, or msgpack, can also be set with the Accept header"),
+    ),
+    responses(
+        (status = 200, content(
+            (ResponseJson<AircraftAndRoute> = "application/json"),
+            (FeatureCollection = "application/geo+json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        (status = 404, body = ResponseJson<String>, description = "Unknown callsign"),
+        Errors
+    )
+)]
+fn callsign_get() {}
+
+#[utoipa::path(patch, path = "/callsign/{callsign}", tag = "update",
+    description = "Only available when the updater is enabled",
+    params(("callsign" = String, Path)),
+    request_body = UpdatedCallsign,
+    security(("authorization" = [])),
+    responses(
+        (status = 200),
+        (status = 401, body = ResponseJson<String>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown callsign, or airport"),
+        Errors
+    )
+)]
+fn callsign_patch() {}
+
+#[utoipa::path(get, path = "/online", tag = "meta",
+    responses((status = 200, body = ResponseJson<Online>), Errors)
+)]
+fn online_get() {}
+
+#[utoipa::path(get, path = "/openapi.json", tag = "meta",
+    description = "This specification",
+    responses((status = 200, content_type = "application/json", description = "OpenAPI 3 specification"), Errors)
+)]
+fn openapi_get() {}
+
+#[utoipa::path(get, path = "/n-number/{n-number}", tag = "conversion",
+    params(("n-number" = String, Path)),
+    responses((status = 200, body = ResponseJson<String>, description = "Mode S, or an empty string"), Errors)
+)]
+fn n_number_get() {}
+
+#[utoipa::path(get, path = "/mode-s/{mode_s}", tag = "conversion",
+    params(("mode_s" = String, Path)),
+    responses((status = 200, body = ResponseJson<String>, description = "N-Number, or an empty string"), Errors)
+)]
+fn mode_s_get() {}
+
+#[utoipa::path(get, path = "/mode-s/{mode_s}/allocation", tag = "conversion",
+    params(("mode_s" = String, Path)),
+    responses((status = 200, body = ResponseJson<ResponseAllocation>), Errors)
+)]
+fn mode_s_allocation_get() {}
+
+#[utoipa::path(get, path = "/mode-s/{mode_s}/registration", tag = "conversion",
+    params(("mode_s" = String, Path)),
+    responses((status = 200, body = ResponseJson<String>, description = "Registration, or an empty string"), Errors)
+)]
+fn mode_s_registration_get() {}
+
+#[utoipa::path(get, path = "/registration/{registration}/mode-s", tag = "conversion",
+    params(("registration" = String, Path)),
+    responses((status = 200, body = ResponseJson<String>, description = "Mode S, or an empty string"), Errors)
+)]
+fn registration_mode_s_get() {}
+
+#[utoipa::path(get, path = "/scraper/pending", tag = "update",
+    description = "Scraped flightroutes that couldn't be inserted, waiting to be accepted or rejected. Only available when the updater is enabled",
+    security(("authorization" = [])),
+    responses(
+        (status = 200, body = ResponseJson<Vec<ResponsePendingFlightroute>>),
+        (status = 401, body = ResponseJson<String>),
+        Errors
+    )
+)]
+fn scraper_pending_get() {}
+
+#[utoipa::path(post, path = "/scraper/pending/{pending_flightroute_id}", tag = "update",
+    description = "Accept, and insert, a pending flightroute, once the missing airline or airport has been inserted. The optional body replaces the scraped airports, e.g. to correct an airport clash. Only available when the updater is enabled",
+    params(("pending_flightroute_id" = i64, Path)),
+    request_body = Option<AcceptedPending>,
+    security(("authorization" = [])),
+    responses(
+        (status = 200),
+        (status = 401, body = ResponseJson<String>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown pending flightroute, airline, or airport"),
+        Errors
+    )
+)]
+fn scraper_pending_post() {}
+
+#[utoipa::path(delete, path = "/scraper/pending/{pending_flightroute_id}", tag = "update",
+    description = "Reject a pending flightroute, it's only pending again if scraped with different airports. Only available when the updater is enabled",
+    params(("pending_flightroute_id" = i64, Path)),
+    security(("authorization" = [])),
+    responses(
+        (status = 200),
+        (status = 401, body = ResponseJson<String>),
+        (status = 404, body = ResponseJson<String>, description = "Unknown pending flightroute"),
+        Errors
+    )
+)]
+fn scraper_pending_delete() {}
+
+#[utoipa::path(get, path = "/scraper/status", tag = "update",
+    description = "In-flight searches, queue depth, and counters since start, of the scraper. Only available when the updater is enabled",
+    security(("authorization" = [])),
+    responses(
+        (status = 200, body = ResponseJson<ScraperStatus>),
+        (status = 401, body = ResponseJson<String>),
+        Errors
+    )
+)]
+fn scraper_status_get() {}
+
+#[utoipa::path(get, path = "/stats", tag = "meta",
+    params(("format" = Option<String>, Query, description = "json, csv, or msgpack, can also be set with the Accept header")),
+    responses(
+        (status = 200, content(
+            (ResponseJson<Stats> = "application/json"),
+            ("text/csv"),
+            ("application/msgpack"),
+        )),
+        Errors
+    )
+)]
+fn stats_get() {}
+
+/// Set the server to the versioned api path, and add the Authorization header used by the update routes
+struct Setup;
+
+impl Modify for Setup {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        openapi.servers = Some(vec![Server::new(API_VERSION.as_str())]);
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "authorization",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Authorization"))),
+            );
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "adsbdb", description = "public aircraft, airline, and flightroute api"),
+    modifiers(&Setup),
+    paths(
+        aircraft_batch_post,
+        aircraft_random_get,
+        aircraft_get,
+        aircraft_photo_get,
+        aircraft_photo_thumbnail_get,
+        aircraft_patch,
+        airline_random_get,
+        airline_get,
+        airline_routes_get,
+        airport_nearest_get,
+        airport_get,
+        airport_routes_get,
+        callsign_batch_post,
+        callsign_random_get,
+        callsign_get,
+        callsign_patch,
+        online_get,
+        openapi_get,
+        n_number_get,
+        mode_s_get,
+        mode_s_allocation_get,
+        mode_s_registration_get,
+        registration_mode_s_get,
+        scraper_pending_get,
+        scraper_pending_post,
+        scraper_pending_delete,
+        scraper_status_get,
+        stats_get,
+    )
+)]
+struct ApiDoc;
+
+pub static OPENAPI: LazyLock<openapi::OpenApi> = LazyLock::new(ApiDoc::openapi);
+
+/// cargo watch -q -c -w src/ -x 'test mod_api_openapi -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode, header},
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        S,
+        api::{ApplicationState, PhotoCache, Routes, api_router},
+        argon::ArgonHash,
+        scraper::ScraperShared,
+    };
+
+    /// Application state whose pools never connect, as every request is answered by the router before reaching a handler
+    fn unconnected_state() -> ApplicationState {
+        let (scraper_tx, _) = async_channel::bounded(1);
+        let (stats_tx, _) = async_channel::bounded(1);
+        ApplicationState {
+            postgres: sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy("postgres://localhost")
+                .unwrap(),
+            redis: fred::types::Builder::default_centralized()
+                .build_pool(1)
+                .unwrap(),
+            uptime: std::time::Instant::now(),
+            scraper_tx,
+            scraper_shared: ScraperShared::default(),
+            stats_tx,
+            url_prefix: S!(),
+            photo_cache: PhotoCache::new(std::env::temp_dir().join("adsbdb_mod_api_openapi"), 0),
+        }
+    }
+
+    #[tokio::test]
+    /// Every path, and each of its methods, is both documented and registered, with the update routes enabled
+    async fn mod_api_openapi_every_route() {
+        let paths = &OPENAPI.paths.paths;
+        for route in Routes::iter() {
+            assert!(
+                paths.contains_key(&route.addr()),
+                "{} has no OpenAPI entry",
+                route.addr()
+            );
+        }
+        let routes = Routes::iter().map(|i| i.addr()).collect::<Vec<_>>();
+        for path in paths.keys() {
+            assert!(routes.contains(path), "{path} isn't a route");
+        }
+
+        // An unregistered method is answered with every registered method in the Allow header
+        let update_hash = ArgonHash::try_from(S!("$argon2id$v=19$m=4096,t=5,p=1$rahU5enqn3WcOo9A58Ifjw$I+7yA6+29LuB5jzPUwnxtLoH66Lng7ExWqHdivwj8Es")).unwrap();
+        let router = api_router(Some(&update_hash)).with_state(unconnected_state());
+        for (path, item) in paths {
+            let documented = [
+                (Method::GET, &item.get),
+                (Method::POST, &item.post),
+                (Method::PATCH, &item.patch),
+                (Method::DELETE, &item.delete),
+            ]
+            .into_iter()
+            .filter(|(_, operation)| operation.is_some())
+            .map(|(method, _)| method.to_string())
+            .collect::<BTreeSet<_>>();
+
+            let request = Request::builder()
+                .method(Method::TRACE)
+                .uri(path.replace(['{', '}'], ""))
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{path}");
+            let registered = response
+                .headers()
+                .get(header::ALLOW)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .split(',')
+                .map(|method| method.trim().to_owned())
+                .filter(|method| method != "HEAD")
+                .collect::<BTreeSet<_>>();
+            assert_eq!(registered, documented, "{path}");
+        }
+    }
+
+    #[test]
+    fn mod_api_openapi_schemas() {
+        let result = serde_json::to_value(&*OPENAPI).unwrap();
+        assert_eq!(result["openapi"], "3.1.0");
+        assert_eq!(result["info"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(result["servers"][0]["url"], API_VERSION.as_str());
+
+        let schemas = result["components"]["schemas"].as_object().unwrap();
+        for name in [
+            "AircraftAndRoute",
+            "ResponseAircraft",
+            "ResponsePhoto",
+            "ResponsePendingFlightroute",
+            "DerivedAircraft",
+            "ResponseFlightRoute",
+            "Airport",
+            "RouteGeodesy",
+            "CallsignMatch",
+            "Stats",
+            "UnknownAircraftJson",
+        ] {
+            assert!(schemas.contains_key(name), "missing schema {name}");
+        }
+
+        let flightroute = &schemas["ResponseFlightRoute"]["properties"];
+        for field in ["callsign", "callsign_icao", "callsign_iata", "geodesy"] {
+            assert!(flightroute.get(field).is_some(), "missing field {field}");
+        }
+
+        let aircraft = &result["paths"]["/aircraft/{mode_s}"];
+        assert!(aircraft["get"]["responses"]["200"]["content"]["application/geo+json"].is_object());
+        assert!(aircraft["get"]["responses"]["404"].is_object());
+        assert!(aircraft["get"]["responses"]["429"].is_object());
+        assert!(aircraft["patch"]["security"].is_array());
+    }
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use axum::body::Body;
+use reqwest::{Client, Response, header::CONTENT_TYPE};
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+use crate::S;
+
+use super::AppError;
+
+/// Photos larger than this aren't proxied
+const MAX_PHOTO_BYTES: u64 = 10 * 1024 * 1024;
+
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Content types that can be proxied, and the file extension that they're cached with
+const IMAGE_TYPES: [(&str, &str); 4] = [
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+/// Extension of a supported content type, any parameters, e.g. `; charset=binary`, are ignored
+fn extension(content_type: &str) -> Option<&'static str> {
+    let content_type = content_type.split(';').next().unwrap_or_default().trim();
+    IMAGE_TYPES
+        .iter()
+        .find(|(i, _)| i.eq_ignore_ascii_case(content_type))
+        .map(|(_, extension)| *extension)
+}
+
+fn content_type(extension: &str) -> Option<&'static str> {
+    IMAGE_TYPES
+        .iter()
+        .find(|(_, i)| *i == extension)
+        .map(|(content_type, _)| *content_type)
+}
+
+/// Cached file name, without the extension, is the sha256 of the photo url as hex
+fn key(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Strong ETag of a proxied photo, the photo at a url doesn't change, so it's the cache key, rather than a hash of the body
+pub fn etag(url: &str) -> String {
+    format!("\"{}\"", key(url))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    extension: &'static str,
+    size: u64,
+    used: u64,
+}
+
+/// Index of the cached files, each use increments the clock, so the smallest used value is the least recently used
+#[derive(Debug)]
+struct Lru {
+    entries: HashMap<String, Entry>,
+    order: BTreeMap<u64, String>,
+    total: u64,
+    clock: u64,
+    max_bytes: u64,
+}
+
+impl Lru {
+    fn new(max_bytes: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            total: 0,
+            clock: 0,
+            max_bytes,
+        }
+    }
+
+    const fn fits(&self, size: u64) -> bool {
+        size <= self.max_bytes
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Mark as the most recently used, returning the extension of the cached file
+    fn touch(&mut self, key: &str) -> Option<&'static str> {
+        let used = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.used);
+        entry.used = used;
+        self.order.insert(used, key.to_owned());
+        Some(entry.extension)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.used);
+        self.total -= entry.size;
+        Some(entry)
+    }
+
+    /// Insert as the most recently used, returning every key, and extension, evicted to stay under max_bytes, which should be deleted from disk
+    fn insert(
+        &mut self,
+        key: String,
+        extension: &'static str,
+        size: u64,
+    ) -> Vec<(String, &'static str)> {
+        self.remove(&key);
+        let used = self.tick();
+        self.entries.insert(
+            key.clone(),
+            Entry {
+                extension,
+                size,
+                used,
+            },
+        );
+        self.order.insert(used, key);
+        self.total += size;
+
+        let mut evicted = vec![];
+        while self.total > self.max_bytes {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.total -= entry.size;
+                evicted.push((key, entry.extension));
+            }
+        }
+        evicted
+    }
+}
+
+/// A photo, streamed from its file in the cache
+#[derive(Debug)]
+pub struct Photo {
+    pub content_type: &'static str,
+    pub body: Body,
+}
+
+impl Photo {
+    fn new(content_type: &'static str, file: File) -> Self {
+        Self {
+            content_type,
+            body: Body::from_stream(ReaderStream::new(file)),
+        }
+    }
+}
+
+/// Photos requested from the third party host, cached on disk, with the least recently used evicted once the cache is over max_bytes
+#[derive(Debug, Clone)]
+pub struct PhotoCache {
+    dir: PathBuf,
+    client: Client,
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl PhotoCache {
+    /// Create the cache directory, and index any files already cached, with the oldest written as the least recently used.
+    /// Temporary files, left by a download that was interrupted by a restart, are removed.
+    /// Photos are streamed from the directory, so if it can't be created, photos can't be proxied
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        let dir = dir.into();
+        let lru = Lru::new(max_bytes);
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            tracing::error!("can't create photo cache {}: {e}", dir.display());
+        }
+
+        let mut cached = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|file| {
+                let path = file.path();
+                if path.extension().is_some_and(|i| i == "tmp") {
+                    std::fs::remove_file(&path).ok();
+                    return None;
+                }
+                let metadata = file.metadata().ok()?;
+                let extension = path
+                    .extension()
+                    .and_then(|i| i.to_str())
+                    .and_then(content_type)
+                    .and_then(extension)?;
+                let key = path.file_stem()?.to_str()?.to_owned();
+                Some((metadata.modified().ok()?, key, extension, metadata.len()))
+            })
+            .collect::<Vec<_>>();
+        cached.sort();
+
+        let cache = Self {
+            dir,
+            client: Client::builder()
+                .timeout(UPSTREAM_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            lru: Arc::new(Mutex::new(lru)),
+        };
+        for (_, key, extension, size) in cached {
+            let evicted = cache.lock().insert(key, extension, size);
+            cache.delete(evicted);
+        }
+        cache
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        self.lru
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn path(&self, key: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{extension}"))
+    }
+
+    fn delete(&self, evicted: Vec<(String, &'static str)>) {
+        for (key, extension) in evicted {
+            if let Err(e) = std::fs::remove_file(self.path(&key, extension)) {
+                tracing::error!("can't evict cached photo {key}: {e}");
+            }
+        }
+    }
+
+    /// Get the photo from disk, else request it from the host, and write it to disk
+    pub async fn get(&self, url: &str) -> Result<Photo, AppError> {
+        let key = key(url);
+        let cached = self.lock().touch(&key);
+        if let Some(extension) = cached {
+            match File::open(self.path(&key, extension)).await {
+                Ok(file) => {
+                    return Ok(Photo::new(
+                        content_type(extension).unwrap_or_default(),
+                        file,
+                    ));
+                }
+                Err(e) => {
+                    tracing::error!("can't read cached photo {key}: {e}");
+                    self.lock().remove(&key);
+                }
+            }
+        }
+        self.request(url, key).await
+    }
+
+    /// Write the photo from the host to a temporary file, and then rename, so that a partially written photo is never read.
+    /// Each temporary file has a random name, as concurrent requests for the same uncached photo each download it.
+    /// A photo that's too large to cache is still served, from the temporary file, which is removed once opened
+    async fn request(&self, url: &str, key: String) -> Result<Photo, AppError> {
+        let upstream = |e: String| {
+            tracing::error!("{url}: {e}");
+            AppError::Upstream(S!("photo unavailable"))
+        };
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| upstream(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(upstream(format!("status {}", response.status())));
+        }
+        let (content_type, extension) = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|i| i.to_str().ok())
+            .and_then(extension)
+            .and_then(|extension| Some((content_type(extension)?, extension)))
+            .ok_or_else(|| upstream(S!("unsupported content type")))?;
+
+        let path = self.path(&key, extension);
+        let tmp = self
+            .dir
+            .join(format!("{key}.{:016x}.tmp", rand::random::<u64>()));
+        let size = match Self::download(response, &tmp).await {
+            Ok(size) => size,
+            Err(e) => {
+                tokio::fs::remove_file(&tmp).await.ok();
+                return Err(match e {
+                    Download::Upstream(e) => upstream(e),
+                    Download::Io(e) => AppError::Io(e),
+                });
+            }
+        };
+
+        if !self.lock().fits(size) {
+            let file = File::open(&tmp).await;
+            tokio::fs::remove_file(&tmp).await.ok();
+            return Ok(Photo::new(content_type, file?));
+        }
+        if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+            tokio::fs::remove_file(&tmp).await.ok();
+            return Err(AppError::Io(e));
+        }
+        // Opened before being inserted, so that the file is still readable if it's evicted by a concurrent request
+        let file = File::open(&path).await?;
+        let evicted = self.lock().insert(key, extension, size);
+        self.delete(evicted);
+        Ok(Photo::new(content_type, file))
+    }
+
+    /// Write the response to the given file in chunks, so that a photo over MAX_PHOTO_BYTES is rejected before it's been fully read, returns the size
+    async fn download(mut response: Response, path: &PathBuf) -> Result<u64, Download> {
+        let mut file = File::create(path).await.map_err(Download::Io)?;
+        let mut size = 0;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| Download::Upstream(e.to_string()))?
+        {
+            size += chunk.len() as u64;
+            if size > MAX_PHOTO_BYTES {
+                return Err(Download::Upstream(S!("photo too large")));
+            }
+            file.write_all(&chunk).await.map_err(Download::Io)?;
+        }
+        file.flush().await.map_err(Download::Io)?;
+        Ok(size)
+    }
+}
+
+/// Reason a download failed, either the host, or writing to disk
+#[derive(Debug)]
+enum Download {
+    Upstream(String),
+    Io(std::io::Error),
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test mod_api_photo_cache '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        Router,
+        extract::Path,
+        http::{StatusCode, header},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+
+    use super::*;
+
+    const JPEG: &[u8] = &[0xff, 0xd8, 0xff, 0xe0, 0, 0, 0, 0, 0, 0];
+
+    /// A new, empty, cache directory in the system temp directory
+    fn temp_dir() -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("adsbdb_photo_cache_{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Serves a 10 byte jpeg for every name, except `text`, and `missing`
+    async fn start_host() -> SocketAddr {
+        async fn photo(Path(name): Path<String>) -> Response {
+            match name.as_str() {
+                "text" => "not a photo".into_response(),
+                "missing" => StatusCode::NOT_FOUND.into_response(),
+                _ => ([(header::CONTENT_TYPE, "image/jpeg")], JPEG).into_response(),
+            }
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/{name}", get(photo)))
+                .await
+                .unwrap();
+        });
+        addr
+    }
+
+    fn files(dir: &PathBuf) -> usize {
+        std::fs::read_dir(dir).unwrap().count()
+    }
+
+    /// Content type, and the streamed body, of a photo
+    async fn read(photo: Photo) -> (&'static str, Vec<u8>) {
+        let bytes = axum::body::to_bytes(photo.body, usize::MAX).await.unwrap();
+        (photo.content_type, bytes.to_vec())
+    }
+
+    #[test]
+    fn mod_api_photo_cache_content_type() {
+        assert_eq!(extension("image/jpeg"), Some("jpg"));
+        assert_eq!(extension("IMAGE/PNG; charset=binary"), Some("png"));
+        assert_eq!(extension("text/html"), None);
+        assert_eq!(content_type("webp"), Some("image/webp"));
+        assert_eq!(content_type("tmp"), None);
+        assert_eq!(key("https://www.example.com/001/001/example.jpg").len(), 64);
+        assert_eq!(
+            etag("https://www.example.com/001/001/example.jpg"),
+            format!("\"{}\"", key("https://www.example.com/001/001/example.jpg"))
+        );
+    }
+
+    #[test]
+    fn mod_api_photo_cache_lru() {
+        let mut lru = Lru::new(30);
+        assert!(lru.insert(S!("a"), "jpg", 10).is_empty());
+        assert!(lru.insert(S!("b"), "jpg", 10).is_empty());
+        assert!(lru.insert(S!("c"), "png", 10).is_empty());
+        assert_eq!(lru.total, 30);
+
+        // a is now the most recently used, so b is evicted
+        assert_eq!(lru.touch("a"), Some("jpg"));
+        assert_eq!(lru.insert(S!("d"), "jpg", 10), [(S!("b"), "jpg")]);
+        assert_eq!(lru.touch("b"), None);
+
+        // Evicts as many as needed
+        assert_eq!(
+            lru.insert(S!("e"), "jpg", 25),
+            [(S!("c"), "png"), (S!("a"), "jpg"), (S!("d"), "jpg")]
+        );
+        assert_eq!(lru.total, 25);
+
+        // Re-inserting replaces the previous size
+        assert!(lru.insert(S!("e"), "jpg", 5).is_empty());
+        assert_eq!(lru.total, 5);
+        assert_eq!(lru.remove("e").unwrap().size, 5);
+        assert_eq!(lru.total, 0);
+
+        assert!(lru.fits(30));
+        assert!(!lru.fits(31));
+    }
+
+    #[tokio::test]
+    async fn mod_api_photo_cache_get() {
+        let addr = start_host().await;
+        let dir = temp_dir();
+        let cache = PhotoCache::new(&dir, 25);
+
+        let url = format!("http://{addr}/one.jpg");
+        assert_eq!(
+            read(cache.get(&url).await.unwrap()).await,
+            ("image/jpeg", JPEG.to_vec())
+        );
+        assert!(cache.path(&key(&url), "jpg").exists());
+
+        // Served from disk
+        std::fs::write(cache.path(&key(&url), "jpg"), b"cached").unwrap();
+        assert_eq!(
+            read(cache.get(&url).await.unwrap()).await,
+            ("image/jpeg", b"cached".to_vec())
+        );
+
+        // Third photo is over max_bytes, so the least recently used is evicted
+        cache.get(&format!("http://{addr}/two.jpg")).await.unwrap();
+        cache
+            .get(&format!("http://{addr}/three.jpg"))
+            .await
+            .unwrap();
+        assert_eq!(files(&dir), 2);
+        assert!(!cache.path(&key(&url), "jpg").exists());
+
+        // Existing files are indexed on start
+        let cache = PhotoCache::new(&dir, 25);
+        assert_eq!(cache.lock().entries.len(), 2);
+        assert_eq!(cache.lock().total, 20);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    /// Concurrent requests for the same uncached photo don't share a temporary file, and stale temporary files are removed on start
+    async fn mod_api_photo_cache_get_concurrent() {
+        let addr = start_host().await;
+        let dir = temp_dir();
+        std::fs::write(dir.join("stale.0000000000000000.tmp"), b"partial").unwrap();
+        let cache = PhotoCache::new(&dir, 25);
+        assert_eq!(files(&dir), 0);
+
+        let url = format!("http://{addr}/one.jpg");
+        let mut requests = tokio::task::JoinSet::new();
+        for _ in 0..8 {
+            let (cache, url) = (cache.clone(), url.clone());
+            requests.spawn(async move { read(cache.get(&url).await.unwrap()).await });
+        }
+        while let Some(photo) = requests.join_next().await {
+            assert_eq!(photo.unwrap(), ("image/jpeg", JPEG.to_vec()));
+        }
+        assert_eq!(files(&dir), 1);
+        assert!(cache.path(&key(&url), "jpg").exists());
+        assert_eq!(cache.lock().total, 10);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn mod_api_photo_cache_get_invalid() {
+        let addr = start_host().await;
+        let dir = temp_dir();
+        let cache = PhotoCache::new(&dir, 5);
+
+        for name in ["text", "missing"] {
+            let result = cache.get(&format!("http://{addr}/{name}")).await;
+            assert!(matches!(result, Err(AppError::Upstream(_))));
+        }
+        // Unreachable host
+        let result = cache.get("http://127.0.0.1:1/one.jpg").await;
+        assert!(matches!(result, Err(AppError::Upstream(_))));
+
+        // Proxied, but too large to cache
+        let photo = cache.get(&format!("http://{addr}/one.jpg")).await.unwrap();
+        assert_eq!(read(photo).await, ("image/jpeg", JPEG.to_vec()));
+        assert_eq!(files(&dir), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
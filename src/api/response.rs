@@ -1,11 +1,28 @@
-use axum::Json;
+use std::collections::BTreeMap;
+
+use axum::{
+    Json,
+    http::header,
+    response::{IntoResponse, Response},
+};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::db_postgres::{EntryCount, ModelAircraft, ModelAirline, ModelFlightroute};
+use crate::{
+    S,
+    api::{AircraftSearch, AppError, Callsign, CallsignVariant, ModeS, ResponseFormat},
+    db_postgres::{
+        EntryCount, ModelAircraft, ModelAircraftPhoto, ModelAirline, ModelAirport,
+        ModelFlightroute, ModelPendingFlightroute,
+    },
+    geodesy::{self, Coordinate},
+    icao_allocation::{self, AllocationKind},
+    registration_converter::{mode_s_to_registration, registration_to_mode_s},
+};
 
 pub type AsJsonRes<T> = Json<ResponseJson<T>>;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, PartialOrd)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, PartialOrd, ToSchema)]
 pub struct ResponseJson<T> {
     pub response: T,
 }
@@ -16,14 +33,203 @@ impl<T> ResponseJson<T> {
     }
 }
 
+/// Bare GeoJSON FeatureCollection response, an alternative to ResponseJson, for use directly by mapping libraries
+#[derive(Debug)]
+pub struct ResponseGeoJson(pub FeatureCollection);
+
+impl IntoResponse for ResponseGeoJson {
+    fn into_response(self) -> Response {
+        (
+            [(header::CONTENT_TYPE, ResponseFormat::GEOJSON_MIME)],
+            Json(self.0),
+        )
+            .into_response()
+    }
+}
+
+/// Response types that can be serialized as something other than ResponseJson, every type can be serialized as MessagePack
+pub trait Formattable: Serialize {
+    /// A GeoJSON FeatureCollection, if the response has a spatial representation
+    fn to_geojson(&self) -> Option<FeatureCollection> {
+        None
+    }
+
+    /// CSV rows, a response that serializes as an array has one row per item
+    fn to_rows(&self) -> Result<Vec<CsvRow>, AppError> {
+        Ok(match to_value(self)? {
+            serde_json::Value::Array(rows) => rows.into_iter().map(CsvRow::from).collect(),
+            row => vec![CsvRow::from(row)],
+        })
+    }
+}
+
+/// Serialize a response as a serde_json Value, in order to be written as CSV rows
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<serde_json::Value, AppError> {
+    serde_json::to_value(value).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// A single CSV row, the leading columns are kept in order, and are followed by the flattened fields of the value
+#[derive(Debug, Default)]
+pub struct CsvRow {
+    leading: Vec<(&'static str, serde_json::Value)>,
+    value: serde_json::Value,
+}
+
+impl From<serde_json::Value> for CsvRow {
+    fn from(value: serde_json::Value) -> Self {
+        Self {
+            leading: vec![],
+            value,
+        }
+    }
+}
+
+impl CsvRow {
+    pub fn new(value: &impl Serialize) -> Result<Self, AppError> {
+        Ok(Self::from(to_value(value)?))
+    }
+
+    pub fn with(mut self, column: &'static str, value: impl Into<serde_json::Value>) -> Self {
+        self.leading.push((column, value.into()));
+        self
+    }
+
+    /// Column name and cell pairs, nested keys, and array indexes, are joined with a `.`, e.g. `flightroute.origin.icao_code`
+    fn cells(&self) -> Vec<(String, String)> {
+        fn flatten(column: &str, value: &serde_json::Value, output: &mut Vec<(String, String)>) {
+            let nested = |key: &str| {
+                if column.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{column}.{key}")
+                }
+            };
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map {
+                        flatten(&nested(key), value, output);
+                    }
+                }
+                serde_json::Value::Array(items) => {
+                    for (index, value) in items.iter().enumerate() {
+                        flatten(&nested(&index.to_string()), value, output);
+                    }
+                }
+                serde_json::Value::Null => output.push((column.to_owned(), S!())),
+                serde_json::Value::String(value) => output.push((column.to_owned(), value.clone())),
+                value => output.push((column.to_owned(), value.to_string())),
+            }
+        }
+
+        let mut output = vec![];
+        for (column, value) in &self.leading {
+            flatten(column, value, &mut output);
+        }
+        match &self.value {
+            serde_json::Value::Object(_) => flatten("", &self.value, &mut output),
+            serde_json::Value::Null if !self.leading.is_empty() => (),
+            value => flatten("response", value, &mut output),
+        }
+        output
+    }
+}
+
+/// Write rows as a CSV, with a header of every column, in the order in which they first appear
+fn to_csv(rows: &[CsvRow]) -> Result<Vec<u8>, AppError> {
+    let rows = rows.iter().map(CsvRow::cells).collect::<Vec<_>>();
+    let mut columns: Vec<&str> = vec![];
+    for (column, _) in rows.iter().flatten() {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    if !columns.is_empty() {
+        writer
+            .write_record(&columns)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    for row in &rows {
+        writer
+            .write_record(columns.iter().map(|column| {
+                row.iter()
+                    .find(|(i, _)| i == column)
+                    .map_or("", |(_, cell)| cell.as_str())
+            }))
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// A ResponseJson, serialized in whichever ResponseFormat was requested
+/// Requesting GeoJSON for a response that has no spatial representation is a bad request
+#[derive(Debug)]
+pub struct ResponseFormatted<T> {
+    format: ResponseFormat,
+    json: Json<ResponseJson<T>>,
+}
+
+impl<T> ResponseFormatted<T> {
+    pub const fn new(response: T, format: ResponseFormat) -> Self {
+        Self {
+            format,
+            json: ResponseJson::new(response),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for ResponseFormatted<T> {
+    type Target = ResponseJson<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.json
+    }
+}
+
+impl<T: Formattable> IntoResponse for ResponseFormatted<T> {
+    fn into_response(self) -> Response {
+        let body = match self.format {
+            ResponseFormat::Json => return self.json.into_response(),
+            ResponseFormat::GeoJson => {
+                return self.json.response.to_geojson().map_or_else(
+                    || AppError::Query(format!("format {}", self.format.as_str())).into_response(),
+                    |collection| ResponseGeoJson(collection).into_response(),
+                );
+            }
+            ResponseFormat::Csv => self.json.response.to_rows().and_then(|rows| to_csv(&rows)),
+            ResponseFormat::MessagePack => {
+                rmp_serde::to_vec_named(&*self.json).map_err(|e| AppError::Internal(e.to_string()))
+            }
+        };
+        let content_type = match self.format {
+            ResponseFormat::Csv => ResponseFormat::CSV_MIME,
+            _ => ResponseFormat::MESSAGEPACK_MIME,
+        };
+        match body {
+            Ok(body) => ([(header::CONTENT_TYPE, content_type)], body).into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
+
+/// Response for an unknown aircraft, searched by mode_s, includes the ICAO block that the mode_s was allocated from
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UnknownAircraftJson {
+    pub response: String,
+    pub allocation: ResponseAllocation,
+}
+
 /// Response for the /online api route
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct Online {
     pub uptime: u64,
     pub api_version: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct ResponseAircraft {
     #[serde(rename = "type")]
     pub aircraft_type: String,
@@ -37,6 +243,59 @@ pub struct ResponseAircraft {
     pub registered_owner: String,
     pub url_photo: Option<String>,
     pub url_photo_thumbnail: Option<String>,
+    /// Every photo of the aircraft, url_photo is the first
+    #[serde(default)]
+    pub photos: Vec<ResponsePhoto>,
+}
+
+/// Aircraft photo, with credit to the photographer, and the page the photo was sourced from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ResponsePhoto {
+    pub url_photo: String,
+    pub url_photo_thumbnail: String,
+    pub photographer: Option<String>,
+    pub url_page: Option<String>,
+}
+
+impl From<ModelAircraftPhoto> for ResponsePhoto {
+    fn from(model: ModelAircraftPhoto) -> Self {
+        Self {
+            url_photo: model.url_photo,
+            url_photo_thumbnail: model.url_photo_thumbnail,
+            photographer: model.photographer,
+            url_page: model.url_page,
+        }
+    }
+}
+
+/// A scraped flightroute waiting to be accepted, or rejected, by an admin
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ResponsePendingFlightroute {
+    pub pending_flightroute_id: i64,
+    #[schema(value_type = String)]
+    pub timestamp: jiff::Timestamp,
+    pub callsign_icao: String,
+    pub callsign_iata: String,
+    pub origin: String,
+    pub midpoint: Option<String>,
+    pub destination: String,
+    /// airport_clash, unknown_airport, or unknown_airline
+    pub reason: String,
+}
+
+impl From<ModelPendingFlightroute> for ResponsePendingFlightroute {
+    fn from(model: ModelPendingFlightroute) -> Self {
+        Self {
+            pending_flightroute_id: model.pending_flightroute_id.get(),
+            timestamp: model.timestamp.to_jiff(),
+            callsign_icao: model.callsign_icao,
+            callsign_iata: model.callsign_iata,
+            origin: model.origin,
+            midpoint: model.midpoint,
+            destination: model.destination,
+            reason: model.reason,
+        }
+    }
 }
 
 impl From<ModelAircraft> for ResponseAircraft {
@@ -53,11 +312,73 @@ impl From<ModelAircraft> for ResponseAircraft {
             registered_owner: model.registered_owner,
             url_photo: model.url_photo,
             url_photo_thumbnail: model.url_photo_thumbnail,
+            photos: model
+                .photos
+                .0
+                .into_iter()
+                .map(ResponsePhoto::from)
+                .collect(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// An aircraft that isn't in the database, but whose registration, or mode_s, can be derived algorithmically from the other
+/// Every field that can't be derived is null
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct DerivedAircraft {
+    #[serde(rename = "type")]
+    pub aircraft_type: Option<String>,
+    pub icao_type: Option<String>,
+    pub manufacturer: Option<String>,
+    pub mode_s: String,
+    pub registration: String,
+    pub registered_owner_country_iso_name: Option<&'static str>,
+    pub registered_owner_country_name: Option<&'static str>,
+    pub registered_owner_operator_flag_code: Option<String>,
+    pub registered_owner: Option<String>,
+    pub url_photo: Option<String>,
+    pub url_photo_thumbnail: Option<String>,
+    /// Always empty, so that a derived aircraft has the same shape as a known one
+    pub photos: Vec<ResponsePhoto>,
+    pub derived: bool,
+}
+
+impl DerivedAircraft {
+    pub fn new(aircraft_search: &AircraftSearch) -> Option<Self> {
+        let mode_s = match aircraft_search {
+            AircraftSearch::ModeS(mode_s) => mode_s.clone(),
+            AircraftSearch::Registration(registration) => registration_to_mode_s(registration)?,
+        };
+        // Also used to format a searched registration, e.g. CGAAA to C-GAAA
+        let registration = mode_s_to_registration(&mode_s)?;
+        let allocation = icao_allocation::allocation(&mode_s);
+        Some(Self {
+            aircraft_type: None,
+            icao_type: None,
+            manufacturer: None,
+            mode_s: mode_s.to_string(),
+            registration: registration.to_string(),
+            registered_owner_country_iso_name: allocation.country_iso_name,
+            registered_owner_country_name: allocation.country_name,
+            registered_owner_operator_flag_code: None,
+            registered_owner: None,
+            url_photo: None,
+            url_photo_thumbnail: None,
+            photos: vec![],
+            derived: true,
+        })
+    }
+}
+
+/// Either an aircraft from the database, or one derived from the searched registration or mode_s
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+#[serde(untagged)]
+pub enum AircraftResult {
+    Known(ResponseAircraft),
+    Derived(DerivedAircraft),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct ResponseAirline {
     pub name: String,
     pub icao: String,
@@ -80,7 +401,7 @@ impl From<ModelAirline> for ResponseAirline {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct Airline {
     pub name: String,
     pub icao: String,
@@ -90,7 +411,7 @@ pub struct Airline {
     pub callsign: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, ToSchema)]
 pub struct StatsEntry {
     pub aircraft: Vec<EntryCount>,
     pub airline: Vec<EntryCount>,
@@ -102,7 +423,7 @@ pub struct StatsEntry {
     pub aggregate: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
 pub struct Stats {
     pub daily: StatsEntry,
     pub total: StatsEntry,
@@ -123,7 +444,7 @@ impl Airline {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 pub struct Airport {
     pub country_iso_name: String,
     pub country_name: String,
@@ -136,6 +457,63 @@ pub struct Airport {
     pub name: String,
 }
 
+impl From<ModelAirport> for Airport {
+    fn from(model: ModelAirport) -> Self {
+        Self {
+            country_iso_name: model.country_iso_name,
+            country_name: model.country_name,
+            elevation: model.elevation,
+            iata_code: model.iata_code,
+            icao_code: model.icao_code,
+            latitude: model.latitude,
+            longitude: model.longitude,
+            municipality: model.municipality,
+            name: model.name,
+        }
+    }
+}
+
+/// An airport, with the distance and initial bearing to it from a searched coordinate
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct NearestAirport {
+    #[serde(flatten)]
+    pub airport: Airport,
+    pub distance_km: f64,
+    pub distance_nm: f64,
+    pub bearing: f64,
+}
+
+impl NearestAirport {
+    /// Convert airports into NearestAirports, removing any outside of radius_km, ordered by distance, and truncated to limit
+    pub fn from_models(
+        airports: Vec<ModelAirport>,
+        from: &Coordinate,
+        radius_km: f64,
+        limit: usize,
+    ) -> Vec<Self> {
+        let mut output = airports
+            .into_iter()
+            .map(|model| {
+                let airport = Airport::from(model);
+                let distance_km = from.distance_km(&airport.coordinate());
+                (airport, distance_km)
+            })
+            .filter(|(_, distance_km)| *distance_km <= radius_km)
+            .collect::<Vec<_>>();
+        output.sort_by(|a, b| a.1.total_cmp(&b.1));
+        output
+            .into_iter()
+            .take(limit)
+            .map(|(airport, distance_km)| Self {
+                bearing: geodesy::round(from.initial_bearing(&airport.coordinate()), 1),
+                airport,
+                distance_km: geodesy::round(distance_km, 1),
+                distance_nm: geodesy::round(geodesy::km_to_nm(distance_km), 1),
+            })
+            .collect()
+    }
+}
+
 impl Airport {
     fn from_model(flightroute: &ModelFlightroute) -> (Self, Option<Self>, Self) {
         let origin = Self {
@@ -198,7 +576,7 @@ impl Airport {
     }
 }
 
-#[derive(Serialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
 pub struct ResponseFlightRoute {
     pub callsign: String,
     pub callsign_icao: Option<String>,
@@ -208,12 +586,14 @@ pub struct ResponseFlightRoute {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub midpoint: Option<Airport>,
     pub destination: Airport,
+    pub geodesy: RouteGeodesy,
 }
 
 impl ResponseFlightRoute {
     pub fn from_model(flightroute_airline: Option<&ModelFlightroute>) -> Option<Self> {
         flightroute_airline.as_ref().map(|flightroute| {
             let airports = Airport::from_model(flightroute);
+            let geodesy = RouteGeodesy::new(&airports.0, airports.1.as_ref(), &airports.2);
             Self {
                 callsign: flightroute.callsign.clone(),
                 callsign_icao: flightroute.callsign_icao.clone(),
@@ -222,15 +602,833 @@ impl ResponseFlightRoute {
                 airline: Airline::from_model(flightroute),
                 midpoint: airports.1,
                 destination: airports.2,
+                geodesy,
             }
         })
     }
 }
 
-#[derive(Debug, Serialize, Clone)]
+/// The ICAO block that a mode_s was allocated from, and the state of registry of that block
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+pub struct ResponseAllocation {
+    pub mode_s: String,
+    pub country_iso_name: Option<&'static str>,
+    pub country_name: Option<&'static str>,
+    pub allocation: AllocationKind,
+    pub block_start: String,
+    pub block_end: String,
+}
+
+impl From<&ModeS> for ResponseAllocation {
+    fn from(mode_s: &ModeS) -> Self {
+        let allocation = icao_allocation::allocation(mode_s);
+        Self {
+            mode_s: mode_s.to_string(),
+            country_iso_name: allocation.country_iso_name,
+            country_name: allocation.country_name,
+            allocation: allocation.kind,
+            block_start: format!("{:06X}", allocation.start),
+            block_end: format!("{:06X}", allocation.end),
+        }
+    }
+}
+
+/// Great-circle geometry of a single leg, between two airports
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct LegGeodesy {
+    pub origin: String,
+    pub destination: String,
+    pub distance_km: f64,
+    pub distance_nm: f64,
+    pub initial_bearing: f64,
+}
+
+/// Great-circle geometry of a flightroute, one leg per airport pair, distances rounded to 1dp
+#[derive(Serialize, Debug, Clone, PartialEq, Default, ToSchema)]
+pub struct RouteGeodesy {
+    pub legs: Vec<LegGeodesy>,
+    pub total_distance_km: f64,
+    pub total_distance_nm: f64,
+    pub estimated_duration_minutes: u32,
+}
+
+impl RouteGeodesy {
+    pub fn new(origin: &Airport, midpoint: Option<&Airport>, destination: &Airport) -> Self {
+        let airports = std::iter::once(origin)
+            .chain(midpoint)
+            .chain(std::iter::once(destination))
+            .collect::<Vec<_>>();
+
+        let (mut total_km, mut minutes) = (0.0, 0.0);
+        let legs = airports
+            .windows(2)
+            .map(|pair| {
+                let (from, to) = (pair[0].coordinate(), pair[1].coordinate());
+                let distance_km = from.distance_km(&to);
+                total_km += distance_km;
+                minutes += geodesy::estimated_leg_minutes(distance_km);
+                LegGeodesy {
+                    origin: pair[0].icao_code.clone(),
+                    destination: pair[1].icao_code.clone(),
+                    distance_km: geodesy::round(distance_km, 1),
+                    distance_nm: geodesy::round(geodesy::km_to_nm(distance_km), 1),
+                    initial_bearing: geodesy::round(from.initial_bearing(&to), 1),
+                }
+            })
+            .collect();
+
+        Self {
+            legs,
+            total_distance_km: geodesy::round(total_km, 1),
+            total_distance_nm: geodesy::round(geodesy::km_to_nm(total_km), 1),
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            estimated_duration_minutes: minutes.round() as u32,
+        }
+    }
+}
+
+/// The callsign that was searched for, and the variant of it that matched a flightroute
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CallsignMatch {
+    pub searched: String,
+    pub matched: String,
+    pub variant: CallsignVariant,
+}
+
+impl CallsignMatch {
+    pub fn new(searched: &Callsign, matched: &Callsign, variant: CallsignVariant) -> Self {
+        Self {
+            searched: searched.to_string(),
+            matched: matched.to_string(),
+            variant,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct AircraftAndRoute {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub aircraft: Option<ResponseAircraft>,
+    pub aircraft: Option<AircraftResult>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub flightroute: Option<ResponseFlightRoute>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callsign_match: Option<CallsignMatch>,
+}
+
+/// Individual result of a batch callsign request, serialized as either `{"flightroute": {...}}` or `{"error": "..."}`
+#[derive(Debug, Serialize, Clone, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CallsignResult {
+    Flightroute(Box<ResponseFlightRoute>),
+    Error(String),
+}
+
+/// A page of flightroutes, `next_cursor` should be used as the `cursor` query param to get the next page, null when there are no more results
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct FlightroutePage {
+    pub routes: Vec<ResponseFlightRoute>,
+    pub next_cursor: Option<i64>,
+}
+
+impl FlightroutePage {
+    /// `flightroutes` should have been fetched with a limit of `page_size + 1`, so that it's known if there's a next page
+    pub fn new(mut flightroutes: Vec<ModelFlightroute>, page_size: usize) -> Self {
+        let next_cursor = if flightroutes.len() > page_size {
+            flightroutes.truncate(page_size);
+            flightroutes.last().map(|i| i.flightroute_id.get())
+        } else {
+            None
+        };
+        Self {
+            routes: flightroutes
+                .iter()
+                .filter_map(|i| ResponseFlightRoute::from_model(Some(i)))
+                .collect(),
+            next_cursor,
+        }
+    }
+}
+
+/// GeoJSON geometry, coordinates are [longitude, latitude]
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+#[serde(tag = "type")]
+pub enum Geometry {
+    Point { coordinates: [f64; 2] },
+    LineString { coordinates: Vec<[f64; 2]> },
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+#[serde(tag = "type")]
+pub struct Feature {
+    pub geometry: Geometry,
+    pub properties: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, ToSchema)]
+#[serde(tag = "type")]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+impl Airport {
+    const fn coordinates(&self) -> [f64; 2] {
+        [self.longitude, self.latitude]
+    }
+
+    const fn coordinate(&self) -> Coordinate {
+        Coordinate::new(self.latitude, self.longitude)
+    }
+
+    /// A Point, with the airport details as properties, role is one of origin, midpoint, or destination
+    pub fn to_point_feature(&self, role: &str) -> Feature {
+        Feature {
+            geometry: Geometry::Point {
+                coordinates: self.coordinates(),
+            },
+            properties: serde_json::json!({
+                "role": role,
+                "country_iso_name": self.country_iso_name,
+                "country_name": self.country_name,
+                "elevation": self.elevation,
+                "iata_code": self.iata_code,
+                "icao_code": self.icao_code,
+                "municipality": self.municipality,
+                "name": self.name,
+            }),
+        }
+    }
+}
+
+impl ResponseFlightRoute {
+    /// A Point for each airport, followed by the route LineString
+    pub fn to_feature_collection(&self) -> FeatureCollection {
+        let features = std::iter::once(self.origin.to_point_feature("origin"))
+            .chain(
+                self.midpoint
+                    .as_ref()
+                    .map(|i| i.to_point_feature("midpoint")),
+            )
+            .chain(std::iter::once(
+                self.destination.to_point_feature("destination"),
+            ))
+            .chain(std::iter::once(self.to_line_feature()))
+            .collect();
+        FeatureCollection { features }
+    }
+
+    /// A LineString from origin, via the optional midpoint, to destination
+    pub fn to_line_feature(&self) -> Feature {
+        let coordinates = std::iter::once(&self.origin)
+            .chain(self.midpoint.as_ref())
+            .chain(std::iter::once(&self.destination))
+            .map(Airport::coordinates)
+            .collect();
+        Feature {
+            geometry: Geometry::LineString { coordinates },
+            properties: serde_json::json!({
+                "callsign": self.callsign,
+                "callsign_icao": self.callsign_icao,
+                "callsign_iata": self.callsign_iata,
+                "origin": self.origin.icao_code,
+                "midpoint": self.midpoint.as_ref().map(|i| &i.icao_code),
+                "destination": self.destination.icao_code,
+            }),
+        }
+    }
+}
+
+impl Formattable for AircraftAndRoute {
+    /// The flightroute as a FeatureCollection, with the aircraft and callsign match added to the properties of the LineString
+    fn to_geojson(&self) -> Option<FeatureCollection> {
+        let Some(flightroute) = self.flightroute.as_ref() else {
+            return Some(FeatureCollection { features: vec![] });
+        };
+        let mut collection = flightroute.to_feature_collection();
+        if let Some(serde_json::Value::Object(properties)) =
+            collection.features.last_mut().map(|i| &mut i.properties)
+        {
+            if let Some(aircraft) = self.aircraft.as_ref() {
+                properties.insert(S!("aircraft"), serde_json::json!(aircraft));
+            }
+            if let Some(callsign_match) = self.callsign_match.as_ref() {
+                properties.insert(S!("callsign_match"), serde_json::json!(callsign_match));
+            }
+        }
+        Some(collection)
+    }
+}
+
+impl Formattable for Vec<ResponseAirline> {}
+
+/// Batch responses have one row per input, with the input in the leading search column
+impl<V: Serialize> Formattable for BTreeMap<String, V> {
+    fn to_rows(&self) -> Result<Vec<CsvRow>, AppError> {
+        self.iter()
+            .map(|(search, value)| Ok(CsvRow::new(value)?.with("search", search.as_str())))
+            .collect()
+    }
+}
+
+impl StatsEntry {
+    /// One row per category and url, then a final row of the aggregate count
+    fn to_rows(&self, period: &'static str) -> impl Iterator<Item = Result<CsvRow, AppError>> + '_ {
+        [
+            ("aircraft", &self.aircraft),
+            ("airline", &self.airline),
+            ("callsign", &self.callsign),
+            ("mode_s", &self.mode_s),
+            ("n_number", &self.n_number),
+            ("online", &self.online),
+            ("stats", &self.stats),
+        ]
+        .into_iter()
+        .flat_map(move |(category, counts)| {
+            counts.iter().map(move |count| {
+                Ok(CsvRow::new(count)?
+                    .with("period", period)
+                    .with("category", category))
+            })
+        })
+        .chain(std::iter::once(
+            CsvRow::new(&serde_json::json!({ "url": null, "count": self.aggregate }))
+                .map(|row| row.with("period", period).with("category", "aggregate")),
+        ))
+    }
+}
+
+impl Formattable for Stats {
+    fn to_rows(&self) -> Result<Vec<CsvRow>, AppError> {
+        self.daily
+            .to_rows("daily")
+            .chain(self.total.to_rows("total"))
+            .collect()
+    }
+}
+
+/// A page of route lines, `next_cursor` is a foreign member of the FeatureCollection
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct FeatureCollectionPage {
+    #[serde(flatten)]
+    pub collection: FeatureCollection,
+    pub next_cursor: Option<i64>,
+}
+
+/// A page of the flightroutes of an airline, either as a list, or as a GeoJSON FeatureCollection of route lines
+#[derive(Debug, Serialize, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum AirlineFlightroutes {
+    Flightroutes(FlightroutePage),
+    GeoJson(FeatureCollectionPage),
+}
+
+impl AirlineFlightroutes {
+    /// `flightroutes` should have been fetched with a limit of `page_size + 1`, as per `FlightroutePage::new`
+    pub fn new(flightroutes: Vec<ModelFlightroute>, page_size: usize, geojson: bool) -> Self {
+        let page = FlightroutePage::new(flightroutes, page_size);
+        if geojson {
+            Self::GeoJson(FeatureCollectionPage {
+                collection: FeatureCollection {
+                    features: page
+                        .routes
+                        .iter()
+                        .map(ResponseFlightRoute::to_line_feature)
+                        .collect(),
+                },
+                next_cursor: page.next_cursor,
+            })
+        } else {
+            Self::Flightroutes(page)
+        }
+    }
+}
+
+/// cargo watch -q -c -w src/ -x 'test mod_api_response -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::S;
+
+    fn airport(icao_code: &str, latitude: f64, longitude: f64) -> Airport {
+        Airport {
+            country_iso_name: S!("GB"),
+            country_name: S!("United Kingdom"),
+            elevation: 0,
+            iata_code: None,
+            icao_code: S!(icao_code),
+            latitude,
+            longitude,
+            municipality: S!("test"),
+            name: S!("test"),
+        }
+    }
+
+    fn model_airport(icao_code: &str, latitude: f64, longitude: f64) -> ModelAirport {
+        ModelAirport {
+            airport_id: 1.into(),
+            country_iso_name: S!("GB"),
+            country_name: S!("United Kingdom"),
+            elevation: 0,
+            iata_code: None,
+            icao_code: S!(icao_code),
+            latitude,
+            longitude,
+            municipality: S!("test"),
+            name: S!("test"),
+        }
+    }
+
+    #[test]
+    fn mod_api_response_nearest_airport() {
+        let from = Coordinate::new(51.5, -0.2);
+        let airports = vec![
+            model_airport("EGSS", 51.885, 0.235),
+            model_airport("EGLL", 51.4775, -0.461_389),
+            model_airport("KJFK", 40.639_801, -73.7789),
+            model_airport("EGLC", 51.505_3, 0.055_277_8),
+        ];
+
+        let result = NearestAirport::from_models(airports.clone(), &from, 100.0, 10);
+        assert_eq!(
+            result
+                .iter()
+                .map(|i| i.airport.icao_code.as_str())
+                .collect::<Vec<_>>(),
+            ["EGLC", "EGLL", "EGSS"]
+        );
+        assert!(
+            result
+                .windows(2)
+                .all(|i| i[0].distance_km <= i[1].distance_km)
+        );
+        assert_eq!(result[1].distance_km, 18.3);
+        assert_eq!(result[1].distance_nm, 9.9);
+        assert_eq!(result[1].bearing, 262.2);
+
+        let result = NearestAirport::from_models(airports.clone(), &from, 100.0, 1);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].airport.icao_code, "EGLC");
+
+        let result = NearestAirport::from_models(airports, &from, 1.0, 10);
+        assert!(result.is_empty());
+
+        let result = serde_json::to_value(NearestAirport::from_models(
+            vec![model_airport("EGLL", 51.4775, -0.461_389)],
+            &from,
+            100.0,
+            10,
+        ))
+        .unwrap();
+        assert_eq!(result[0]["icao_code"], "EGLL");
+        assert_eq!(result[0]["distance_km"], 18.3);
+    }
+
+    #[test]
+    fn mod_api_response_allocation() {
+        use crate::api::Validate;
+
+        let result = ResponseAllocation::from(&ModeS::validate("400f1a").unwrap());
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            serde_json::json!({
+                "mode_s": "400F1A",
+                "country_iso_name": "GB",
+                "country_name": "United Kingdom",
+                "allocation": "civil",
+                "block_start": "400000",
+                "block_end": "43FFFF"
+            })
+        );
+
+        let result = ResponseAllocation::from(&ModeS::validate("004400").unwrap());
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            serde_json::json!({
+                "mode_s": "004400",
+                "country_iso_name": null,
+                "country_name": null,
+                "allocation": "unallocated",
+                "block_start": "004400",
+                "block_end": "005FFF"
+            })
+        );
+    }
+
+    #[test]
+    fn mod_api_response_derived_aircraft() {
+        use crate::api::{Registration, Validate};
+
+        let result =
+            DerivedAircraft::new(&AircraftSearch::ModeS(ModeS::validate("a00001").unwrap()))
+                .unwrap();
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            serde_json::json!({
+                "type": null,
+                "icao_type": null,
+                "manufacturer": null,
+                "mode_s": "A00001",
+                "registration": "N1",
+                "registered_owner_country_iso_name": "US",
+                "registered_owner_country_name": "United States",
+                "registered_owner_operator_flag_code": null,
+                "registered_owner": null,
+                "url_photo": null,
+                "url_photo_thumbnail": null,
+                "photos": [],
+                "derived": true
+            })
+        );
+
+        let result = DerivedAircraft::new(&AircraftSearch::Registration(
+            Registration::validate("d-aiab").unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(result.mode_s, "3C6422");
+        assert_eq!(result.registration, "D-AIAB");
+        assert_eq!(result.registered_owner_country_iso_name, Some("DE"));
+        assert!(result.derived);
+
+        // US military block, and unallocated addresses, can't be derived
+        for mode_s in ["ADF7C8", "004400", "F8ABAB"] {
+            assert!(
+                DerivedAircraft::new(&AircraftSearch::ModeS(ModeS::validate(mode_s).unwrap()))
+                    .is_none()
+            );
+        }
+        assert!(
+            DerivedAircraft::new(&AircraftSearch::Registration(
+                Registration::validate("AB-ABAB").unwrap()
+            ))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn mod_api_response_route_geodesy() {
+        let origin = airport("EGLL", 51.4775, -0.461_389);
+        let midpoint = airport("EINN", 52.702, -8.924_81);
+        let destination = airport("KJFK", 40.639_801, -73.7789);
+
+        let result = RouteGeodesy::new(&origin, None, &destination);
+        assert_eq!(result.legs.len(), 1);
+        assert_eq!(result.legs[0].origin, "EGLL");
+        assert_eq!(result.legs[0].destination, "KJFK");
+        assert_eq!(result.legs[0].distance_km, result.total_distance_km);
+        assert_eq!(result.total_distance_km, 5_539.5);
+        assert_eq!(result.total_distance_nm, 2_991.1);
+        assert_eq!(result.estimated_duration_minutes, 445);
+
+        let result = RouteGeodesy::new(&origin, Some(&midpoint), &destination);
+        assert_eq!(
+            result.legs,
+            vec![
+                LegGeodesy {
+                    origin: S!("EGLL"),
+                    destination: S!("EINN"),
+                    distance_km: 593.7,
+                    distance_nm: 320.6,
+                    initial_bearing: 286.6,
+                },
+                LegGeodesy {
+                    origin: S!("EINN"),
+                    destination: S!("KJFK"),
+                    distance_km: 4_946.0,
+                    distance_nm: 2_670.6,
+                    initial_bearing: 281.4,
+                }
+            ]
+        );
+        // Total is calculated from the unrounded legs
+        assert_eq!(result.total_distance_km, 5_539.6);
+        assert_eq!(result.total_distance_nm, 2_991.2);
+        // Each leg has its own overhead
+        assert_eq!(result.estimated_duration_minutes, 475);
+    }
+
+    #[test]
+    fn mod_api_response_flightroute_to_line_feature() {
+        let flightroute = ResponseFlightRoute {
+            callsign: S!("BAW1"),
+            callsign_icao: Some(S!("BAW1")),
+            callsign_iata: Some(S!("BA1")),
+            airline: None,
+            origin: airport("EGLL", 51.4775, -0.461_389),
+            midpoint: Some(airport("EINN", 52.702, -8.924_81)),
+            destination: airport("KJFK", 40.639_801, -73.7789),
+            geodesy: RouteGeodesy::default(),
+        };
+
+        let result = serde_json::to_value(FeatureCollection {
+            features: vec![flightroute.to_line_feature()],
+        })
+        .unwrap();
+
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": [[-0.461_389, 51.4775], [-8.924_81, 52.702], [-73.7789, 40.639_801]]
+                    },
+                    "properties": {
+                        "callsign": "BAW1",
+                        "callsign_icao": "BAW1",
+                        "callsign_iata": "BA1",
+                        "origin": "EGLL",
+                        "midpoint": "EINN",
+                        "destination": "KJFK"
+                    }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn mod_api_response_feature_collection_page() {
+        let page = FeatureCollectionPage {
+            collection: FeatureCollection { features: vec![] },
+            next_cursor: Some(100),
+        };
+        assert_eq!(
+            serde_json::to_value(AirlineFlightroutes::GeoJson(page)).unwrap(),
+            serde_json::json!({
+                "type": "FeatureCollection",
+                "features": [],
+                "next_cursor": 100
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn mod_api_response_aircraft_and_route_geojson() {
+        let flightroute = ResponseFlightRoute {
+            callsign: S!("BAW1"),
+            callsign_icao: Some(S!("BAW1")),
+            callsign_iata: Some(S!("BA1")),
+            airline: None,
+            origin: airport("EGLL", 51.4775, -0.461_389),
+            midpoint: None,
+            destination: airport("KJFK", 40.639_801, -73.7789),
+            geodesy: RouteGeodesy::default(),
+        };
+        let callsign = Callsign::Icao((S!("BAW"), S!("001")));
+        let matched = Callsign::Icao((S!("BAW"), S!("1")));
+        let response = AircraftAndRoute {
+            aircraft: None,
+            flightroute: Some(flightroute.clone()),
+            callsign_match: Some(CallsignMatch::new(
+                &callsign,
+                &matched,
+                CallsignVariant::Normalised,
+            )),
+        };
+
+        let result = serde_json::to_value(response.to_geojson().unwrap()).unwrap();
+        assert_eq!(result["type"], "FeatureCollection");
+        let features = result["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(
+            features[0]["geometry"],
+            serde_json::json!({"type": "Point", "coordinates": [-0.461_389, 51.4775]})
+        );
+        assert_eq!(features[0]["properties"]["role"], "origin");
+        assert_eq!(features[0]["properties"]["icao_code"], "EGLL");
+        assert_eq!(features[1]["properties"]["role"], "destination");
+        assert_eq!(features[2]["geometry"]["type"], "LineString");
+        assert_eq!(
+            features[2]["properties"]["callsign_match"],
+            serde_json::json!({"searched": "BAW001", "matched": "BAW1", "variant": "normalised"})
+        );
+        assert!(features[2]["properties"].get("aircraft").is_none());
+
+        // Without a flightroute, the collection is empty
+        let result = AircraftAndRoute {
+            aircraft: None,
+            flightroute: None,
+            callsign_match: None,
+        }
+        .to_geojson()
+        .unwrap();
+        assert!(result.features.is_empty());
+
+        // Content type, and body, depend on the requested format
+        let response = AircraftAndRoute {
+            aircraft: None,
+            flightroute: Some(flightroute),
+            callsign_match: None,
+        };
+        let result =
+            ResponseFormatted::new(response.clone(), ResponseFormat::GeoJson).into_response();
+        assert_eq!(
+            result.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/geo+json"
+        );
+        let body = axum::body::to_bytes(result.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], "FeatureCollection");
+
+        let result = ResponseFormatted::new(response, ResponseFormat::Json).into_response();
+        assert_eq!(
+            result.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = axum::body::to_bytes(result.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["response"]["flightroute"]["callsign"], "BAW1");
+    }
+
+    async fn into_parts(response: Response) -> (String, Vec<u8>) {
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (content_type, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn mod_api_response_formatted_csv() {
+        let airline = |icao: &str, iata: Option<&str>| ResponseAirline {
+            name: format!("{icao} airline"),
+            icao: S!(icao),
+            iata: iata.map(|i| S!(i)),
+            country: S!("United Kingdom"),
+            country_iso: S!("GB"),
+            callsign: None,
+        };
+        let response = ResponseFormatted::new(
+            vec![airline("BAW", Some("BA")), airline("EZY", None)],
+            ResponseFormat::Csv,
+        )
+        .into_response();
+        let (content_type, body) = into_parts(response).await;
+        assert_eq!(content_type, "text/csv");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "callsign,country,country_iso,iata,icao,name\n,United Kingdom,GB,BA,BAW,BAW airline\n,United Kingdom,GB,,EZY,EZY airline\n"
+        );
+
+        // Batch responses are keyed by search, nested objects are flattened
+        let flightroute = ResponseFlightRoute {
+            callsign: S!("BAW1"),
+            callsign_icao: Some(S!("BAW1")),
+            callsign_iata: None,
+            airline: None,
+            origin: airport("EGLL", 51.4775, -0.461_389),
+            midpoint: None,
+            destination: airport("KJFK", 40.639_801, -73.7789),
+            geodesy: RouteGeodesy::default(),
+        };
+        let batch = BTreeMap::from([
+            (
+                S!("BAW1"),
+                CallsignResult::Flightroute(Box::new(flightroute)),
+            ),
+            (
+                S!("ABC"),
+                CallsignResult::Error(S!("invalid callsign: ABC")),
+            ),
+        ]);
+        let (_, body) =
+            into_parts(ResponseFormatted::new(batch, ResponseFormat::Csv).into_response()).await;
+        let body = String::from_utf8(body).unwrap();
+        let mut reader = csv::Reader::from_reader(body.as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        assert_eq!(&headers[0], "search");
+        assert_eq!(&headers[1], "error");
+        assert!(headers.iter().any(|i| i == "flightroute.origin.icao_code"));
+        assert!(
+            headers
+                .iter()
+                .any(|i| i == "flightroute.geodesy.total_distance_km")
+        );
+        let rows = reader.records().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+        let column = |name: &str| headers.iter().position(|i| i == name).unwrap();
+        assert_eq!(&rows[0][0], "ABC");
+        assert_eq!(&rows[0][1], "invalid callsign: ABC");
+        assert_eq!(&rows[0][column("flightroute.callsign")], "");
+        assert_eq!(&rows[1][0], "BAW1");
+        assert_eq!(&rows[1][1], "");
+        assert_eq!(
+            &rows[1][column("flightroute.destination.icao_code")],
+            "KJFK"
+        );
+
+        // Stats are one row per period, category, and url
+        let entry = |aggregate: i64| StatsEntry {
+            aircraft: vec![
+                serde_json::from_value(serde_json::json!({"url": "/aircraft/N1", "count": 2}))
+                    .unwrap(),
+            ],
+            airline: vec![],
+            callsign: vec![],
+            mode_s: vec![],
+            n_number: vec![],
+            online: vec![],
+            stats: vec![],
+            aggregate,
+        };
+        let stats = Stats {
+            daily: entry(2),
+            total: entry(10),
+        };
+        let (_, body) =
+            into_parts(ResponseFormatted::new(stats, ResponseFormat::Csv).into_response()).await;
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "period,category,count,url\ndaily,aircraft,2,/aircraft/N1\ndaily,aggregate,2,\ntotal,aircraft,2,/aircraft/N1\ntotal,aggregate,10,\n"
+        );
+    }
+
+    #[tokio::test]
+    /// A response that can't be converted into rows is an internal error, rather than an empty CSV
+    async fn mod_api_response_formatted_csv_error() {
+        let batch = BTreeMap::from([(S!("A00001"), BTreeMap::from([((1, 2), 3)]))]);
+        let response = ResponseFormatted::new(batch, ResponseFormat::Csv).into_response();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn mod_api_response_formatted_msgpack() {
+        let batch = BTreeMap::from([(S!("A00001"), None::<ResponseAircraft>)]);
+        let (content_type, body) =
+            into_parts(ResponseFormatted::new(batch, ResponseFormat::MessagePack).into_response())
+                .await;
+        assert_eq!(content_type, "application/msgpack");
+        let result: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(result, serde_json::json!({"response": {"A00001": null}}));
+    }
+
+    #[tokio::test]
+    async fn mod_api_response_formatted_geojson_unsupported() {
+        let response = ResponseFormatted::new(
+            BTreeMap::from([(S!("A00001"), None::<ResponseAircraft>)]),
+            ResponseFormat::GeoJson,
+        )
+        .into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let (_, body) = into_parts(response).await;
+        let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({"response": "invalid query param: format geojson"})
+        );
+    }
 }
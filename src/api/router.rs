@@ -1,57 +1,269 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use axum::{
     extract::{OriginalUri, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 
-use super::input::{AircraftSearch, AirlineCode, Callsign, ModeS, NNumber, Validate};
+use super::input::{
+    AircraftSearch, AircraftSearchBatch, AirlineCode, AirportCode, Callsign, CallsignBatch,
+    CallsignVariant, ModeS, NNumber, NearestAirports, Registration, ResponseFormat, RouteDirection,
+    Validate,
+};
 use super::response::{
-    AircraftAndRoute, AsJsonRes, Online, ResponseAircraft, ResponseAirline, ResponseFlightRoute,
-    ResponseJson,
+    AircraftAndRoute, AircraftResult, AirlineFlightroutes, Airport, AsJsonRes, CallsignMatch,
+    CallsignResult, DerivedAircraft, FlightroutePage, NearestAirport, Online, ResponseAircraft,
+    ResponseAirline, ResponseAllocation, ResponseFlightRoute, ResponseFormatted, ResponseJson,
+};
+use super::{
+    AppError, ApplicationState, Routes, app_error::UnknownAC, cache_control, openapi::OPENAPI,
+    photo_cache, update_routes::IncomingJson,
 };
-use super::{AppError, ApplicationState, app_error::UnknownAC};
 use crate::{
     S,
     api::response::Stats,
-    db_postgres::{ModelAircraft, ModelAirline, ModelFlightroute, ModelIncomingRequest},
-    db_redis::{RedisKey, get_cache, insert_cache},
+    db_postgres::{
+        FlightrouteId, ModelAircraft, ModelAirline, ModelAirport, ModelFlightroute,
+        ModelIncomingRequest, ModelScrapeAttempt,
+    },
+    db_redis::{RedisKey, get_cache, get_cache_many, insert_cache, insert_cache_many},
     n_number::{mode_s_to_n_number, n_number_to_mode_s},
+    registration_converter::{mode_s_to_registration, registration_to_mode_s},
+    scraper::{ScrapeFailure, ScrapeKind},
 };
 
+/// Max number of flightroutes returned in a single page
+const ROUTES_PAGE_SIZE: i64 = 100;
+
+/// The optional cursor query param of a paginated route, which is the next_cursor of the previous page
+fn cursor(queries: &HashMap<String, String>) -> Result<Option<FlightrouteId>, AppError> {
+    queries
+        .get("cursor")
+        .map(|i| {
+            i.parse::<i64>()
+                .map(FlightrouteId::from)
+                .map_err(|_| AppError::Query(format!("cursor {i}")))
+        })
+        .transpose()
+}
+
 pub struct RouterHelper;
 
 impl RouterHelper {
     /// Get flightroute, refactored so can use in either `get_mode_s` (with a callsign query param), or `get_callsign`.
-    /// Check redis cache for Option\<ModelFlightroute>, else query postgres
+    /// If there's no exact match in redis or postgres, try each of the callsign's normalised fallbacks,
+    /// and only scrape the exact callsign if none of them match. Fallbacks are never scraped
     async fn find_flightroute(
         state: &ApplicationState,
         callsign: &Callsign,
+    ) -> Result<Option<(ModelFlightroute, CallsignMatch)>, AppError> {
+        let exact = Self::find_flightroute_many(state, std::slice::from_ref(callsign))
+            .await?
+            .pop()
+            .flatten();
+        if let Some(Some(flightroute)) = exact {
+            return Ok(Some((
+                flightroute,
+                CallsignMatch::new(callsign, callsign, CallsignVariant::Exact),
+            )));
+        }
+
+        let fallbacks = callsign.fallbacks();
+        let to_find = fallbacks
+            .iter()
+            .map(|(fallback, _)| fallback.clone())
+            .collect::<Vec<_>>();
+        let found = Self::find_flightroute_many(state, &to_find).await?;
+        for ((fallback, variant), flightroute) in fallbacks.into_iter().zip(found) {
+            if let Some(Some(flightroute)) = flightroute {
+                return Ok(Some((
+                    flightroute,
+                    CallsignMatch::new(callsign, &fallback, variant),
+                )));
+            }
+        }
+
+        // Only a scrape caches an unknown, so a cached unknown has already been scraped
+        if exact.is_some() {
+            return Err(AppError::UnknownInDb(UnknownAC::Callsign));
+        }
+        Ok(Self::scrape_flightroute(state, callsign)
+            .await?
+            .map(|flightroute| {
+                (
+                    flightroute,
+                    CallsignMatch::new(callsign, callsign, CallsignVariant::Exact),
+                )
+            }))
+    }
+
+    /// Scrape a callsign that's neither cached, nor in postgres, and cache the result
+    async fn scrape_flightroute(
+        state: &ApplicationState,
+        callsign: &Callsign,
     ) -> Result<Option<ModelFlightroute>, AppError> {
-        let redis_key = RedisKey::Callsign(callsign);
-        if let Some(flightroute) = get_cache::<ModelFlightroute>(&state.redis, &redis_key).await? {
-            flightroute.map_or(Err(AppError::UnknownInDb(UnknownAC::Callsign)), |route| {
-                Ok(Some(route))
+        let (one_tx, one_rx) = tokio::sync::oneshot::channel();
+        let mut flightroute = None;
+        if state
+            .scraper_tx
+            .send(crate::scraper::MsgScraper::CallSign((
+                one_tx,
+                callsign.clone(),
+            )))
+            .await
+            .is_ok()
+        {
+            flightroute = one_rx.await.unwrap_or(None);
+        }
+        Self::cache_scraped_flightroute(state, callsign, flightroute.as_ref()).await?;
+        Ok(flightroute)
+    }
+
+    /// Cache the result of a flightroute scrape, an unknown callsign isn't cached if the scrape failed transiently,
+    /// so that it will be scraped again once its backoff has passed
+    async fn cache_scraped_flightroute(
+        state: &ApplicationState,
+        callsign: &Callsign,
+        flightroute: Option<&ModelFlightroute>,
+    ) -> Result<(), AppError> {
+        if flightroute.is_none()
+            && ModelScrapeAttempt::get(&state.postgres, ScrapeKind::Callsign, &callsign.to_string())
+                .await?
+                .and_then(|attempt| attempt.failure())
+                .is_some_and(ScrapeFailure::is_transient)
+        {
+            return Ok(());
+        }
+        insert_cache(&state.redis, flightroute, RedisKey::Callsign(callsign)).await
+    }
+
+    /// Check redis cache, in a single pipeline, for each Option\<ModelFlightroute>, then query postgres, in a single query, for any cache misses.
+    /// Flightroutes found in postgres are cached, unknown callsigns are only cached once they've been scraped.
+    /// Results are in the same order as the given callsigns, and are None if the callsign is neither cached, nor in postgres
+    async fn find_flightroute_many(
+        state: &ApplicationState,
+        callsigns: &[Callsign],
+    ) -> Result<Vec<Option<Option<ModelFlightroute>>>, AppError> {
+        let redis_keys = callsigns.iter().map(RedisKey::Callsign).collect::<Vec<_>>();
+        let cached = get_cache_many::<ModelFlightroute>(&state.redis, &redis_keys).await?;
+
+        let misses = callsigns
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(callsign, _)| callsign.clone())
+            .collect::<Vec<_>>();
+        let found = ModelFlightroute::get_many(&state.postgres, &misses).await?;
+
+        let mut to_insert = vec![];
+        let mut output = Vec::with_capacity(callsigns.len());
+        for (callsign, cached) in callsigns.iter().zip(cached) {
+            if cached.is_some() {
+                output.push(cached);
+                continue;
+            }
+            match found.get(&callsign.to_string()) {
+                Some(flightroute) => {
+                    to_insert.push(output.len());
+                    output.push(Some(Some(flightroute.clone())));
+                }
+                None => output.push(None),
+            }
+        }
+        let to_insert = to_insert
+            .into_iter()
+            .map(|index| {
+                (
+                    output[index].as_ref().and_then(Option::as_ref),
+                    RedisKey::Callsign(&callsigns[index]),
+                )
             })
-        } else {
-            let mut flightroute = ModelFlightroute::get(&state.postgres, callsign).await;
-            if flightroute.is_none() {
-                let (one_tx, one_rx) = tokio::sync::oneshot::channel();
+            .collect::<Vec<_>>();
+        insert_cache_many(&state.redis, &to_insert).await?;
+        Ok(output)
+    }
 
-                if state
-                    .scraper_tx
-                    .send(crate::scraper::MsgScraper::CallSign((
-                        one_tx,
-                        callsign.clone(),
-                    )))
-                    .await
-                    .is_ok()
-                {
-                    flightroute = one_rx.await.unwrap_or(None);
+    /// Batch version of `find_flightroute`, each callsign without an exact match is checked against its normalised fallbacks,
+    /// all of which are looked up together. Callsigns that are neither cached, in postgres, nor matched by a fallback,
+    /// are queued to be scraped in the background, and will be cached once each scrape is complete
+    async fn find_flightroute_batch(
+        state: &ApplicationState,
+        callsigns: &[Callsign],
+    ) -> Result<Vec<Option<ModelFlightroute>>, AppError> {
+        let exact = Self::find_flightroute_many(state, callsigns).await?;
+
+        let fallbacks = callsigns
+            .iter()
+            .zip(&exact)
+            .map(|(callsign, exact)| {
+                if matches!(exact, Some(Some(_))) {
+                    vec![]
+                } else {
+                    callsign.fallbacks()
+                }
+            })
+            .collect::<Vec<_>>();
+        let to_find = fallbacks
+            .iter()
+            .flatten()
+            .map(|(fallback, _)| fallback.clone())
+            .collect::<Vec<_>>();
+        let mut found = Self::find_flightroute_many(state, &to_find)
+            .await?
+            .into_iter()
+            .map(Option::flatten);
+
+        let mut to_scrape = vec![];
+        let mut output = Vec::with_capacity(callsigns.len());
+        for ((callsign, exact), fallbacks) in callsigns.iter().zip(exact).zip(fallbacks) {
+            // Take every fallback's result, so the iterator stays aligned, but keep the first match
+            let fallback = found.by_ref().take(fallbacks.len()).fold(None, Option::or);
+            match (exact, fallback) {
+                (Some(Some(flightroute)), _) | (_, Some(flightroute)) => {
+                    output.push(Some(flightroute));
                 }
+                (Some(None), None) => output.push(None),
+                (None, None) => {
+                    to_scrape.push(callsign.clone());
+                    output.push(None);
+                }
+            }
+        }
+
+        if !to_scrape.is_empty() {
+            let state = state.clone();
+            tokio::spawn(async move {
+                Self::scrape_flightroutes(&state, to_scrape).await;
+            });
+        }
+        Ok(output)
+    }
+
+    /// Send each callsign to the scraper, and cache each result, known or unknown, once the scrape is complete
+    async fn scrape_flightroutes(state: &ApplicationState, to_scrape: Vec<Callsign>) {
+        let mut receivers = vec![];
+        for callsign in to_scrape {
+            let (one_tx, one_rx) = tokio::sync::oneshot::channel();
+            if state
+                .scraper_tx
+                .send(crate::scraper::MsgScraper::CallSign((
+                    one_tx,
+                    callsign.clone(),
+                )))
+                .await
+                .is_ok()
+            {
+                receivers.push((callsign, one_rx));
+            }
+        }
+        for (callsign, one_rx) in receivers {
+            let flightroute = one_rx.await.unwrap_or(None);
+            if let Err(e) =
+                Self::cache_scraped_flightroute(state, &callsign, flightroute.as_ref()).await
+            {
+                tracing::error!("{e:?}");
             }
-            insert_cache(&state.redis, flightroute.as_ref(), redis_key).await?;
-            Ok(flightroute)
         }
     }
 
@@ -117,9 +329,7 @@ impl RouterHelper {
         let redis_key = RedisKey::from(aircraft_search);
 
         if let Some(aircraft) = get_cache::<ModelAircraft>(&state.redis, &redis_key).await? {
-            aircraft.map_or(Err(AppError::UnknownInDb(UnknownAC::Aircraft)), |craft| {
-                Ok(Some(craft))
-            })
+            Ok(aircraft)
         } else {
             let mut aircraft =
                 ModelAircraft::get(&state.postgres, aircraft_search, &state.url_prefix).await?;
@@ -146,6 +356,145 @@ impl RouterHelper {
         }
     }
 
+    /// Proxy the photo, or thumbnail, of a known aircraft, using the photo urls composed with the url_photo_prefix in the aircraft query.
+    /// The photo is streamed, so the ETag is the photo's cache key, and If-None-Match is answered without reading the photo
+    async fn aircraft_photo(
+        state: &ApplicationState,
+        mode_s: ModeS,
+        headers: &HeaderMap,
+        route: Routes,
+    ) -> Result<Response, AppError> {
+        let aircraft_search = AircraftSearch::ModeS(mode_s);
+        let Some(aircraft) = Self::find_aircraft(state, &aircraft_search).await? else {
+            return Err(AppError::UnknownInDb(UnknownAC::aircraft(&aircraft_search)));
+        };
+        let url = if matches!(route, Routes::AircraftPhotoThumbnail) {
+            aircraft.url_photo_thumbnail
+        } else {
+            aircraft.url_photo
+        };
+        let Some(url) = url else {
+            return Err(AppError::UnknownInDb(UnknownAC::Photo));
+        };
+        let etag = photo_cache::etag(&url);
+        let cache_headers = cache_control::cache_headers(route.cache_control(), &etag);
+        if cache_control::none_match(headers, &etag) {
+            return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+        }
+        let photo = state.photo_cache.get(&url).await?;
+        Ok((
+            StatusCode::OK,
+            cache_headers,
+            [(header::CONTENT_TYPE, photo.content_type)],
+            photo.body,
+        )
+            .into_response())
+    }
+
+    /// Check redis cache, in a single pipeline, for each Option\<ModelAircraft>, then query postgres, in a single query, for any cache misses.
+    /// Photo scrapes are queued in the background, with the cache updated once each scrape is complete
+    async fn find_aircraft_batch(
+        state: &ApplicationState,
+        aircraft_searches: &[AircraftSearch],
+    ) -> Result<Vec<Option<ModelAircraft>>, AppError> {
+        let redis_keys = aircraft_searches
+            .iter()
+            .map(RedisKey::from)
+            .collect::<Vec<_>>();
+        let cached = get_cache_many::<ModelAircraft>(&state.redis, &redis_keys).await?;
+
+        let misses = aircraft_searches
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(aircraft_search, _)| aircraft_search.clone())
+            .collect::<Vec<_>>();
+
+        let found = if misses.is_empty() {
+            vec![]
+        } else {
+            ModelAircraft::get_many(&state.postgres, &misses, &state.url_prefix).await?
+        };
+
+        let mut to_scrape = vec![];
+        let mut to_insert = vec![];
+        let mut output = Vec::with_capacity(aircraft_searches.len());
+        for (aircraft_search, cached) in aircraft_searches.iter().zip(cached) {
+            if let Some(aircraft) = cached {
+                output.push(aircraft);
+                continue;
+            }
+            let aircraft = found
+                .iter()
+                .find(|craft| match aircraft_search {
+                    AircraftSearch::ModeS(mode_s) => &craft.mode_s == mode_s,
+                    AircraftSearch::Registration(registration) => {
+                        &craft.registration == registration
+                    }
+                })
+                .cloned();
+            if let Some(craft) = aircraft.as_ref()
+                && craft.url_photo.is_none()
+            {
+                to_scrape.push((aircraft_search.clone(), craft.mode_s.clone()));
+            }
+            to_insert.push(output.len());
+            output.push(aircraft);
+        }
+        let to_insert = to_insert
+            .into_iter()
+            .map(|index| {
+                (
+                    output[index].as_ref(),
+                    RedisKey::from(&aircraft_searches[index]),
+                )
+            })
+            .collect::<Vec<_>>();
+        insert_cache_many(&state.redis, &to_insert).await?;
+
+        if !to_scrape.is_empty() {
+            let state = state.clone();
+            tokio::spawn(async move {
+                Self::scrape_photos(&state, to_scrape).await;
+            });
+        }
+        Ok(output)
+    }
+
+    /// Send each mode_s to the scraper, and once all have been scraped, update the cache for any newly found photos
+    async fn scrape_photos(state: &ApplicationState, to_scrape: Vec<(AircraftSearch, ModeS)>) {
+        let mut receivers = vec![];
+        for (aircraft_search, mode_s) in to_scrape {
+            let (one_tx, one_rx) = tokio::sync::oneshot::channel();
+            if state
+                .scraper_tx
+                .send(crate::scraper::MsgScraper::Photo((one_tx, mode_s)))
+                .await
+                .is_ok()
+            {
+                receivers.push((aircraft_search, one_rx));
+            }
+        }
+        for (aircraft_search, one_rx) in receivers {
+            one_rx.await.ok();
+            match ModelAircraft::get(&state.postgres, &aircraft_search, &state.url_prefix).await {
+                Ok(aircraft) => {
+                    if aircraft.as_ref().is_some_and(|i| i.url_photo.is_some())
+                        && let Err(e) = insert_cache(
+                            &state.redis,
+                            aircraft.as_ref(),
+                            RedisKey::from(&aircraft_search),
+                        )
+                        .await
+                    {
+                        tracing::error!("{e:?}");
+                    }
+                }
+                Err(e) => tracing::error!("{e:?}"),
+            }
+        }
+    }
+
     // Return a random airline - not caching at the moment
     async fn find_random_airline(state: &ApplicationState) -> Result<ModelAirline, AppError> {
         ModelAirline::get_random(&state.postgres).await
@@ -168,6 +517,27 @@ impl RouterHelper {
             Ok(airline)
         }
     }
+
+    /// Check redis cache for Option\<ModelAirport>, else query postgres
+    async fn find_airport(
+        state: &ApplicationState,
+        airport: &AirportCode,
+    ) -> Result<Option<ModelAirport>, AppError> {
+        let redis_key = RedisKey::Airport(airport);
+
+        if let Some(cached) = get_cache::<ModelAirport>(&state.redis, &redis_key).await? {
+            cached.map_or(
+                Err(AppError::UnknownInDb(UnknownAC::Airport(
+                    airport.to_string(),
+                ))),
+                |airport| Ok(Some(airport)),
+            )
+        } else {
+            let airport = ModelAirport::get_by_airport_code(&state.postgres, airport).await?;
+            insert_cache(&state.redis, airport.as_ref(), redis_key).await?;
+            Ok(airport)
+        }
+    }
 }
 pub struct ApiRoutes;
 
@@ -175,68 +545,182 @@ impl ApiRoutes {
     /// Return random aircraft, /aircraft/random
     pub async fn aircraft_random_get(
         State(state): State<ApplicationState>,
-    ) -> Result<(StatusCode, AsJsonRes<AircraftAndRoute>), AppError> {
+        format: ResponseFormat,
+    ) -> Result<(StatusCode, ResponseFormatted<AircraftAndRoute>), AppError> {
         Ok((
             StatusCode::OK,
-            ResponseJson::new(AircraftAndRoute {
-                aircraft: Some(ResponseAircraft::from(
-                    RouterHelper::find_random_aircraft(&state).await?,
-                )),
-                flightroute: None,
-            }),
+            ResponseFormatted::new(
+                AircraftAndRoute {
+                    aircraft: Some(AircraftResult::Known(ResponseAircraft::from(
+                        RouterHelper::find_random_aircraft(&state).await?,
+                    ))),
+                    flightroute: None,
+                    callsign_match: None,
+                },
+                format,
+            ),
         ))
     }
 
     /// Return an aircraft detail from a modes input
+    /// If the aircraft is unknown, but the registration or mode_s can be derived algorithmically, return a partial derived aircraft
     /// optional query param of callsign, so can get both aircraft and flightroute in a single request
+    /// optional query param of format, or Accept header, to return the flightroute as a GeoJSON FeatureCollection, or as CSV or MessagePack
     /// /aircraft/[:REGISTRATION/:MODE-S] *or* /aircraft/[:REGISTRATION/:MODE-S]?callsign=[:CALLSIGN]
     pub async fn aircraft_get(
         State(state): State<ApplicationState>,
         aircraft_search: AircraftSearch,
+        format: ResponseFormat,
         axum::extract::Query(queries): axum::extract::Query<HashMap<String, String>>,
-    ) -> Result<(StatusCode, AsJsonRes<AircraftAndRoute>), AppError> {
+    ) -> Result<(StatusCode, ResponseFormatted<AircraftAndRoute>), AppError> {
         // Check if optional callsign query param
-        if let Some(query_param) = queries.get("callsign") {
-            let callsign = Callsign::validate(query_param)?;
+        let callsign = queries
+            .get("callsign")
+            .map(|i| Callsign::validate(i))
+            .transpose()?;
+
+        let aircraft = match RouterHelper::find_aircraft(&state, &aircraft_search).await? {
+            Some(aircraft) => AircraftResult::Known(ResponseAircraft::from(aircraft)),
+            None => match DerivedAircraft::new(&aircraft_search) {
+                Some(derived) => AircraftResult::Derived(derived),
+                None => return Err(AppError::UnknownInDb(UnknownAC::aircraft(&aircraft_search))),
+            },
+        };
 
-            let Some(aircraft) = RouterHelper::find_aircraft(&state, &aircraft_search).await?
-            else {
-                return Err(AppError::UnknownInDb(UnknownAC::Aircraft));
-            };
-            let flightroute = RouterHelper::find_flightroute(&state, &callsign).await?;
-
-            Ok((
-                StatusCode::OK,
-                ResponseJson::new(AircraftAndRoute {
-                    aircraft: Some(ResponseAircraft::from(aircraft)),
-                    flightroute: ResponseFlightRoute::from_model(flightroute.as_ref()),
-                }),
-            ))
+        let (flightroute, callsign_match) = if let Some(callsign) = callsign {
+            RouterHelper::find_flightroute(&state, &callsign)
+                .await?
+                .map_or((None, None), |(flightroute, callsign_match)| {
+                    (
+                        ResponseFlightRoute::from_model(Some(&flightroute)),
+                        Some(callsign_match),
+                    )
+                })
         } else {
-            let Some(aircraft) = RouterHelper::find_aircraft(&state, &aircraft_search).await?
-            else {
-                return Err(AppError::UnknownInDb(UnknownAC::Aircraft));
+            (None, None)
+        };
+
+        Ok((
+            StatusCode::OK,
+            ResponseFormatted::new(
+                AircraftAndRoute {
+                    aircraft: Some(aircraft),
+                    flightroute,
+                    callsign_match,
+                },
+                format,
+            ),
+        ))
+    }
+
+    /// Return the aircraft's photo, proxied, and cached on disk, so that the third party host isn't hotlinked
+    /// /aircraft/[:MODE-S]/photo
+    pub async fn aircraft_photo_get(
+        State(state): State<ApplicationState>,
+        mode_s: ModeS,
+        headers: HeaderMap,
+    ) -> Result<Response, AppError> {
+        RouterHelper::aircraft_photo(&state, mode_s, &headers, Routes::AircraftPhoto).await
+    }
+
+    /// Return the aircraft's photo thumbnail, proxied, and cached on disk
+    /// /aircraft/[:MODE-S]/photo/thumbnail
+    pub async fn aircraft_photo_thumbnail_get(
+        State(state): State<ApplicationState>,
+        mode_s: ModeS,
+        headers: HeaderMap,
+    ) -> Result<Response, AppError> {
+        RouterHelper::aircraft_photo(&state, mode_s, &headers, Routes::AircraftPhotoThumbnail).await
+    }
+
+    /// Return multiple aircraft details from a JSON array of modes and/or registrations
+    /// Each input is a key in the response, with a value of either the aircraft, or null
+    /// /aircraft
+    pub async fn aircraft_batch_post(
+        State(state): State<ApplicationState>,
+        format: ResponseFormat,
+        IncomingJson(body): IncomingJson<Vec<String>>,
+    ) -> Result<
+        (
+            StatusCode,
+            ResponseFormatted<BTreeMap<String, Option<ResponseAircraft>>>,
+        ),
+        AppError,
+    > {
+        let batch = AircraftSearchBatch::validate(&body)?;
+        let aircraft = RouterHelper::find_aircraft_batch(&state, &batch.0).await?;
+        let found = batch.0.into_iter().zip(aircraft).collect::<HashMap<_, _>>();
+
+        let mut output = BTreeMap::new();
+        for input in body {
+            let aircraft = AircraftSearch::validate(&input)
+                .ok()
+                .and_then(|aircraft_search| found.get(&aircraft_search).cloned().flatten())
+                .map(ResponseAircraft::from);
+            output.insert(input, aircraft);
+        }
+        Ok((StatusCode::OK, ResponseFormatted::new(output, format)))
+    }
+
+    /// Return multiple flightroutes from a JSON array of callsigns
+    /// Each input is a key in the response, with a value of either the flightroute, or an error
+    /// /callsign
+    pub async fn callsign_batch_post(
+        State(state): State<ApplicationState>,
+        format: ResponseFormat,
+        IncomingJson(body): IncomingJson<Vec<String>>,
+    ) -> Result<
+        (
+            StatusCode,
+            ResponseFormatted<BTreeMap<String, CallsignResult>>,
+        ),
+        AppError,
+    > {
+        let batch = CallsignBatch::validate(body)?;
+        let callsigns = batch.callsigns();
+        let flightroutes = RouterHelper::find_flightroute_batch(&state, &callsigns).await?;
+        let found = callsigns
+            .into_iter()
+            .zip(flightroutes)
+            .collect::<HashMap<_, _>>();
+
+        let mut output = BTreeMap::new();
+        for (input, callsign) in batch.0 {
+            let result = match callsign {
+                Ok(callsign) => {
+                    ResponseFlightRoute::from_model(found.get(&callsign).and_then(Option::as_ref))
+                        .map_or_else(
+                            || CallsignResult::Error(format!("unknown {}", UnknownAC::Callsign)),
+                            |flightroute| CallsignResult::Flightroute(Box::new(flightroute)),
+                        )
+                }
+                Err(e) => CallsignResult::Error(format!("{e} {}", input.to_uppercase())),
             };
-            Ok((
-                StatusCode::OK,
-                ResponseJson::new(AircraftAndRoute {
-                    aircraft: Some(ResponseAircraft::from(aircraft)),
-                    flightroute: None,
-                }),
-            ))
+            output.insert(input, result);
         }
+        Ok((StatusCode::OK, ResponseFormatted::new(output, format)))
     }
 
     /// Return a vec random airline, vec will be len 1
     /// /airline/random
     pub async fn airline_random_get(
         State(state): State<ApplicationState>,
-    ) -> Result<(axum::http::StatusCode, AsJsonRes<Vec<ResponseAirline>>), AppError> {
+        format: ResponseFormat,
+    ) -> Result<
+        (
+            axum::http::StatusCode,
+            ResponseFormatted<Vec<ResponseAirline>>,
+        ),
+        AppError,
+    > {
         Ok((
             StatusCode::OK,
-            ResponseJson::new(vec![ResponseAirline::from(
-                RouterHelper::find_random_airline(&state).await?,
-            )]),
+            ResponseFormatted::new(
+                vec![ResponseAirline::from(
+                    RouterHelper::find_random_airline(&state).await?,
+                )],
+                format,
+            ),
         ))
     }
 
@@ -245,54 +729,190 @@ impl ApiRoutes {
     pub async fn airline_get(
         State(state): State<ApplicationState>,
         airline_code: AirlineCode,
-    ) -> Result<(axum::http::StatusCode, AsJsonRes<Vec<ResponseAirline>>), AppError> {
+        format: ResponseFormat,
+    ) -> Result<
+        (
+            axum::http::StatusCode,
+            ResponseFormatted<Vec<ResponseAirline>>,
+        ),
+        AppError,
+    > {
         let Some(airline) = RouterHelper::find_airline(&state, &airline_code).await? else {
             return Err(AppError::UnknownInDb(UnknownAC::Airline));
         };
 
         Ok((
             StatusCode::OK,
-            ResponseJson::new(
+            ResponseFormatted::new(
                 airline
                     .into_iter()
                     .map(ResponseAirline::from)
                     .collect::<Vec<_>>(),
+                format,
             ),
         ))
     }
 
+    /// Return a page of flightroutes for an airline, optional query params of cursor, and of format, to return a GeoJSON FeatureCollection
+    /// /airline/[:AIRLINE_CODE]/routes?cursor=[:CURSOR] *or* /airline/[:AIRLINE_CODE]/routes?format=geojson&cursor=[:CURSOR]
+    pub async fn airline_routes_get(
+        State(state): State<ApplicationState>,
+        airline_code: AirlineCode,
+        axum::extract::Query(queries): axum::extract::Query<HashMap<String, String>>,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<AirlineFlightroutes>), AppError> {
+        let geojson = match queries.get("format") {
+            None => false,
+            Some(format) => match ResponseFormat::validate(format)? {
+                ResponseFormat::Json => false,
+                ResponseFormat::GeoJson => true,
+                ResponseFormat::Csv | ResponseFormat::MessagePack => {
+                    return Err(AppError::Query(format!("format {format}")));
+                }
+            },
+        };
+
+        let Some(airline) = RouterHelper::find_airline(&state, &airline_code).await? else {
+            return Err(AppError::UnknownInDb(UnknownAC::Airline));
+        };
+        let flightroutes = ModelFlightroute::get_by_airlines(
+            &state.postgres,
+            &airline,
+            cursor(&queries)?,
+            ROUTES_PAGE_SIZE + 1,
+        )
+        .await?;
+
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(AirlineFlightroutes::new(
+                flightroutes,
+                usize::try_from(ROUTES_PAGE_SIZE).unwrap_or_default(),
+                geojson,
+            )),
+        ))
+    }
+
+    /// Return an airport detail from a ICAO or IATA airport code
+    /// /airport/[:AIRPORT_CODE]
+    pub async fn airport_get(
+        State(state): State<ApplicationState>,
+        airport_code: AirportCode,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<Airport>), AppError> {
+        let Some(airport) = RouterHelper::find_airport(&state, &airport_code).await? else {
+            return Err(AppError::UnknownInDb(UnknownAC::Airport(
+                airport_code.to_string(),
+            )));
+        };
+
+        Ok((StatusCode::OK, ResponseJson::new(Airport::from(airport))))
+    }
+
+    /// Return airports near a coordinate, ordered by distance, optional query params of radius_km, limit, and airport_type
+    /// /airport/nearest?lat=[:LAT]&lon=[:LON]&radius_km=[:RADIUS_KM]&limit=[:LIMIT]&airport_type=[:AIRPORT_TYPE]
+    pub async fn airport_nearest_get(
+        State(state): State<ApplicationState>,
+        axum::extract::Query(queries): axum::extract::Query<HashMap<String, String>>,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<Vec<NearestAirport>>), AppError> {
+        let nearest = NearestAirports::validate(&queries)?;
+        let airports = ModelAirport::get_within(
+            &state.postgres,
+            &nearest.coordinate.bounding_box(nearest.radius_km),
+            nearest.airport_type,
+        )
+        .await?;
+
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(NearestAirport::from_models(
+                airports,
+                &nearest.coordinate,
+                nearest.radius_km,
+                nearest.limit,
+            )),
+        ))
+    }
+
+    /// Return a page of flightroutes that use an airport, optional query params of direction and cursor
+    /// /airport/[:AIRPORT_CODE]/routes?direction=[origin|destination|any]&cursor=[:CURSOR]
+    pub async fn airport_routes_get(
+        State(state): State<ApplicationState>,
+        airport_code: AirportCode,
+        axum::extract::Query(queries): axum::extract::Query<HashMap<String, String>>,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<FlightroutePage>), AppError> {
+        let direction = queries.get("direction").map_or_else(
+            || Ok(RouteDirection::default()),
+            |i| RouteDirection::validate(i),
+        )?;
+        let cursor = cursor(&queries)?;
+
+        let Some(airport) = RouterHelper::find_airport(&state, &airport_code).await? else {
+            return Err(AppError::UnknownInDb(UnknownAC::Airport(
+                airport_code.to_string(),
+            )));
+        };
+
+        let flightroutes = ModelFlightroute::get_by_airport(
+            &state.postgres,
+            &airport,
+            direction,
+            cursor,
+            ROUTES_PAGE_SIZE + 1,
+        )
+        .await?;
+
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(FlightroutePage::new(
+                flightroutes,
+                usize::try_from(ROUTES_PAGE_SIZE).unwrap_or_default(),
+            )),
+        ))
+    }
+
     /// Return an flightroute
-    /// /callsign/random
+    /// /callsign/random *or* /callsign/random?format=geojson
     pub async fn callsign_random_get(
         State(state): State<ApplicationState>,
-    ) -> Result<(StatusCode, AsJsonRes<AircraftAndRoute>), AppError> {
+        format: ResponseFormat,
+    ) -> Result<(StatusCode, ResponseFormatted<AircraftAndRoute>), AppError> {
         Ok((
             StatusCode::OK,
-            ResponseJson::new(AircraftAndRoute {
-                aircraft: None,
-                flightroute: ResponseFlightRoute::from_model(Some(
-                    &RouterHelper::find_random_flightroute(&state).await?,
-                )),
-            }),
+            ResponseFormatted::new(
+                AircraftAndRoute {
+                    aircraft: None,
+                    flightroute: ResponseFlightRoute::from_model(Some(
+                        &RouterHelper::find_random_flightroute(&state).await?,
+                    )),
+                    callsign_match: None,
+                },
+                format,
+            ),
         ))
     }
 
     /// Return a flightroute detail from a callsign input
-    /// /callsign/[:CALLSIGN]
+    /// /callsign/[:CALLSIGN] *or* /callsign/[:CALLSIGN]?format=geojson
     pub async fn callsign_get(
         State(state): State<ApplicationState>,
         callsign: Callsign,
-    ) -> Result<(axum::http::StatusCode, AsJsonRes<AircraftAndRoute>), AppError> {
-        let Some(flightroute) = RouterHelper::find_flightroute(&state, &callsign).await? else {
+        format: ResponseFormat,
+    ) -> Result<(axum::http::StatusCode, ResponseFormatted<AircraftAndRoute>), AppError> {
+        let Some((flightroute, callsign_match)) =
+            RouterHelper::find_flightroute(&state, &callsign).await?
+        else {
             return Err(AppError::UnknownInDb(UnknownAC::Callsign));
         };
 
         Ok((
             StatusCode::OK,
-            ResponseJson::new(AircraftAndRoute {
-                aircraft: None,
-                flightroute: ResponseFlightRoute::from_model(Some(&flightroute)),
-            }),
+            ResponseFormatted::new(
+                AircraftAndRoute {
+                    aircraft: None,
+                    flightroute: ResponseFlightRoute::from_model(Some(&flightroute)),
+                    callsign_match: Some(callsign_match),
+                },
+                format,
+            ),
         ))
     }
 
@@ -312,11 +932,13 @@ impl ApiRoutes {
     /// /stats
     pub async fn stats_get(
         State(state): State<ApplicationState>,
-    ) -> Result<(axum::http::StatusCode, AsJsonRes<Stats>), AppError> {
+        format: ResponseFormat,
+    ) -> Result<(axum::http::StatusCode, ResponseFormatted<Stats>), AppError> {
         Ok((
             StatusCode::OK,
-            ResponseJson::new(
+            ResponseFormatted::new(
                 ModelIncomingRequest::get_stats(&state.postgres, &state.redis).await?,
+                format,
             ),
         ))
     }
@@ -333,6 +955,44 @@ impl ApiRoutes {
         ))
     }
 
+    /// Route to convert Mode_S to registration, for registries that assign Mode_S algorithmically
+    /// /mode-s/[:MODE-S]/registration
+    #[allow(clippy::unused_async)]
+    pub async fn mode_s_registration_get(
+        mode_s: ModeS,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<String>), AppError> {
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(mode_s_to_registration(&mode_s).map_or(S!(), |f| f.to_string())),
+        ))
+    }
+
+    /// Route to convert registration to Mode_S, for registries that assign Mode_S algorithmically
+    /// /registration/[:REGISTRATION]/mode-s
+    #[allow(clippy::unused_async)]
+    pub async fn registration_mode_s_get(
+        registration: Registration,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<String>), AppError> {
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(
+                registration_to_mode_s(&registration).map_or(S!(), |f| f.to_string()),
+            ),
+        ))
+    }
+
+    /// Route to find the ICAO allocated block, and state of registry, of a Mode_S
+    /// /mode-s/[:MODE-S]/allocation
+    #[allow(clippy::unused_async)]
+    pub async fn mode_s_allocation_get(
+        mode_s: ModeS,
+    ) -> Result<(axum::http::StatusCode, AsJsonRes<ResponseAllocation>), AppError> {
+        Ok((
+            StatusCode::OK,
+            ResponseJson::new(ResponseAllocation::from(&mode_s)),
+        ))
+    }
+
     /// Return a simple online status response
     /// /online
     #[allow(clippy::unused_async)]
@@ -348,6 +1008,13 @@ impl ApiRoutes {
         )
     }
 
+    /// Return the OpenAPI specification of every route
+    /// /openapi.json
+    #[allow(clippy::unused_async)]
+    pub async fn openapi_get() -> (StatusCode, axum::Json<&'static utoipa::openapi::OpenApi>) {
+        (StatusCode::OK, axum::Json(&OPENAPI))
+    }
+
     /// return a unknown endpoint response
     /// /*
     #[allow(clippy::unused_async)]
@@ -379,33 +1046,97 @@ mod tests {
     use crate::api::{
         Registration,
         input::Validate,
-        response::{Airline, Airport},
+        response::{Airline, LegGeodesy, ResponsePhoto, RouteGeodesy},
         tests::delete_incoming_request,
     };
     use crate::db_postgres;
     use crate::db_redis;
     use crate::parse_env;
-    use crate::scraper::tests::{TEST_CALLSIGN, remove_scraped_data};
+    use crate::scraper::{
+        Sources,
+        tests::{TEST_CALLSIGN, remove_scraped_data},
+    };
     use crate::sleep;
     use crate::{S, start_incoming_requests, start_scraper};
 
     /// Get application state for test, also delete recent request stats
     async fn get_application_state() -> State<ApplicationState> {
-        let app_env = parse_env::AppEnv::get_env();
+        let mut app_env = parse_env::AppEnv::get_env();
         let postgres = db_postgres::get_pool(&app_env).await.unwrap();
         let redis = db_redis::get_pool(&app_env).await.unwrap();
         redis.flushall::<()>(true).await.unwrap();
 
-        let tx_scraper = start_scraper(&app_env).await.unwrap();
+        let sources = Sources::mock(&mut app_env).await;
+        let (tx_scraper, scraper_shared) = start_scraper(&app_env, sources).await.unwrap();
         let tx_stats = start_incoming_requests(&app_env).await.unwrap();
 
         delete_incoming_request(&postgres).await;
 
         State(ApplicationState::new(
-            &app_env, postgres, redis, tx_scraper, tx_stats,
+            &app_env,
+            postgres,
+            redis,
+            tx_scraper,
+            scraper_shared,
+            tx_stats,
         ))
     }
 
+    /// Expected geodesy of the fixture flightroutes, calculated independently of the geodesy module
+    fn geodesy_cyul_mroc() -> RouteGeodesy {
+        RouteGeodesy {
+            legs: vec![LegGeodesy {
+                origin: S!("CYUL"),
+                destination: S!("MROC"),
+                distance_km: 4069.3,
+                distance_nm: 2197.3,
+                initial_bearing: 197.5,
+            }],
+            total_distance_km: 4069.3,
+            total_distance_nm: 2197.3,
+            estimated_duration_minutes: 335,
+        }
+    }
+
+    fn geodesy_yssy_wsss_egll() -> RouteGeodesy {
+        RouteGeodesy {
+            legs: vec![
+                LegGeodesy {
+                    origin: S!("YSSY"),
+                    destination: S!("WSSS"),
+                    distance_km: 6293.4,
+                    distance_nm: 3398.2,
+                    initial_bearing: 298.5,
+                },
+                LegGeodesy {
+                    origin: S!("WSSS"),
+                    destination: S!("EGLL"),
+                    distance_km: 10883.2,
+                    distance_nm: 5876.5,
+                    initial_bearing: 322.5,
+                },
+            ],
+            total_distance_km: 17176.7,
+            total_distance_nm: 9274.7,
+            estimated_duration_minutes: 1348,
+        }
+    }
+
+    fn geodesy_roah_rjtt() -> RouteGeodesy {
+        RouteGeodesy {
+            legs: vec![LegGeodesy {
+                origin: S!("ROAH"),
+                destination: S!("RJTT"),
+                distance_km: 1554.3,
+                distance_nm: 839.3,
+                initial_bearing: 45.1,
+            }],
+            total_distance_km: 1554.3,
+            total_distance_nm: 839.3,
+            estimated_duration_minutes: 147,
+        }
+    }
+
     #[tokio::test]
     // basically a 404 handler
     async fn http_api_fallback_route() {
@@ -438,13 +1169,59 @@ mod tests {
         assert_eq!(response.1.response, "A05ED9");
     }
 
+    #[tokio::test]
+    async fn http_api_mode_s_registration_route() {
+        let mode_s = ModeS::validate("c044a9").unwrap();
+        let response = ApiRoutes::mode_s_registration_get(mode_s).await.unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        assert_eq!(response.1.response, "C-GAAA");
+
+        let mode_s = ModeS::validate("400F1A").unwrap();
+        let response = ApiRoutes::mode_s_registration_get(mode_s).await.unwrap();
+        assert_eq!(response.1.response, "");
+    }
+
+    #[tokio::test]
+    async fn http_api_registration_mode_s_route() {
+        let registration = Registration::validate("f-gkxa").unwrap();
+        let response = ApiRoutes::registration_mode_s_get(registration)
+            .await
+            .unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        assert_eq!(response.1.response, "392AE0");
+
+        let registration = Registration::validate("G-EUPT").unwrap();
+        let response = ApiRoutes::registration_mode_s_get(registration)
+            .await
+            .unwrap();
+        assert_eq!(response.1.response, "");
+    }
+
+    #[tokio::test]
+    async fn http_api_mode_s_allocation_route() {
+        let mode_s = ModeS::validate("43c6e1").unwrap();
+        let response = ApiRoutes::mode_s_allocation_get(mode_s).await;
+
+        assert!(response.is_ok());
+        let response = response.unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        assert_eq!(response.1.response.mode_s, "43C6E1");
+        assert_eq!(response.1.response.country_name, Some("United Kingdom"));
+        assert_eq!(
+            response.1.response.allocation,
+            crate::icao_allocation::AllocationKind::Military
+        );
+    }
+
     #[tokio::test]
     async fn http_api_get_mode_s_ok_with_photo() {
         let mode_s = S!("A44F3B");
         let application_state = get_application_state().await;
         let path = AircraftSearch::ModeS(ModeS::validate(&mode_s).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -467,10 +1244,22 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.aircraft {
-            Some(x) => assert_eq!(x, &aircraft),
+            Some(x) => assert_eq!(x, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
         assert!(response.1.response.flightroute.is_none());
@@ -482,7 +1271,9 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AircraftSearch::Registration(Registration::validate(&registration).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -505,10 +1296,22 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.aircraft {
-            Some(x) => assert_eq!(x, &aircraft),
+            Some(x) => assert_eq!(x, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
 
@@ -521,7 +1324,9 @@ mod tests {
         let path = AircraftSearch::ModeS(ModeS::validate(mode_s).unwrap());
         let application_state = get_application_state().await;
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -538,10 +1343,11 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.aircraft {
-            Some(x) => assert_eq!(x, &aircraft),
+            Some(x) => assert_eq!(x, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
 
@@ -554,7 +1360,9 @@ mod tests {
         let path = AircraftSearch::Registration(Registration::validate(registration).unwrap());
         let application_state = get_application_state().await;
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -571,10 +1379,11 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.aircraft {
-            Some(x) => assert_eq!(x, &aircraft),
+            Some(x) => assert_eq!(x, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
 
@@ -591,9 +1400,10 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AircraftSearch::ModeS(ModeS::validate(mode_s).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
-            .await
-            .unwrap();
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap();
 
         let result: Result<String, fred::error::Error> =
             application_state.redis.hget(key.to_string(), "data").await;
@@ -601,7 +1411,10 @@ mod tests {
 
         let result: ResponseAircraft = serde_json::from_str(&result.unwrap()).unwrap();
 
-        assert_eq!(&result, response.1.response.aircraft.as_ref().unwrap());
+        assert_eq!(
+            &AircraftResult::Known(result),
+            response.1.response.aircraft.as_ref().unwrap()
+        );
 
         let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
         assert_eq!(ttl, 604_800);
@@ -617,9 +1430,10 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AircraftSearch::Registration(Registration::validate(registration).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
-            .await
-            .unwrap();
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap();
 
         let result: Result<String, fred::error::Error> =
             application_state.redis.hget(key.to_string(), "data").await;
@@ -627,7 +1441,10 @@ mod tests {
 
         let result: ResponseAircraft = serde_json::from_str(&result.unwrap()).unwrap();
 
-        assert_eq!(&result, response.1.response.aircraft.as_ref().unwrap());
+        assert_eq!(
+            &AircraftResult::Known(result),
+            response.1.response.aircraft.as_ref().unwrap()
+        );
 
         let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
         assert_eq!(ttl, 604_800);
@@ -641,9 +1458,10 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AircraftSearch::ModeS(ModeS::validate(&mode_s).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
-            .await
-            .unwrap();
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap();
 
         let result: Result<String, fred::error::Error> =
             application_state.redis.hget(key.to_string(), "data").await;
@@ -651,7 +1469,10 @@ mod tests {
 
         let result: ResponseAircraft = serde_json::from_str(&result.unwrap()).unwrap();
 
-        assert_eq!(&result, response.1.response.aircraft.as_ref().unwrap());
+        assert_eq!(
+            &AircraftResult::Known(result),
+            response.1.response.aircraft.as_ref().unwrap()
+        );
 
         let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
         assert_eq!(ttl, 604_800);
@@ -665,16 +1486,20 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AircraftSearch::Registration(Registration::validate(&registration).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
-            .await
-            .unwrap();
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap();
         let result: Result<String, fred::error::Error> =
             application_state.redis.hget(key.to_string(), "data").await;
         assert!(result.is_ok());
 
         let result: ResponseAircraft = serde_json::from_str(&result.unwrap()).unwrap();
 
-        assert_eq!(&result, response.1.response.aircraft.as_ref().unwrap());
+        assert_eq!(
+            &AircraftResult::Known(result),
+            response.1.response.aircraft.as_ref().unwrap()
+        );
 
         let ttl: usize = application_state
             .redis
@@ -689,18 +1514,27 @@ mod tests {
     // Make sure unknown aircraft gets placed into cache as ""
     // and a second request will extend the ttl
     async fn http_api_get_mode_s_unknown_cached() {
-        let mode_s = S!("ABABAB");
+        // Unallocated, so can't be derived
+        let mode_s = S!("F8ABAB");
         let tmp_mode_s = ModeS::validate(&mode_s).unwrap();
         let key = RedisKey::ModeS(&tmp_mode_s);
         let application_state = get_application_state().await;
         let path = AircraftSearch::ModeS(ModeS::validate(&mode_s).unwrap());
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path.clone(), hm)
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::aircraft_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+            hm,
+        )
+        .await
+        .unwrap_err();
 
         match response {
-            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft),
+            AppError::UnknownInDb(x) => assert_eq!(
+                x,
+                UnknownAC::Aircraft(Some(ResponseAllocation::from(&tmp_mode_s)))
+            ),
             _ => unreachable!(),
         };
         let result: Result<String, fred::error::Error> =
@@ -720,12 +1554,16 @@ mod tests {
 
         // make sure a second request to an unknown mode_s will extend cache ttl
         let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
-            .await
-            .unwrap_err();
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap_err();
 
         match response {
-            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft),
+            AppError::UnknownInDb(x) => assert_eq!(
+                x,
+                UnknownAC::Aircraft(Some(ResponseAllocation::from(&tmp_mode_s)))
+            ),
             _ => unreachable!(),
         };
 
@@ -738,6 +1576,64 @@ mod tests {
         assert_eq!(ttl, 604_800);
     }
 
+    #[tokio::test]
+    /// An unknown mode_s, or registration, that can be converted, returns a derived aircraft,
+    /// both when the unknown is first cached as "", and from the cached miss on a second request
+    async fn http_api_get_aircraft_derived_cached() {
+        let application_state = get_application_state().await;
+        let mode_s = ModeS::validate("ABABAB").unwrap();
+        let registration = Registration::validate("N851GF").unwrap();
+        let expected = DerivedAircraft {
+            aircraft_type: None,
+            icao_type: None,
+            manufacturer: None,
+            mode_s: S!("ABABAB"),
+            registration: S!("N851GF"),
+            registered_owner_country_iso_name: Some("US"),
+            registered_owner_country_name: Some("United States"),
+            registered_owner_operator_flag_code: None,
+            registered_owner: None,
+            url_photo: None,
+            url_photo_thumbnail: None,
+            photos: vec![],
+            derived: true,
+        };
+
+        for (aircraft_search, key) in [
+            (
+                AircraftSearch::ModeS(mode_s.clone()),
+                RedisKey::ModeS(&mode_s),
+            ),
+            (
+                AircraftSearch::Registration(registration.clone()),
+                RedisKey::Registration(&registration),
+            ),
+        ] {
+            for _ in 0..2 {
+                let response = ApiRoutes::aircraft_get(
+                    application_state.clone(),
+                    aircraft_search.clone(),
+                    ResponseFormat::Json,
+                    axum::extract::Query(HashMap::new()),
+                )
+                .await
+                .unwrap();
+                assert_eq!(response.0, axum::http::StatusCode::OK);
+                assert_eq!(
+                    response.1.response.aircraft,
+                    Some(AircraftResult::Derived(expected.clone()))
+                );
+
+                let result: String = application_state
+                    .redis
+                    .hget(key.to_string(), "data")
+                    .await
+                    .unwrap();
+                assert_eq!(result, "");
+            }
+        }
+    }
+
     #[tokio::test]
     // Make sure unknown aircraft gets placed into cache as ""
     // and a second request will extend the ttl
@@ -746,44 +1642,258 @@ mod tests {
         let tmp_registration = Registration::validate(&registration).unwrap();
         let key = RedisKey::Registration(&tmp_registration);
         let application_state = get_application_state().await;
-        let path = AircraftSearch::Registration(Registration::validate(&registration).unwrap());
-        let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path.clone(), hm)
-            .await
-            .unwrap_err();
+        let path = AircraftSearch::Registration(Registration::validate(&registration).unwrap());
+        let hm = axum::extract::Query(HashMap::new());
+        let response = ApiRoutes::aircraft_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+            hm,
+        )
+        .await
+        .unwrap_err();
+
+        match response {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft(None)),
+            _ => unreachable!(),
+        };
+        let result: Result<String, fred::error::Error> =
+            application_state.redis.hget(key.to_string(), "data").await;
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "");
+
+        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
+        assert_eq!(ttl, 604_800);
+
+        sleep!();
+
+        // make sure a second request to an unknown mode_s will extend cache ttl
+        let hm = axum::extract::Query(HashMap::new());
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap_err();
+
+        match response {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft(None)),
+            _ => unreachable!(),
+        };
+
+        let result: Result<String, fred::error::Error> =
+            application_state.redis.hget(key.to_string(), "data").await;
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "");
+
+        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
+        assert_eq!(ttl, 604_800);
+    }
+
+    #[tokio::test]
+    async fn http_api_post_aircraft_batch_ok() {
+        let application_state = get_application_state().await;
+        let body = vec![S!("a44f3b"), S!("N37522"), S!("F8ABAB"), S!("A44F3B")];
+        let response = ApiRoutes::aircraft_batch_post(
+            application_state.clone(),
+            ResponseFormat::Json,
+            IncomingJson(body),
+        )
+        .await;
+
+        assert!(response.is_ok());
+        let response = response.unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        let response = response.1.response.clone();
+        assert_eq!(response.len(), 4);
+
+        let with_photo = response.get("a44f3b").unwrap().as_ref().unwrap();
+        assert_eq!(with_photo.registration, "N377QS");
+        assert_eq!(
+            with_photo.url_photo,
+            Some(format!(
+                "{}{}",
+                application_state.url_prefix, "001/572/001572354.jpg"
+            ))
+        );
+        assert_eq!(response.get("A44F3B").unwrap().as_ref(), Some(with_photo));
+
+        let no_photo = response.get("N37522").unwrap().as_ref().unwrap();
+        assert_eq!(no_photo.mode_s, "A44917");
+        assert!(no_photo.url_photo.is_none());
+
+        assert!(response.get("F8ABAB").unwrap().is_none());
+
+        // Each search is now cached, unknown aircraft as ""
+        let mode_s = ModeS::validate("F8ABAB").unwrap();
+        let result: String = application_state
+            .redis
+            .hget(RedisKey::ModeS(&mode_s).to_string(), "data")
+            .await
+            .unwrap();
+        assert_eq!(result, "");
+
+        let registration = Registration::validate("N37522").unwrap();
+        let key = RedisKey::Registration(&registration).to_string();
+        let result: String = application_state.redis.hget(&key, "data").await.unwrap();
+        let result: ModelAircraft = serde_json::from_str(&result).unwrap();
+        assert_eq!(result.mode_s.to_string(), "A44917");
+        let ttl: usize = application_state.redis.ttl(&key).await.unwrap();
+        assert_eq!(ttl, 604_800);
+    }
+
+    #[tokio::test]
+    /// A cached unknown is Some(None), and isn't queried again, whereas a key that has never been cached is None
+    async fn http_api_post_aircraft_batch_cached_unknown() {
+        let application_state = get_application_state().await;
+        let unknown = ModeS::validate("F8ABAB").unwrap();
+        let uncached = ModeS::validate("F8ABAC").unwrap();
+        let keys = [RedisKey::ModeS(&unknown), RedisKey::ModeS(&uncached)];
+
+        insert_cache_many::<ModelAircraft>(&application_state.redis, &[(None, keys[0].clone())])
+            .await
+            .unwrap();
+        let result = get_cache_many::<ModelAircraft>(&application_state.redis, &keys)
+            .await
+            .unwrap();
+        assert_eq!(result, vec![Some(None), None]);
+
+        // Each known, or unknown, search is cached in a single pipeline
+        let body = vec![S!("F8ABAB"), S!("F8ABAC"), S!("A44F3B")];
+        let response = ApiRoutes::aircraft_batch_post(
+            application_state.clone(),
+            ResponseFormat::Json,
+            IncomingJson(body),
+        )
+        .await
+        .unwrap();
+        assert!(response.1.response.get("F8ABAC").unwrap().is_none());
+        let result = get_cache_many::<ModelAircraft>(&application_state.redis, &keys)
+            .await
+            .unwrap();
+        assert_eq!(result, vec![Some(None), Some(None)]);
+        let ttl: usize = application_state
+            .redis
+            .ttl(keys[1].to_string())
+            .await
+            .unwrap();
+        assert_eq!(ttl, 604_800);
+    }
+
+    #[tokio::test]
+    async fn http_api_post_aircraft_batch_err() {
+        let application_state = get_application_state().await;
 
-        match response {
-            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft),
+        let response = ApiRoutes::aircraft_batch_post(
+            application_state.clone(),
+            ResponseFormat::Json,
+            IncomingJson(vec![]),
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Body(_)));
+
+        let body = vec![S!("A44F3B"), S!("abc?/")];
+        let response = ApiRoutes::aircraft_batch_post(
+            application_state,
+            ResponseFormat::Json,
+            IncomingJson(body),
+        )
+        .await;
+        match response.unwrap_err() {
+            AppError::AircraftSearch(x) => assert_eq!(x, "abc?/"),
             _ => unreachable!(),
-        };
-        let result: Result<String, fred::error::Error> =
-            application_state.redis.hget(key.to_string(), "data").await;
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "");
+        }
+    }
 
-        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
-        assert_eq!(ttl, 604_800);
+    #[tokio::test]
+    async fn http_api_post_callsign_batch() {
+        let application_state = get_application_state().await;
+        let body = ["ACA959", "ac959", "abc"].map(String::from).to_vec();
+        let response = ApiRoutes::callsign_batch_post(
+            application_state.clone(),
+            ResponseFormat::Json,
+            IncomingJson(body),
+        )
+        .await;
 
-        sleep!();
+        assert!(response.is_ok());
+        let response = response.unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        let response = &response.1.response;
+        assert_eq!(response.len(), 3);
+
+        match response.get("ACA959").unwrap() {
+            CallsignResult::Flightroute(x) => {
+                assert_eq!(x.callsign, "ACA959");
+                assert_eq!(x.callsign_iata, Some(S!("AC959")));
+                assert_eq!(x.origin.icao_code, "CYUL");
+            }
+            CallsignResult::Error(_) => unreachable!(),
+        }
+        match response.get("ac959").unwrap() {
+            CallsignResult::Flightroute(x) => {
+                assert_eq!(x.callsign, "AC959");
+                assert_eq!(x.callsign_icao, Some(S!("ACA959")));
+            }
+            CallsignResult::Error(_) => unreachable!(),
+        }
+        match response.get("abc").unwrap() {
+            CallsignResult::Error(x) => assert_eq!(x, "invalid callsign: ABC"),
+            CallsignResult::Flightroute(_) => unreachable!(),
+        }
 
-        // make sure a second request to an unknown mode_s will extend cache ttl
-        let hm = axum::extract::Query(HashMap::new());
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm)
+        // Found flightroutes are cached
+        let callsign = Callsign::validate("ACA959").unwrap();
+        let result: String = application_state
+            .redis
+            .hget(RedisKey::Callsign(&callsign).to_string(), "data")
             .await
-            .unwrap_err();
+            .unwrap();
+        let result: ModelFlightroute = serde_json::from_str(&result).unwrap();
+        assert_eq!(result.callsign, "ACA959");
 
-        match response {
-            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Aircraft),
-            _ => unreachable!(),
-        };
+        let response = ApiRoutes::callsign_batch_post(
+            application_state,
+            ResponseFormat::Json,
+            IncomingJson(vec![]),
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Body(_)));
+    }
 
-        let result: Result<String, fred::error::Error> =
-            application_state.redis.hget(key.to_string(), "data").await;
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "");
+    #[tokio::test]
+    /// Batch lookups use the same fallbacks as single lookups, and return the same flightroute
+    async fn http_api_post_callsign_batch_fallback() {
+        let application_state = get_application_state().await;
+        let body = ["ACA0959", "ACA959A", "AC0959", "ACA959"]
+            .map(String::from)
+            .to_vec();
+        let response = ApiRoutes::callsign_batch_post(
+            application_state.clone(),
+            ResponseFormat::Json,
+            IncomingJson(body.clone()),
+        )
+        .await
+        .unwrap();
+        let response = &response.1.response;
 
-        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
-        assert_eq!(ttl, 604_800);
+        for callsign in body {
+            let CallsignResult::Flightroute(batch) = response.get(&callsign).unwrap() else {
+                unreachable!()
+            };
+            assert_eq!(batch.callsign_icao, Some(S!("ACA959")));
+
+            let single = ApiRoutes::callsign_get(
+                application_state.clone(),
+                Callsign::validate(&callsign).unwrap(),
+                ResponseFormat::Json,
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                single.1.response.flightroute.as_ref(),
+                Some(batch.as_ref()),
+                "{callsign}"
+            );
+        }
     }
 
     #[tokio::test]
@@ -791,7 +1901,8 @@ mod tests {
         let callsign = "ACA959";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        let response = ApiRoutes::callsign_get(application_state.clone(), path).await;
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json).await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -833,6 +1944,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         match &response.1.response.flightroute {
@@ -841,6 +1953,156 @@ mod tests {
         }
 
         assert!(response.1.response.aircraft.is_none());
+        assert_eq!(
+            response.1.response.callsign_match,
+            Some(CallsignMatch {
+                searched: S!(callsign),
+                matched: S!(callsign),
+                variant: CallsignVariant::Exact,
+            })
+        );
+    }
+
+    #[tokio::test]
+    /// Unknown callsigns fall back to the normalised flight number, and then to the base flight number
+    async fn http_api_get_callsign_fallback_ok() {
+        let application_state = get_application_state().await;
+
+        for (callsign, variant) in [
+            ("ACA0959", CallsignVariant::Normalised),
+            ("ACA959A", CallsignVariant::BaseFlight),
+            ("AC0959", CallsignVariant::Normalised),
+        ] {
+            let path = Callsign::validate(callsign).unwrap();
+            let response =
+                ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json)
+                    .await
+                    .unwrap();
+            assert_eq!(response.0, axum::http::StatusCode::OK);
+
+            let flightroute = response.1.response.flightroute.as_ref().unwrap();
+            assert_eq!(flightroute.callsign_icao, Some(S!("ACA959")));
+            assert_eq!(flightroute.callsign_iata, Some(S!("AC959")));
+
+            let callsign_match = response.1.response.callsign_match.as_ref().unwrap();
+            assert_eq!(callsign_match.searched, callsign);
+            assert_eq!(callsign_match.variant, variant);
+        }
+    }
+
+    #[tokio::test]
+    /// A callsign matched by a fallback isn't scraped, nor is the unknown exact callsign cached
+    async fn http_api_get_callsign_fallback_not_scraped() {
+        let callsign = "ACA0959";
+        let application_state = get_application_state().await;
+        sqlx::query("DELETE FROM scrape_attempt WHERE search = $1")
+            .bind(callsign)
+            .execute(&application_state.postgres)
+            .await
+            .unwrap();
+
+        let path = Callsign::validate(callsign).unwrap();
+        let response = ApiRoutes::callsign_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        assert_eq!(
+            response.1.response.callsign_match.as_ref().unwrap().variant,
+            CallsignVariant::Normalised
+        );
+
+        sleep!();
+        let attempt =
+            ModelScrapeAttempt::get(&application_state.postgres, ScrapeKind::Callsign, callsign)
+                .await
+                .unwrap();
+        assert!(attempt.is_none());
+
+        let result: Option<String> = application_state
+            .redis
+            .hget(RedisKey::Callsign(&path).to_string(), "data")
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    /// An unknown fallback isn't cached, so a later search for the fallback itself is still scraped
+    async fn http_api_get_callsign_fallback_unknown_not_cached() {
+        let application_state = get_application_state().await;
+        let delete_attempts = async || {
+            sqlx::query("DELETE FROM scrape_attempt WHERE search = ANY($1)")
+                .bind(["BAW12A", "BAW12"])
+                .execute(&application_state.postgres)
+                .await
+                .unwrap();
+        };
+        delete_attempts().await;
+
+        let path = Callsign::validate("BAW12A").unwrap();
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json)
+                .await
+                .unwrap_err();
+        assert!(matches!(
+            response,
+            AppError::UnknownInDb(UnknownAC::Callsign)
+        ));
+
+        let fallback = Callsign::validate("BAW12").unwrap();
+        let result: bool = application_state
+            .redis
+            .exists(RedisKey::Callsign(&fallback).to_string())
+            .await
+            .unwrap();
+        assert!(!result);
+
+        let response = ApiRoutes::callsign_get(
+            application_state.clone(),
+            fallback.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            response,
+            AppError::UnknownInDb(UnknownAC::Callsign)
+        ));
+        let attempt =
+            ModelScrapeAttempt::get(&application_state.postgres, ScrapeKind::Callsign, "BAW12")
+                .await
+                .unwrap();
+        assert!(attempt.is_some());
+        delete_attempts().await;
+    }
+
+    #[tokio::test]
+    /// An aircraft with a cached unknown callsign query param is a callsign 404
+    async fn http_api_get_aircraft_callsign_none_cached() {
+        let application_state = get_application_state().await;
+        let callsign = Callsign::validate("ABABAB").unwrap();
+        insert_cache::<ModelFlightroute>(
+            &application_state.redis,
+            None,
+            RedisKey::Callsign(&callsign),
+        )
+        .await
+        .unwrap();
+
+        let path = AircraftSearch::ModeS(ModeS::validate("A44F3B").unwrap());
+        let hm = axum::extract::Query(HashMap::from([(S!("callsign"), S!("ABABAB"))]));
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await
+                .unwrap_err();
+        match response {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Callsign),
+            _ => unreachable!(),
+        };
     }
 
     #[tokio::test]
@@ -848,10 +2110,11 @@ mod tests {
         let callsign = "AC959";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        let response = ApiRoutes::callsign_get(application_state.clone(), path).await;
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json).await;
 
         assert!(response.is_ok());
-        let response: (StatusCode, axum::Json<ResponseJson<AircraftAndRoute>>) = response.unwrap();
+        let response: (StatusCode, ResponseFormatted<AircraftAndRoute>) = response.unwrap();
         assert_eq!(response.0, axum::http::StatusCode::OK);
 
         let flightroute = ResponseFlightRoute {
@@ -889,6 +2152,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         match &response.1.response.flightroute {
@@ -904,7 +2168,8 @@ mod tests {
         let callsign = "QFA31";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        let response = ApiRoutes::callsign_get(application_state.clone(), path).await;
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json).await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -955,6 +2220,7 @@ mod tests {
                 municipality: S!("London"),
                 name: S!("London Heathrow Airport"),
             },
+            geodesy: geodesy_yssy_wsss_egll(),
         };
 
         match &response.1.response.flightroute {
@@ -970,7 +2236,8 @@ mod tests {
         let callsign = "QF31";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        let response = ApiRoutes::callsign_get(application_state.clone(), path).await;
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json).await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1021,6 +2288,7 @@ mod tests {
                 municipality: S!("London"),
                 name: S!("London Heathrow Airport"),
             },
+            geodesy: geodesy_yssy_wsss_egll(),
         };
         match &response.1.response.flightroute {
             Some(d) => assert_eq!(d, &flightroute),
@@ -1036,7 +2304,7 @@ mod tests {
         let callsign = "AC959";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        ApiRoutes::callsign_get(application_state.clone(), path)
+        ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json)
             .await
             .unwrap();
 
@@ -1108,7 +2376,7 @@ mod tests {
         let callsign = "QFA31";
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
-        ApiRoutes::callsign_get(application_state.clone(), path)
+        ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json)
             .await
             .unwrap();
         let tmp_callsign = Callsign::validate(callsign).unwrap();
@@ -1178,7 +2446,8 @@ mod tests {
     async fn http_api_get_callsign_scraper() {
         let application_state = get_application_state().await;
         let path = Callsign::validate(TEST_CALLSIGN).unwrap();
-        let response = ApiRoutes::callsign_get(application_state.clone(), path).await;
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json).await;
         assert!(response.is_ok());
         let response = response.unwrap();
         assert_eq!(response.0, axum::http::StatusCode::OK);
@@ -1218,6 +2487,7 @@ mod tests {
                 municipality: S!("Tokyo"),
                 name: S!("Tokyo Haneda International Airport"),
             },
+            geodesy: geodesy_roah_rjtt(),
         };
 
         assert!(response.1.response.flightroute.is_some());
@@ -1234,9 +2504,13 @@ mod tests {
         let application_state = get_application_state().await;
         let path = Callsign::validate(callsign).unwrap();
 
-        let response = ApiRoutes::callsign_get(application_state.clone(), path.clone())
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::callsign_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Callsign),
             _ => unreachable!(),
@@ -1255,9 +2529,10 @@ mod tests {
         sleep!();
 
         // Check second request is also in redis, and cache ttl gets reset
-        let response = ApiRoutes::callsign_get(application_state.clone(), path)
-            .await
-            .unwrap_err();
+        let response =
+            ApiRoutes::callsign_get(application_state.clone(), path, ResponseFormat::Json)
+                .await
+                .unwrap_err();
 
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Callsign),
@@ -1283,7 +2558,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1324,6 +2601,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1344,6 +2622,18 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.flightroute {
@@ -1352,7 +2642,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1366,7 +2656,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1407,6 +2699,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1427,6 +2720,18 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.flightroute {
@@ -1435,7 +2740,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1449,7 +2754,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1490,6 +2797,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
         let aircraft = ResponseAircraft {
             aircraft_type: S!("Citation Sovereign"),
@@ -1509,6 +2817,18 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.flightroute {
@@ -1517,7 +2837,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1531,7 +2851,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1572,6 +2894,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
         let aircraft = ResponseAircraft {
             aircraft_type: S!("Citation Sovereign"),
@@ -1591,6 +2914,18 @@ mod tests {
                 "{}thumbnails/{}",
                 application_state.url_prefix, "001/572/001572354.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: format!(
+                    "{}{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                url_photo_thumbnail: format!(
+                    "{}thumbnails/{}",
+                    application_state.url_prefix, "001/572/001572354.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         };
 
         match &response.1.response.flightroute {
@@ -1599,7 +2934,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1614,7 +2949,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1655,6 +2992,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1669,6 +3007,7 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.flightroute {
@@ -1677,7 +3016,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1692,7 +3031,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1733,6 +3074,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1747,6 +3089,7 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.flightroute {
@@ -1755,7 +3098,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1770,7 +3113,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1811,6 +3156,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1825,6 +3171,7 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.flightroute {
@@ -1833,7 +3180,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1848,7 +3195,9 @@ mod tests {
         let mut hm = HashMap::new();
         hm.insert(S!("callsign"), callsign.clone());
         let hm = axum::extract::Query(hm);
-        let response = ApiRoutes::aircraft_get(application_state.clone(), path, hm).await;
+        let response =
+            ApiRoutes::aircraft_get(application_state.clone(), path, ResponseFormat::Json, hm)
+                .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1889,6 +3238,7 @@ mod tests {
                 municipality: S!("San José (Alajuela)"),
                 name: S!("Juan Santamaría International Airport"),
             },
+            geodesy: geodesy_cyul_mroc(),
         };
 
         let aircraft = ResponseAircraft {
@@ -1903,6 +3253,7 @@ mod tests {
             registered_owner_country_iso_name: S!("US"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: vec![],
         };
 
         match &response.1.response.flightroute {
@@ -1911,7 +3262,7 @@ mod tests {
         }
 
         match &response.1.response.aircraft {
-            Some(d) => assert_eq!(d, &aircraft),
+            Some(d) => assert_eq!(d, &AircraftResult::Known(aircraft)),
             None => unreachable!(),
         }
     }
@@ -1927,9 +3278,13 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AirlineCode::Iata(callsign.to_owned());
 
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone())
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airline),
             _ => unreachable!(),
@@ -1947,9 +3302,13 @@ mod tests {
         sleep!();
 
         // Check second request is also in redis, and cache ttl gets reset
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone())
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
 
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airline),
@@ -1974,9 +3333,13 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AirlineCode::Icao(callsign.to_owned());
 
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone())
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airline),
             _ => unreachable!(),
@@ -1994,9 +3357,13 @@ mod tests {
         sleep!();
 
         // Check second request is also in redis, and cache ttl gets reset
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone())
-            .await
-            .unwrap_err();
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await
+        .unwrap_err();
 
         match response {
             AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airline),
@@ -2021,7 +3388,12 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AirlineCode::Icao(callsign.to_owned());
 
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone()).await;
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -2064,7 +3436,12 @@ mod tests {
         let application_state = get_application_state().await;
         let path = AirlineCode::Iata(callsign.to_owned());
 
-        let response = ApiRoutes::airline_get(application_state.clone(), path.clone()).await;
+        let response = ApiRoutes::airline_get(
+            application_state.clone(),
+            path.clone(),
+            ResponseFormat::Json,
+        )
+        .await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -2117,12 +3494,371 @@ mod tests {
         assert_eq!(ttl, 604_800);
     }
 
+    /// Airport Route
+    /// `/airport/[airport_code]`
+
+    #[tokio::test]
+    /// Make sure that an unknown Airport is inserted correctly into redis cache as NULL and has ttl of 604800
+    /// and another request extends the tll to 604800 again
+    async fn http_api_get_airport_none_cached() {
+        let application_state = get_application_state().await;
+        let path = AirportCode::Icao(S!("ZZZZ"));
+
+        let response = ApiRoutes::airport_get(application_state.clone(), path.clone())
+            .await
+            .unwrap_err();
+        match response {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airport(S!("ZZZZ"))),
+            _ => unreachable!(),
+        };
+        let key = RedisKey::Airport(&path);
+
+        let result: Result<String, fred::error::Error> =
+            application_state.redis.hget(key.to_string(), "data").await;
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "");
+
+        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
+        assert_eq!(ttl, 604_800);
+
+        sleep!();
+
+        // Check second request is also in redis, and cache ttl gets reset
+        let response = ApiRoutes::airport_get(application_state.clone(), path.clone())
+            .await
+            .unwrap_err();
+
+        match response {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airport(S!("ZZZZ"))),
+            _ => unreachable!(),
+        };
+
+        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
+        assert_eq!(ttl, 604_800);
+    }
+
+    #[tokio::test]
+    /// Make sure that a known icao Airport is returned, and inserted correctly into redis cache
+    async fn http_api_get_icao_airport_ok_and_cached() {
+        let application_state = get_application_state().await;
+        let path = AirportCode::Icao(S!("CYUL"));
+
+        let response = ApiRoutes::airport_get(application_state.clone(), path.clone()).await;
+
+        assert!(response.is_ok());
+        let response = response.unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        let expected = Airport {
+            country_iso_name: S!("CA"),
+            country_name: S!("Canada"),
+            elevation: 118,
+            iata_code: Some(S!("YUL")),
+            icao_code: S!("CYUL"),
+            latitude: 45.470_600_128_2,
+            longitude: -73.740_798_950_2,
+            municipality: S!("Montréal"),
+            name: S!("Montreal / Pierre Elliott Trudeau International Airport"),
+        };
+        assert_eq!(response.1.response, expected);
+
+        let key = RedisKey::Airport(&path);
+
+        let result: Result<String, fred::error::Error> =
+            application_state.redis.hget(key.to_string(), "data").await;
+        assert!(result.is_ok());
+
+        let result: ModelAirport = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(Airport::from(result), expected);
+
+        let ttl: usize = application_state.redis.ttl(key.to_string()).await.unwrap();
+        assert_eq!(ttl, 604_800);
+    }
+
+    #[tokio::test]
+    /// Make sure that a known iata Airport is returned, and matches the icao response
+    async fn http_api_get_iata_airport_ok() {
+        let application_state = get_application_state().await;
+
+        let response_iata =
+            ApiRoutes::airport_get(application_state.clone(), AirportCode::Iata(S!("YUL")))
+                .await
+                .unwrap();
+        let response_icao =
+            ApiRoutes::airport_get(application_state.clone(), AirportCode::Icao(S!("CYUL")))
+                .await
+                .unwrap();
+
+        assert_eq!(response_iata.0, axum::http::StatusCode::OK);
+        assert_eq!(response_iata.1.response, response_icao.1.response);
+        assert_eq!(response_iata.1.response.icao_code, "CYUL");
+    }
+
+    #[tokio::test]
+    /// Every page is capped at ROUTES_PAGE_SIZE, and following next_cursor eventually returns every route of the airline
+    async fn http_api_get_airline_routes_ok() {
+        let application_state = get_application_state().await;
+        let query = |format: Option<&str>, cursor: Option<i64>| {
+            let mut hm = HashMap::new();
+            if let Some(format) = format {
+                hm.insert(S!("format"), S!(format));
+            }
+            if let Some(cursor) = cursor {
+                hm.insert(S!("cursor"), cursor.to_string());
+            }
+            axum::extract::Query(hm)
+        };
+
+        let mut cursor = None;
+        let mut flightroutes = vec![];
+        let mut first_page = None;
+        loop {
+            let response = ApiRoutes::airline_routes_get(
+                application_state.clone(),
+                AirlineCode::Icao(S!("ACA")),
+                query(None, cursor),
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.0, axum::http::StatusCode::OK);
+            let AirlineFlightroutes::Flightroutes(page) = response.1.response.clone() else {
+                unreachable!()
+            };
+            assert!(page.routes.len() <= 100);
+            assert!(
+                page.routes
+                    .iter()
+                    .all(|i| i.airline.as_ref().unwrap().icao == "ACA")
+            );
+            first_page.get_or_insert_with(|| page.clone());
+            flightroutes.extend(page.routes);
+            if page.next_cursor.is_none() {
+                break;
+            }
+            assert!(page.next_cursor > cursor);
+            cursor = page.next_cursor;
+        }
+        assert!(flightroutes.iter().any(|i| i.callsign == "ACA959"));
+        let first_page = first_page.unwrap();
+
+        let response = ApiRoutes::airline_routes_get(
+            application_state,
+            AirlineCode::Iata(S!("AC")),
+            query(Some("geojson"), None),
+        )
+        .await
+        .unwrap();
+        let AirlineFlightroutes::GeoJson(page) = &response.1.response else {
+            unreachable!()
+        };
+        assert_eq!(page.collection.features.len(), first_page.routes.len());
+        assert_eq!(page.next_cursor, first_page.next_cursor);
+        let result = serde_json::to_value(page).unwrap();
+        assert_eq!(result["type"], "FeatureCollection");
+        assert_eq!(result["next_cursor"], serde_json::json!(page.next_cursor));
+    }
+
+    #[tokio::test]
+    async fn http_api_get_airline_routes_err() {
+        let application_state = get_application_state().await;
+
+        let hm = axum::extract::Query(HashMap::from([(S!("format"), S!("xml"))]));
+        let response = ApiRoutes::airline_routes_get(
+            application_state.clone(),
+            AirlineCode::Icao(S!("ACA")),
+            hm,
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+
+        let hm = axum::extract::Query(HashMap::from([(S!("cursor"), S!("abc"))]));
+        let response = ApiRoutes::airline_routes_get(
+            application_state.clone(),
+            AirlineCode::Icao(S!("ACA")),
+            hm,
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+
+        let hm = axum::extract::Query(HashMap::new());
+        let response =
+            ApiRoutes::airline_routes_get(application_state, AirlineCode::Icao(S!("DDD")), hm)
+                .await;
+        match response.unwrap_err() {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airline),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn http_api_get_airport_routes_ok() {
+        let application_state = get_application_state().await;
+        let query = |direction: &str, cursor: Option<i64>| {
+            let mut hm = HashMap::from([(S!("direction"), S!(direction))]);
+            if let Some(cursor) = cursor {
+                hm.insert(S!("cursor"), cursor.to_string());
+            }
+            axum::extract::Query(hm)
+        };
+
+        let response = ApiRoutes::airport_routes_get(
+            application_state.clone(),
+            AirportCode::Icao(S!("CYUL")),
+            query("origin", None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        let first_page = &response.1.response;
+        assert!(!first_page.routes.is_empty());
+        assert!(first_page.routes.len() <= 100);
+        assert!(
+            first_page
+                .routes
+                .iter()
+                .all(|i| i.origin.icao_code == "CYUL")
+        );
+
+        if let Some(cursor) = first_page.next_cursor {
+            let response = ApiRoutes::airport_routes_get(
+                application_state.clone(),
+                AirportCode::Iata(S!("YUL")),
+                query("origin", Some(cursor)),
+            )
+            .await
+            .unwrap();
+            let second_page = &response.1.response;
+            assert!(!second_page.routes.is_empty());
+            assert!(second_page.routes.iter().all(|i| {
+                i.origin.icao_code == "CYUL"
+                    && !first_page
+                        .routes
+                        .iter()
+                        .any(|first| first.callsign == i.callsign)
+            }));
+        }
+
+        let response = ApiRoutes::airport_routes_get(
+            application_state,
+            AirportCode::Icao(S!("CYUL")),
+            query("destination", None),
+        )
+        .await
+        .unwrap();
+        assert!(
+            response
+                .1
+                .response
+                .routes
+                .iter()
+                .all(|i| i.destination.icao_code == "CYUL")
+        );
+    }
+
+    #[tokio::test]
+    async fn http_api_get_airport_routes_err() {
+        let application_state = get_application_state().await;
+
+        let hm = axum::extract::Query(HashMap::from([(S!("direction"), S!("sideways"))]));
+        let response = ApiRoutes::airport_routes_get(
+            application_state.clone(),
+            AirportCode::Icao(S!("CYUL")),
+            hm,
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+
+        let hm = axum::extract::Query(HashMap::from([(S!("cursor"), S!("abc"))]));
+        let response = ApiRoutes::airport_routes_get(
+            application_state.clone(),
+            AirportCode::Icao(S!("CYUL")),
+            hm,
+        )
+        .await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+
+        let hm = axum::extract::Query(HashMap::new());
+        let response =
+            ApiRoutes::airport_routes_get(application_state, AirportCode::Icao(S!("ZZZZ")), hm)
+                .await;
+        match response.unwrap_err() {
+            AppError::UnknownInDb(x) => assert_eq!(x, UnknownAC::Airport(S!("ZZZZ"))),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn http_api_get_airport_nearest_ok() {
+        let application_state = get_application_state().await;
+        let query = |x: &[(&str, &str)]| {
+            axum::extract::Query(
+                x.iter()
+                    .map(|(k, v)| (S!(*k), S!(*v)))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+
+        // Central London
+        let response = ApiRoutes::airport_nearest_get(
+            application_state.clone(),
+            query(&[("lat", "51.5"), ("lon", "-0.12"), ("radius_km", "100")]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.0, axum::http::StatusCode::OK);
+        let airports = &response.1.response;
+        assert!(!airports.is_empty());
+        assert!(airports.len() <= 10);
+        assert!(airports.iter().all(|i| i.distance_km <= 100.0));
+        assert!(
+            airports
+                .windows(2)
+                .all(|i| i[0].distance_km <= i[1].distance_km)
+        );
+
+        let response = ApiRoutes::airport_nearest_get(
+            application_state,
+            query(&[
+                ("lat", "51.5"),
+                ("lon", "-0.12"),
+                ("radius_km", "100"),
+                ("limit", "100"),
+                ("airport_type", "large_airport"),
+            ]),
+        )
+        .await
+        .unwrap();
+        assert!(
+            response
+                .1
+                .response
+                .iter()
+                .any(|i| i.airport.icao_code == "EGLL")
+        );
+    }
+
+    #[tokio::test]
+    async fn http_api_get_airport_nearest_err() {
+        let application_state = get_application_state().await;
+
+        let hm = axum::extract::Query(HashMap::from([(S!("lat"), S!("51.5"))]));
+        let response = ApiRoutes::airport_nearest_get(application_state.clone(), hm).await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+
+        let hm = axum::extract::Query(HashMap::from([
+            (S!("lat"), S!("51.5")),
+            (S!("lon"), S!("-0.12")),
+            (S!("radius_km"), S!("10000")),
+        ]));
+        let response = ApiRoutes::airport_nearest_get(application_state, hm).await;
+        assert!(matches!(response.unwrap_err(), AppError::Query(_)));
+    }
+
     // Stats
     #[tokio::test]
     async fn http_api_get_stats() {
         let application_state = get_application_state().await;
 
-        let response = ApiRoutes::stats_get(application_state.clone()).await;
+        let response = ApiRoutes::stats_get(application_state.clone(), ResponseFormat::Json).await;
 
         assert!(response.is_ok());
         let response = response.unwrap();
@@ -1,6 +1,6 @@
 use axum::{
     extract::{
-        FromRequest, State,
+        FromRequest, OptionalFromRequest, State,
         rejection::{JsonDataError, JsonRejection},
     },
     http::{HeaderMap, Request},
@@ -17,10 +17,18 @@ use crate::{
     S,
     api::UnknownAC,
     argon::ArgonHash,
-    db_postgres::{ModelAircraft, ModelAirport, ModelFlightroute},
+    db_postgres::{
+        ModelAircraft, ModelAirport, ModelFlightroute, ModelPendingFlightroute,
+        PendingFlightrouteId,
+    },
+    db_redis::delete_flightroute_cache,
+    scraper::{ScrapeFailure, Scraper, ScraperStatus},
 };
 
-use super::{AppError, ApplicationState, Callsign, ModeS, response::ResponseAircraft};
+use super::{
+    AppError, ApplicationState, Callsign, ModeS,
+    response::{AsJsonRes, ResponseAircraft, ResponseJson, ResponsePendingFlightroute},
+};
 
 /// Verify the Authorization header against the app_env.argon_hash
 pub async fn auth_header(
@@ -89,6 +97,24 @@ where
 
 pub struct IncomingJson<T>(pub T);
 
+/// Convert a json rejection into an ApiError, with the reason that the body is invalid
+fn json_rejection(rejection: JsonRejection) -> AppError {
+    match rejection {
+        JsonRejection::JsonDataError(e) => extract_serde_error(e),
+        JsonRejection::JsonSyntaxError(_) => AppError::Body(S!("JSON syntax")),
+        JsonRejection::MissingJsonContentType(e) => {
+            tracing::trace!("{e:?}");
+            AppError::Body(S!("\"application/json\" header"))
+        }
+        JsonRejection::BytesRejection(e) => {
+            tracing::trace!("{e:?}");
+            tracing::trace!("BytesRejection");
+            AppError::Body(S!("Bytes Rejected"))
+        }
+        _ => AppError::Body(S!("IncomingJson from_request error")),
+    }
+}
+
 /// Implement custom error handing for JSON extraction on incoming JSON
 /// Either return valid json (meeting a struct spec listed below), or return an ApiError
 /// Then each route handler, can use `IncomingJson(body): IncomingJson<T>`, to extract T into param body
@@ -103,32 +129,47 @@ where
         req: Request<axum::body::Body>,
         state: &S,
     ) -> Result<Self, Self::Rejection> {
-        match axum::Json::<T>::from_request(req, state).await {
-            Ok(value) => Ok(Self(value.0)),
-            Err(rejection) => match rejection {
-                JsonRejection::JsonDataError(e) => Err(extract_serde_error(e)),
-                JsonRejection::JsonSyntaxError(_) => Err(AppError::Body(S!("JSON syntax"))),
-                JsonRejection::MissingJsonContentType(e) => {
-                    tracing::trace!("{e:?}");
-                    Err(AppError::Body(S!("\"application/json\" header")))
-                }
-                JsonRejection::BytesRejection(e) => {
-                    tracing::trace!("{e:?}");
-                    tracing::trace!("BytesRejection");
-                    Err(AppError::Body(S!("Bytes Rejected")))
-                }
-                _ => Err(AppError::Body(S!("IncomingJson from_request error"))),
-            },
-        }
+        axum::Json::<T>::from_request(req, state)
+            .await
+            .map(|value| Self(value.0))
+            .map_err(json_rejection)
+    }
+}
+
+/// An optional body, `Option<IncomingJson<T>>` is None when the request doesn't have a content-type header
+impl<S, T> OptionalFromRequest<S> for IncomingJson<T>
+where
+    axum::Json<T>: OptionalFromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(
+        req: Request<axum::body::Body>,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        <axum::Json<T> as OptionalFromRequest<S>>::from_request(req, state)
+            .await
+            .map(|value| value.map(|value| Self(value.0)))
+            .map_err(json_rejection)
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
 pub struct UpdatedCallsign {
     origin: String,
     destination: String,
 }
 
+/// Airports to accept a pending flightroute with, instead of the scraped airports, e.g. to fix an airport clash
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct AcceptedPending {
+    origin: String,
+    #[serde(default)]
+    midpoint: Option<String>,
+    destination: String,
+}
+
 /// Just some simple to checks to make sure that the new aircraft body is valid
 /// Just basic checks, could still result in invalid data being inputted
 /// But it's better than nothing, the limits are the current max length for each +10%
@@ -176,23 +217,21 @@ pub async fn callsign_patch(
     {
         return Err(AppError::Body(S!("no change")));
     }
+    // The body doesn't include a midpoint, so keep the current midpoint
+    let midpoint = match flightroute.midpoint_airport_icao_code.as_ref() {
+        Some(midpoint) => {
+            let Some(midpoint) = ModelAirport::get(&state.postgres, midpoint).await? else {
+                return Err(AppError::UnknownInDb(UnknownAC::Airport(S!(midpoint))));
+            };
+            Some(midpoint)
+        }
+        None => None,
+    };
     flightroute
-        .update(&state.postgres, origin, destination)
+        .update(&state.postgres, origin, midpoint, destination)
         .await?;
 
-    if let Some(iata) = flightroute.callsign_iata.as_ref() {
-        state
-            .redis
-            .del::<(), String>(format!("callsign::{iata}"))
-            .await?;
-    }
-
-    if let Some(icao) = flightroute.callsign_icao.as_ref() {
-        state
-            .redis
-            .del::<(), String>(format!("callsign::{icao}"))
-            .await?;
-    }
+    delete_flightroute_cache(&state.redis, &flightroute).await?;
     Ok(StatusCode::OK)
 }
 
@@ -218,14 +257,11 @@ pub async fn aircraft_patch(
     mode_s: ModeS,
     IncomingJson(body): IncomingJson<ResponseAircraft>,
 ) -> Result<StatusCode, AppError> {
-    let Some(current_aircraft) = ModelAircraft::get(
-        &state.postgres,
-        &super::AircraftSearch::ModeS(mode_s),
-        &state.url_prefix,
-    )
-    .await?
+    let aircraft_search = super::AircraftSearch::ModeS(mode_s);
+    let Some(current_aircraft) =
+        ModelAircraft::get(&state.postgres, &aircraft_search, &state.url_prefix).await?
     else {
-        return Err(AppError::UnknownInDb(UnknownAC::Aircraft));
+        return Err(AppError::UnknownInDb(UnknownAC::aircraft(&aircraft_search)));
     };
 
     // Simple check to make sure the values aren't excessively large
@@ -265,6 +301,87 @@ pub async fn aircraft_patch(
     Ok(StatusCode::OK)
 }
 
+/// Return the in-flight searches, queue depth, and counters, of the scraper
+pub async fn scraper_status_get(
+    State(state): State<ApplicationState>,
+) -> Result<(StatusCode, AsJsonRes<ScraperStatus>), AppError> {
+    let status = state.scraper_shared.status(state.scraper_tx.len());
+    Ok((StatusCode::OK, ResponseJson::new(status)))
+}
+
+/// Return every scraped flightroute that couldn't be inserted, and is waiting to be accepted or rejected
+pub async fn scraper_pending_get(
+    State(state): State<ApplicationState>,
+) -> Result<(StatusCode, AsJsonRes<Vec<ResponsePendingFlightroute>>), AppError> {
+    let pending = ModelPendingFlightroute::get_all(&state.postgres)
+        .await?
+        .into_iter()
+        .map(ResponsePendingFlightroute::from)
+        .collect::<Vec<_>>();
+    Ok((StatusCode::OK, ResponseJson::new(pending)))
+}
+
+/// Accept a pending flightroute, the missing airline or airport should have been inserted first.
+/// An optional body replaces the scraped airports, so that an airport clash can be corrected
+pub async fn scraper_pending_post(
+    State(state): State<ApplicationState>,
+    pending_flightroute_id: PendingFlightrouteId,
+    body: Option<IncomingJson<AcceptedPending>>,
+) -> Result<StatusCode, AppError> {
+    let Some(pending) =
+        ModelPendingFlightroute::get(&state.postgres, pending_flightroute_id).await?
+    else {
+        return Err(AppError::UnknownInDb(UnknownAC::PendingFlightroute));
+    };
+    let Some(mut scraped_flightroute) = pending.scraped_flightroute() else {
+        return Err(AppError::Body(S!("invalid callsign")));
+    };
+    if let Some(IncomingJson(body)) = body {
+        scraped_flightroute.origin = body.origin;
+        scraped_flightroute.midpoint = body.midpoint;
+        scraped_flightroute.destination = body.destination;
+    }
+    if scraped_flightroute.airport_clash() {
+        return Err(AppError::Body(S!("airport clash")));
+    }
+    if ModelFlightroute::get(&state.postgres, &scraped_flightroute.callsign_icao)
+        .await
+        .is_some()
+    {
+        return Err(AppError::Body(S!("flightroute exists")));
+    }
+
+    match Scraper::insert_flightroute(&state.postgres, &scraped_flightroute).await? {
+        Ok(flightroute) => {
+            ModelPendingFlightroute::delete_callsign(
+                &state.postgres,
+                &scraped_flightroute.callsign_icao,
+            )
+            .await?;
+            delete_flightroute_cache(&state.redis, &flightroute).await?;
+            Ok(StatusCode::OK)
+        }
+        Err(failed) if failed.failure == ScrapeFailure::UnknownAirline => {
+            Err(AppError::UnknownInDb(UnknownAC::Airline))
+        }
+        Err(failed) => Err(AppError::UnknownInDb(UnknownAC::Airport(failed.reason))),
+    }
+}
+
+/// Reject a pending flightroute, it won't be pending again unless it's scraped with different airports
+pub async fn scraper_pending_delete(
+    State(state): State<ApplicationState>,
+    pending_flightroute_id: PendingFlightrouteId,
+) -> Result<StatusCode, AppError> {
+    let Some(pending) =
+        ModelPendingFlightroute::get(&state.postgres, pending_flightroute_id).await?
+    else {
+        return Err(AppError::UnknownInDb(UnknownAC::PendingFlightroute));
+    };
+    pending.reject(&state.postgres).await?;
+    Ok(StatusCode::OK)
+}
+
 #[cfg(test)]
 #[allow(clippy::pedantic, clippy::unwrap_used)]
 pub mod tests {
@@ -274,13 +391,21 @@ pub mod tests {
 
     use crate::S;
     use crate::api::API_VERSION;
+    use crate::api::response::ResponsePhoto;
     use crate::api::serve;
     use crate::api::tests::CLIENT;
     use crate::api::tests::test_setup;
     use crate::parse_env::AppEnv;
     use crate::sleep;
     use crate::start_incoming_requests;
-    use crate::start_scraper;
+    use crate::{
+        api::Validate,
+        scraper::{
+            ScrapedFlightroute, Sources,
+            tests::{TEST_CALLSIGN, TEST_DESTINATION, TEST_ORIGIN, remove_scraped_data},
+        },
+        start_scraper,
+    };
 
     use fred::prelude::{HashesInterface, Pool};
     use reqwest::{Client, StatusCode};
@@ -327,13 +452,21 @@ pub mod tests {
 
         let redis = setup.redis.clone();
 
-        let tx_scraper = start_scraper(&app_env).await.unwrap();
+        let sources = Sources::mock(&mut app_env).await;
+        let (tx_scraper, scraper_shared) = start_scraper(&app_env, sources).await.unwrap();
         let tx_stats = start_incoming_requests(&app_env).await.unwrap();
 
         let handle = tokio::spawn(async move {
-            serve(spawn_env, postgres, redis, tx_scraper, tx_stats)
-                .await
-                .unwrap();
+            serve(
+                spawn_env,
+                postgres,
+                redis,
+                tx_scraper,
+                scraper_shared,
+                tx_stats,
+            )
+            .await
+            .unwrap();
         });
         // just sleep to make sure the server is running - 1ms is enough
         sleep!(100);
@@ -372,6 +505,14 @@ pub mod tests {
             url_photo_thumbnail: Some(S!(
                 "https://airport-data.com/images/aircraft/thumbnails/001/675/001675893.jpg"
             )),
+            photos: vec![ResponsePhoto {
+                url_photo: S!("https://airport-data.com/images/aircraft/001/675/001675893.jpg"),
+                url_photo_thumbnail: S!(
+                    "https://airport-data.com/images/aircraft/thumbnails/001/675/001675893.jpg"
+                ),
+                photographer: None,
+                url_page: None,
+            }],
         }
     }
 
@@ -1334,4 +1475,390 @@ pub mod tests {
 
         reset_callsign(&CLIENT).await;
     }
+
+    //
+    // Scraper Status Tests
+    //
+
+    fn scraper_status_url() -> String {
+        format!(
+            "http://127.0.0.1:8282{}/scraper/status",
+            API_VERSION.as_str()
+        )
+    }
+
+    #[tokio::test]
+    /// env.update is None, route doesn't exist, 404 response
+    async fn http_mod_get_scraper_status_no_update() {
+        start_server(None).await;
+
+        let resp = CLIENT.get(scraper_status_url()).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    /// No auth header, or invalid auth header, return 401
+    async fn http_mod_get_scraper_status_unauthorized() {
+        start_server(Some(())).await;
+
+        let resp = CLIENT.get(scraper_status_url()).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = CLIENT
+            .get(scraper_status_url())
+            .header("authorization", "invalid_header")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn http_mod_get_scraper_status_ok() {
+        start_server(Some(())).await;
+
+        let resp = CLIENT
+            .get(scraper_status_url())
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "no-store");
+
+        let resp = resp.json::<TestResponse>().await.unwrap().response;
+        assert!(resp["queue_depth"].is_u64());
+        for job in ["callsign", "photo"] {
+            assert!(resp[job]["in_flight"].is_array());
+            for counter in ["success", "failure", "timeout"] {
+                assert!(resp[job][counter].is_u64());
+            }
+            assert!(resp[job].get("average_latency_ms").is_some());
+        }
+        assert!(resp.get("last_upstream_error").is_some());
+    }
+
+    //
+    // Scraper pending tests
+    //
+
+    fn scraper_pending_url(pending_flightroute_id: Option<&str>) -> String {
+        let url = format!(
+            "http://127.0.0.1:8282{}/scraper/pending",
+            API_VERSION.as_str()
+        );
+        pending_flightroute_id.map_or(url.clone(), |id| format!("{url}/{id}"))
+    }
+
+    #[tokio::test]
+    /// env.update is None, routes don't exist, 404 response
+    async fn http_mod_scraper_pending_no_update() {
+        start_server(None).await;
+
+        let resp = CLIENT.get(scraper_pending_url(None)).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some("1")))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    /// No auth header, or invalid auth header, return 401
+    async fn http_mod_scraper_pending_unauthorized() {
+        start_server(Some(())).await;
+
+        let resp = CLIENT.get(scraper_pending_url(None)).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = CLIENT
+            .delete(scraper_pending_url(Some("1")))
+            .header("authorization", "invalid_header")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn http_mod_scraper_pending_get_ok() {
+        start_server(Some(())).await;
+
+        let resp = CLIENT
+            .get(scraper_pending_url(None))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "no-store");
+        let resp = resp.json::<TestResponse>().await.unwrap().response;
+        assert!(resp.is_array());
+    }
+
+    #[tokio::test]
+    /// Unknown, or invalid, pending_flightroute_id returns 404
+    async fn http_mod_scraper_pending_unknown() {
+        start_server(Some(())).await;
+
+        for id in ["0", "invalid"] {
+            let resp = CLIENT
+                .post(scraper_pending_url(Some(id)))
+                .header("authorization", "password123")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            let resp = resp.json::<TestResponse>().await.unwrap().response;
+            assert_eq!(resp, "unknown pending flightroute");
+
+            let resp = CLIENT
+                .delete(scraper_pending_url(Some(id)))
+                .header("authorization", "password123")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    /// Queue TEST_CALLSIGN for review, with the given airports, and return its pending_flightroute_id
+    async fn insert_pending(
+        postgres: &PgPool,
+        origin: &str,
+        destination: &str,
+        failure: ScrapeFailure,
+    ) -> String {
+        let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
+        ModelPendingFlightroute::delete_callsign(postgres, &callsign)
+            .await
+            .unwrap();
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: callsign,
+            callsign_iata: Callsign::validate("NH460").unwrap(),
+            origin: S!(origin),
+            midpoint: None,
+            destination: S!(destination),
+        };
+        ModelPendingFlightroute::insert(postgres, &scraped_flightroute, failure)
+            .await
+            .unwrap();
+        pending_ids(postgres).await.pop().unwrap()
+    }
+
+    /// Every pending_flightroute_id for TEST_CALLSIGN returned by the GET route
+    async fn pending_ids(postgres: &PgPool) -> Vec<String> {
+        let resp = CLIENT
+            .get(scraper_pending_url(None))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = resp.json::<TestResponse>().await.unwrap().response;
+        let ids = resp
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|i| i["callsign_icao"] == TEST_CALLSIGN)
+            .map(|i| i["pending_flightroute_id"].to_string())
+            .collect::<Vec<_>>();
+        assert!(ids.len() <= 1);
+        // The route only returns pending flightroutes that haven't been rejected
+        let stored = ModelPendingFlightroute::get_all(postgres).await.unwrap();
+        assert_eq!(
+            stored
+                .iter()
+                .filter(|i| i.callsign_icao == TEST_CALLSIGN)
+                .count(),
+            ids.len()
+        );
+        ids
+    }
+
+    #[tokio::test]
+    /// Accepting inserts the flightroute, and removes both the cached callsign, and the pending flightroute
+    async fn http_mod_scraper_pending_accept() {
+        let setup = start_server(Some(())).await;
+        remove_scraped_data(&setup._postgres).await;
+        let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
+        let id = insert_pending(
+            &setup._postgres,
+            TEST_ORIGIN,
+            TEST_DESTINATION,
+            ScrapeFailure::UnknownAirline,
+        )
+        .await;
+
+        let key = format!("callsign::{TEST_CALLSIGN}");
+        setup
+            .redis
+            .set::<(), _, _>(&key, "cached", None, None, false)
+            .await
+            .unwrap();
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let flightroute = ModelFlightroute::get(&setup._postgres, &callsign)
+            .await
+            .unwrap();
+        assert_eq!(flightroute.origin_airport_icao_code, TEST_ORIGIN);
+        assert_eq!(flightroute.destination_airport_icao_code, TEST_DESTINATION);
+        assert!(!setup.redis.exists::<bool, _>(&key).await.unwrap());
+        assert!(pending_ids(&setup._postgres).await.is_empty());
+
+        // Now unknown, as it's no longer pending
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        remove_scraped_data(&setup._postgres).await;
+    }
+
+    #[tokio::test]
+    /// An airport clash can only be accepted once the airports have been corrected in the body
+    async fn http_mod_scraper_pending_accept_airport_clash() {
+        let setup = start_server(Some(())).await;
+        remove_scraped_data(&setup._postgres).await;
+        let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
+        let id = insert_pending(
+            &setup._postgres,
+            TEST_ORIGIN,
+            TEST_ORIGIN,
+            ScrapeFailure::AirportClash,
+        )
+        .await;
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = resp.json::<TestResponse>().await.unwrap().response;
+        assert_eq!(resp, "invalid body airport clash");
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .json(&serde_json::json!({"origin": TEST_ORIGIN, "destination": "ZZZZ"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = resp.json::<TestResponse>().await.unwrap().response;
+        assert_eq!(resp, "unknown airport: ROAH - ZZZZ");
+        assert_eq!(
+            pending_ids(&setup._postgres).await,
+            std::slice::from_ref(&id)
+        );
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .json(&serde_json::json!({"origin": TEST_ORIGIN, "destination": TEST_DESTINATION}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let flightroute = ModelFlightroute::get(&setup._postgres, &callsign)
+            .await
+            .unwrap();
+        assert_eq!(flightroute.origin_airport_icao_code, TEST_ORIGIN);
+        assert_eq!(flightroute.destination_airport_icao_code, TEST_DESTINATION);
+        assert!(pending_ids(&setup._postgres).await.is_empty());
+        remove_scraped_data(&setup._postgres).await;
+    }
+
+    #[tokio::test]
+    /// Accepting a pending flightroute is a POST, so the update routes' preflight allows it
+    async fn http_mod_scraper_pending_cors_preflight() {
+        start_server(Some(())).await;
+        let resp = CLIENT
+            .request(reqwest::Method::OPTIONS, scraper_pending_url(Some("1")))
+            .header(reqwest::header::ORIGIN, "https://www.example.com")
+            .header(reqwest::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(reqwest::header::ACCESS_CONTROL_ALLOW_METHODS)
+                .unwrap(),
+            "GET,PATCH,POST,DELETE"
+        );
+    }
+
+    #[tokio::test]
+    /// A rejected flightroute isn't returned, can't be accepted, and stays hidden when scraped again with the same airports
+    async fn http_mod_scraper_pending_reject() {
+        let setup = start_server(Some(())).await;
+        remove_scraped_data(&setup._postgres).await;
+        let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
+        let id = insert_pending(
+            &setup._postgres,
+            TEST_ORIGIN,
+            "ZZZZ",
+            ScrapeFailure::UnknownAirport,
+        )
+        .await;
+
+        let resp = CLIENT
+            .delete(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(pending_ids(&setup._postgres).await.is_empty());
+
+        let resp = CLIENT
+            .post(scraper_pending_url(Some(&id)))
+            .header("authorization", "password123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(
+            ModelFlightroute::get(&setup._postgres, &callsign)
+                .await
+                .is_none()
+        );
+
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: callsign.clone(),
+            callsign_iata: Callsign::validate("NH460").unwrap(),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!("ZZZZ"),
+        };
+        ModelPendingFlightroute::insert(
+            &setup._postgres,
+            &scraped_flightroute,
+            ScrapeFailure::UnknownAirport,
+        )
+        .await
+        .unwrap();
+        assert!(pending_ids(&setup._postgres).await.is_empty());
+
+        ModelPendingFlightroute::delete_callsign(&setup._postgres, &callsign)
+            .await
+            .unwrap();
+        remove_scraped_data(&setup._postgres).await;
+    }
 }
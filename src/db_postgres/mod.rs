@@ -5,15 +5,19 @@ mod model_airline;
 mod model_airport;
 mod model_flightroute;
 mod model_incoming_request;
+mod model_pending_flightroute;
+mod model_scrape_attempt;
 
-pub use model_aircraft::ModelAircraft;
+pub use model_aircraft::{AircraftId, ModelAircraft, ModelAircraftPhoto, ModelMissingPhotographer};
 pub use model_airline::ModelAirline;
 pub use model_airport::ModelAirport;
-pub use model_flightroute::ModelFlightroute;
+pub use model_flightroute::{FlightrouteId, ModelFlightroute};
 pub use model_incoming_request::{
     EntryCount, ModelIncomingRequest, MsgIncomingRequest, PathID, QueryID, RE_SEED_TIME, UriMethod,
     VersionID,
 };
+pub use model_pending_flightroute::{ModelPendingFlightroute, PendingFlightrouteId};
+pub use model_scrape_attempt::ModelScrapeAttempt;
 
 use crate::{api::AppError, parse_env::AppEnv};
 
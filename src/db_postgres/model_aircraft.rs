@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction, types::Json};
 
 use crate::{
     S,
     api::{AircraftSearch, AppError, ModeS, Registration, ResponseAircraft},
     db_postgres::ID,
     generic_id, redis_hash_to_struct,
-    scraper::PhotoData,
+    scraper::{MAX_PHOTOS, PhotoData},
 };
 
 generic_id!(Country);
@@ -41,10 +41,29 @@ pub struct ModelAircraft {
     pub registered_owner: String,
     pub url_photo: Option<String>,
     pub url_photo_thumbnail: Option<String>,
+    /// Every stored photo, in gallery order, the first is the same as url_photo
+    #[serde(default)]
+    pub photos: Json<Vec<ModelAircraftPhoto>>,
 }
 
 redis_hash_to_struct!(ModelAircraft);
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct ModelAircraftPhoto {
+    pub url_photo: String,
+    pub url_photo_thumbnail: String,
+    pub photographer: Option<String>,
+    pub url_page: Option<String>,
+}
+
+/// An aircraft with at least one photo in its gallery that has no photographer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelMissingPhotographer {
+    pub aircraft_id: AircraftId,
+    pub mode_s: ModeS,
+    pub registration: Registration,
+}
+
 impl ModelAircraft {
     pub async fn get_random(db: &PgPool, photo_prefix: &str) -> Result<Self, AppError> {
         Ok(sqlx::query_as!(
@@ -75,7 +94,27 @@ SELECT
     CASE 
         WHEN ap.url_photo IS NOT NULL 
         THEN CONCAT($1::TEXT, 'thumbnails/', ap.url_photo) 
-    END AS url_photo_thumbnail
+    END AS url_photo_thumbnail,
+    COALESCE(
+        (
+            SELECT
+                jsonb_agg(
+                    jsonb_build_object(
+                        'url_photo', CONCAT($1::TEXT, gp.url_photo),
+                        'url_photo_thumbnail', CONCAT($1::TEXT, 'thumbnails/', gp.url_photo),
+                        'photographer', gp.photographer,
+                        'url_page', gp.url_page
+                    )
+                    ORDER BY ag.position
+                )
+            FROM
+                aircraft_gallery ag
+                JOIN aircraft_photo gp USING(aircraft_photo_id)
+            WHERE
+                ag.aircraft_id = aa.aircraft_id
+        ),
+        '[]'
+    ) AS "photos!: Json<Vec<ModelAircraftPhoto>>"
 FROM aircraft aa
 JOIN random_aircraft ra ON ra.aircraft_id = aa.aircraft_id
 JOIN aircraft_mode_s ams USING (aircraft_mode_s_id)
@@ -120,7 +159,27 @@ SELECT
     CASE
         WHEN ap.url_photo IS NOT NULL THEN CONCAT($2::TEXT, 'thumbnails/', ap.url_photo)
         ELSE NULL
-    END AS url_photo_thumbnail
+    END AS url_photo_thumbnail,
+    COALESCE(
+        (
+            SELECT
+                jsonb_agg(
+                    jsonb_build_object(
+                        'url_photo', CONCAT($2::TEXT, gp.url_photo),
+                        'url_photo_thumbnail', CONCAT($2::TEXT, 'thumbnails/', gp.url_photo),
+                        'photographer', gp.photographer,
+                        'url_page', gp.url_page
+                    )
+                    ORDER BY ag.position
+                )
+            FROM
+                aircraft_gallery ag
+                JOIN aircraft_photo gp USING(aircraft_photo_id)
+            WHERE
+                ag.aircraft_id = aa.aircraft_id
+        ),
+        '[]'
+    ) AS "photos!: Json<Vec<ModelAircraftPhoto>>"
 FROM
     aircraft aa
     JOIN aircraft_mode_s ams USING(aircraft_mode_s_id)
@@ -168,7 +227,27 @@ SELECT
     CASE
         WHEN ap.url_photo IS NOT NULL THEN CONCAT($2::TEXT, 'thumbnails/', ap.url_photo)
         ELSE NULL
-    END AS url_photo_thumbnail
+    END AS url_photo_thumbnail,
+    COALESCE(
+        (
+            SELECT
+                jsonb_agg(
+                    jsonb_build_object(
+                        'url_photo', CONCAT($2::TEXT, gp.url_photo),
+                        'url_photo_thumbnail', CONCAT($2::TEXT, 'thumbnails/', gp.url_photo),
+                        'photographer', gp.photographer,
+                        'url_page', gp.url_page
+                    )
+                    ORDER BY ag.position
+                )
+            FROM
+                aircraft_gallery ag
+                JOIN aircraft_photo gp USING(aircraft_photo_id)
+            WHERE
+                ag.aircraft_id = aa.aircraft_id
+        ),
+        '[]'
+    ) AS "photos!: Json<Vec<ModelAircraftPhoto>>"
 FROM
     aircraft aa
     JOIN aircraft_mode_s ams USING(aircraft_mode_s_id)
@@ -204,56 +283,212 @@ WHERE
         })
     }
 
+    /// Search for multiple aircraft, by mode_s and/or registration, in a single query
+    pub async fn get_many(
+        db: &PgPool,
+        aircraft_searches: &[AircraftSearch],
+        photo_prefix: &str,
+    ) -> Result<Vec<Self>, AppError> {
+        let (mut mode_s, mut registration) = (vec![], vec![]);
+        for i in aircraft_searches {
+            match i {
+                AircraftSearch::ModeS(_) => mode_s.push(i.to_string()),
+                AircraftSearch::Registration(_) => registration.push(i.to_string()),
+            }
+        }
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    aa.aircraft_id,
+    ams.mode_s,
+    ar.registration AS "registration!: _",
+    aro.registered_owner,
+    aof.operator_flag_code AS "registered_owner_operator_flag_code?",
+    co.country_name AS registered_owner_country_name,
+    co.country_iso_name AS registered_owner_country_iso_name,
+    am.manufacturer,
+    at.type AS aircraft_type,
+    ait.icao_type,
+    CASE
+        WHEN ap.url_photo IS NOT NULL THEN CONCAT($3::TEXT, ap.url_photo)
+        ELSE NULL
+    END AS url_photo,
+    CASE
+        WHEN ap.url_photo IS NOT NULL THEN CONCAT($3::TEXT, 'thumbnails/', ap.url_photo)
+        ELSE NULL
+    END AS url_photo_thumbnail,
+    COALESCE(
+        (
+            SELECT
+                jsonb_agg(
+                    jsonb_build_object(
+                        'url_photo', CONCAT($3::TEXT, gp.url_photo),
+                        'url_photo_thumbnail', CONCAT($3::TEXT, 'thumbnails/', gp.url_photo),
+                        'photographer', gp.photographer,
+                        'url_page', gp.url_page
+                    )
+                    ORDER BY ag.position
+                )
+            FROM
+                aircraft_gallery ag
+                JOIN aircraft_photo gp USING(aircraft_photo_id)
+            WHERE
+                ag.aircraft_id = aa.aircraft_id
+        ),
+        '[]'
+    ) AS "photos!: Json<Vec<ModelAircraftPhoto>>"
+FROM
+    aircraft aa
+    JOIN aircraft_mode_s ams USING(aircraft_mode_s_id)
+    JOIN country co USING(country_id)
+    JOIN aircraft_registration ar USING(aircraft_registration_id)
+    JOIN aircraft_type at USING(aircraft_type_id)
+    JOIN aircraft_registered_owner aro USING(aircraft_registered_owner_id)
+    JOIN aircraft_icao_type ait USING(aircraft_icao_type_id)
+    JOIN aircraft_manufacturer am USING(aircraft_manufacturer_id)
+    LEFT JOIN aircraft_operator_flag_code aof USING(aircraft_operator_flag_code_id)
+    LEFT JOIN aircraft_photo ap USING(aircraft_photo_id)
+WHERE
+    ams.mode_s = ANY($1)
+    OR ar.registration = ANY($2)"#,
+            &mode_s,
+            &registration,
+            photo_prefix
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Replace the aircraft's gallery with the given photos, in order, and set the first as the aircraft's photo.
+    /// Photos that are already stored have their photographer and url_page updated
     pub async fn photo_transaction(
         transaction: &mut Transaction<'_, Postgres>,
-        photo: PhotoData,
+        photos: &[PhotoData],
         mode_s: &ModeS,
     ) -> Result<(), AppError> {
-        let aircraft_photo = sqlx::query_as!(
-            ID::<AircraftPhoto>,
+        let Some(aircraft_id) = sqlx::query_as!(
+            ID::<AircraftId>,
             "
+SELECT
+    a.aircraft_id AS id
+FROM
+    aircraft a
+    JOIN aircraft_mode_s ams USING (aircraft_mode_s_id)
+WHERE
+    ams.mode_s = $1
+LIMIT 1",
+            mode_s.to_string()
+        )
+        .fetch_optional(&mut **transaction)
+        .await?
+        .map(|i| i.id) else {
+            return Ok(());
+        };
+
+        let mut aircraft_photo_ids = vec![];
+        for photo in photos.iter().take(MAX_PHOTOS) {
+            aircraft_photo_ids.push(
+                sqlx::query_as!(
+                    ID::<AircraftPhoto>,
+                    "
 INSERT INTO
-    aircraft_photo(url_photo)
+    aircraft_photo(url_photo, photographer, url_page)
 VALUES
-    ($1)
+    ($1, $2, $3)
+ON CONFLICT (url_photo) DO UPDATE SET
+    photographer = EXCLUDED.photographer,
+    url_page = EXCLUDED.url_page
 RETURNING
     aircraft_photo_id AS id",
-            photo.image
+                    photo.image,
+                    photo.photographer,
+                    photo.link
+                )
+                .fetch_one(&mut **transaction)
+                .await?
+                .id
+                .get(),
+            );
+        }
+
+        sqlx::query!(
+            "DELETE FROM aircraft_gallery WHERE aircraft_id = $1",
+            aircraft_id.get()
         )
-        .fetch_one(&mut **transaction)
-        .await?
-        .id;
+        .execute(&mut **transaction)
+        .await?;
 
         sqlx::query!(
             "
-UPDATE
-    aircraft
-SET
-    aircraft_photo_id = $1
-WHERE
-    aircraft_id = (
-        SELECT a.aircraft_id
-        FROM aircraft a
-        JOIN aircraft_mode_s ams USING (aircraft_mode_s_id)
-        WHERE ams.mode_s = $2
-        LIMIT 1
-    );",
-            aircraft_photo.0,
-            mode_s.to_string()
+INSERT INTO
+    aircraft_gallery(aircraft_id, aircraft_photo_id, position)
+SELECT
+    $1, aircraft_photo_id, (position - 1)::SMALLINT
+FROM
+    UNNEST($2::BIGINT[]) WITH ORDINALITY AS gallery(aircraft_photo_id, position)",
+            aircraft_id.get(),
+            &aircraft_photo_ids
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE aircraft SET aircraft_photo_id = $1 WHERE aircraft_id = $2",
+            aircraft_photo_ids.first(),
+            aircraft_id.get()
         )
         .execute(&mut **transaction)
         .await?;
         Ok(())
     }
 
-    /// Insert a new flightroute based on scraped data, separated transaction so can be tested with a rollback
+    /// The first aircraft, ordered by aircraft_id, after the given aircraft_id, with a gallery photo that has no photographer
+    pub async fn get_missing_photographer(
+        db: &PgPool,
+        after: Option<AircraftId>,
+    ) -> Result<Option<ModelMissingPhotographer>, AppError> {
+        Ok(sqlx::query_as!(
+            ModelMissingPhotographer,
+            r#"
+SELECT
+    aa.aircraft_id,
+    ams.mode_s AS "mode_s!: _",
+    ar.registration
+FROM
+    aircraft aa
+    JOIN aircraft_mode_s ams USING(aircraft_mode_s_id)
+    JOIN aircraft_registration ar USING(aircraft_registration_id)
+WHERE
+    aa.aircraft_id > $1
+    AND EXISTS (
+        SELECT
+            1
+        FROM
+            aircraft_gallery ag
+            JOIN aircraft_photo ap USING(aircraft_photo_id)
+        WHERE
+            ag.aircraft_id = aa.aircraft_id
+            AND ap.photographer IS NULL
+    )
+ORDER BY
+    aa.aircraft_id
+LIMIT
+    1"#,
+            after.map_or(0, |i| i.get())
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Insert photos based on scraped data, separated transaction so can be tested with a rollback
     pub async fn insert_photo(
         db: &PgPool,
-        photo: PhotoData,
+        photos: &[PhotoData],
         mode_s: &ModeS,
     ) -> Result<(), AppError> {
         let mut transaction = db.begin().await?;
-        Self::photo_transaction(&mut transaction, photo, mode_s).await?;
+        Self::photo_transaction(&mut transaction, photos, mode_s).await?;
         transaction.commit().await?;
         Ok(())
     }
@@ -538,9 +773,18 @@ mod tests {
 
         let mut transaction = test_setup.postgres.begin().await.unwrap();
 
-        let photodata = PhotoData {
-            image: S!("example.jpg"),
-        };
+        let photodata = [
+            PhotoData {
+                image: S!("example.jpg"),
+                link: Some(S!("http://www.example.com/photo/example.html")),
+                photographer: Some(S!("Example Photographer")),
+            },
+            PhotoData {
+                image: S!("example_2.jpg"),
+                link: None,
+                photographer: None,
+            },
+        ];
 
         let url_prefix = "http://www.example.com/";
 
@@ -557,9 +801,10 @@ mod tests {
             registered_owner: S!("United Express"),
             url_photo: None,
             url_photo_thumbnail: None,
+            photos: Json(vec![]),
         };
 
-        ModelAircraft::photo_transaction(&mut transaction, photodata, &test_aircraft.mode_s)
+        ModelAircraft::photo_transaction(&mut transaction, &photodata, &test_aircraft.mode_s)
             .await
             .unwrap();
 
@@ -585,6 +830,20 @@ mod tests {
             registered_owner: S!("United Express"),
             url_photo: Some(S!("http://www.example.com/example.jpg")),
             url_photo_thumbnail: Some(S!("http://www.example.com/thumbnails/example.jpg")),
+            photos: Json(vec![
+                ModelAircraftPhoto {
+                    url_photo: S!("http://www.example.com/example.jpg"),
+                    url_photo_thumbnail: S!("http://www.example.com/thumbnails/example.jpg"),
+                    photographer: Some(S!("Example Photographer")),
+                    url_page: Some(S!("http://www.example.com/photo/example.html")),
+                },
+                ModelAircraftPhoto {
+                    url_photo: S!("http://www.example.com/example_2.jpg"),
+                    url_photo_thumbnail: S!("http://www.example.com/thumbnails/example_2.jpg"),
+                    photographer: None,
+                    url_page: None,
+                },
+            ]),
         };
 
         assert_eq!(result, expected);
@@ -608,6 +867,7 @@ mod tests {
             registered_owner: S!("United Express"),
             url_photo: Some(S!("http://www.example.com/example.jpg")),
             url_photo_thumbnail: Some(S!("http://www.example.com/thumbnails/example.jpg")),
+            photos: Json(vec![]),
         }
     }
 
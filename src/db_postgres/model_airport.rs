@@ -1,34 +1,163 @@
 use serde::{Deserialize, Serialize};
 use sqlx::PgExecutor;
 
-use crate::{api::AppError, generic_id};
+use crate::{
+    api::{AirportCode, AirportType, AppError},
+    generic_id,
+    geodesy::BoundingBox,
+    redis_hash_to_struct,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ModelAirport {
     pub airport_id: AirportId,
+    pub country_iso_name: String,
+    pub country_name: String,
+    pub elevation: i32,
+    pub iata_code: Option<String>,
+    pub icao_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub municipality: String,
+    pub name: String,
 }
 
 generic_id!(AirportId);
 
+redis_hash_to_struct!(ModelAirport);
+
 impl ModelAirport {
-    /// Used for checking that a scraped airport is in db
+    /// Search for an airport by ICAO code, also used for checking that a scraped airport is in db
     pub async fn get(
         db: impl PgExecutor<'_>,
         airport_icao: &str,
     ) -> Result<Option<Self>, AppError> {
         Ok(sqlx::query_as!(
             Self,
-            "
+            r#"
 SELECT
-    airport_id
+    ap.airport_id,
+    co.country_iso_name,
+    co.country_name,
+    ae.elevation AS "elevation!",
+    aia.iata_code AS "iata_code?",
+    aic.icao_code,
+    ala.latitude AS "latitude!",
+    alo.longitude AS "longitude!",
+    am.municipality AS "municipality!",
+    an.name AS "name!"
 FROM
-    airport
-    JOIN airport_icao_code ar USING (airport_icao_code_id)
+    airport ap
+    JOIN airport_icao_code aic USING (airport_icao_code_id)
+    JOIN country co USING (country_id)
+    JOIN airport_elevation ae USING (airport_elevation_id)
+    LEFT JOIN airport_iata_code aia USING (airport_iata_code_id)
+    JOIN airport_latitude ala USING (airport_latitude_id)
+    JOIN airport_longitude alo USING (airport_longitude_id)
+    JOIN airport_municipality am USING (airport_municipality_id)
+    JOIN airport_name an USING (airport_name_id)
 WHERE
-    ar.icao_code = UPPER($1)",
+    aic.icao_code = UPPER($1)"#,
             airport_icao
         )
         .fetch_optional(db)
         .await?)
     }
+
+    /// Search for an airport by IATA code
+    async fn get_by_iata(
+        db: impl PgExecutor<'_>,
+        airport_iata: &str,
+    ) -> Result<Option<Self>, AppError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    ap.airport_id,
+    co.country_iso_name,
+    co.country_name,
+    ae.elevation AS "elevation!",
+    aia.iata_code AS "iata_code?",
+    aic.icao_code,
+    ala.latitude AS "latitude!",
+    alo.longitude AS "longitude!",
+    am.municipality AS "municipality!",
+    an.name AS "name!"
+FROM
+    airport ap
+    JOIN airport_iata_code aia USING (airport_iata_code_id)
+    JOIN airport_icao_code aic USING (airport_icao_code_id)
+    JOIN country co USING (country_id)
+    JOIN airport_elevation ae USING (airport_elevation_id)
+    JOIN airport_latitude ala USING (airport_latitude_id)
+    JOIN airport_longitude alo USING (airport_longitude_id)
+    JOIN airport_municipality am USING (airport_municipality_id)
+    JOIN airport_name an USING (airport_name_id)
+WHERE
+    aia.iata_code = UPPER($1)"#,
+            airport_iata
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Find airport by either iata or icao code
+    pub async fn get_by_airport_code(
+        db: impl PgExecutor<'_>,
+        airport_code: &AirportCode,
+    ) -> Result<Option<Self>, AppError> {
+        match airport_code {
+            AirportCode::Iata(x) => Self::get_by_iata(db, x).await,
+            AirportCode::Icao(x) => Self::get(db, x).await,
+        }
+    }
+
+    /// Find every airport inside a bounding box, optionally of a given type
+    /// Box can cross the antimeridian, in which case min_longitude is greater than max_longitude
+    pub async fn get_within(
+        db: impl PgExecutor<'_>,
+        bounding_box: &BoundingBox,
+        airport_type: Option<AirportType>,
+    ) -> Result<Vec<Self>, AppError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    ap.airport_id,
+    co.country_iso_name,
+    co.country_name,
+    ae.elevation AS "elevation!",
+    aia.iata_code AS "iata_code?",
+    aic.icao_code,
+    ala.latitude AS "latitude!",
+    alo.longitude AS "longitude!",
+    am.municipality AS "municipality!",
+    an.name AS "name!"
+FROM
+    airport ap
+    JOIN airport_icao_code aic USING (airport_icao_code_id)
+    JOIN country co USING (country_id)
+    JOIN airport_elevation ae USING (airport_elevation_id)
+    LEFT JOIN airport_iata_code aia USING (airport_iata_code_id)
+    JOIN airport_latitude ala USING (airport_latitude_id)
+    JOIN airport_longitude alo USING (airport_longitude_id)
+    JOIN airport_municipality am USING (airport_municipality_id)
+    JOIN airport_name an USING (airport_name_id)
+    JOIN airport_type aty USING (airport_type_id)
+WHERE
+    ala.latitude BETWEEN $1 AND $2
+    AND (
+        alo.longitude BETWEEN $3 AND $4
+        OR ($3 > $4 AND (alo.longitude >= $3 OR alo.longitude <= $4))
+    )
+    AND ($5::TEXT IS NULL OR aty.type = $5)"#,
+            bounding_box.min_latitude,
+            bounding_box.max_latitude,
+            bounding_box.min_longitude,
+            bounding_box.max_longitude,
+            airport_type.map(AirportType::as_str)
+        )
+        .fetch_all(db)
+        .await?)
+    }
 }
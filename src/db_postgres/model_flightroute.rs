@@ -1,9 +1,11 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use sqlx::{AssertSqlSafe, PgPool};
 
 use crate::{
     S,
-    api::{AppError, Callsign},
+    api::{AppError, Callsign, RouteDirection},
     generic_id, redis_hash_to_struct,
     scraper::ScrapedFlightroute,
 };
@@ -64,6 +66,14 @@ pub struct ModelFlightroute {
 redis_hash_to_struct!(ModelFlightroute);
 generic_id!(FlightrouteId);
 
+/// A flightroute, alongside the callsign that was used to find it, used in the batch query
+#[derive(sqlx::FromRow, Debug)]
+struct BatchFlightroute {
+    search_callsign: String,
+    #[sqlx(flatten)]
+    flightroute: ModelFlightroute,
+}
+
 impl ModelFlightroute {
     /// Get a random flightroute
     /// Recursive ish function, using loops otherwise the async call stack gets silly
@@ -193,6 +203,8 @@ SELECT
     {}
 WHERE
     fci.callsign = $1
+ORDER BY
+    fl.flightroute_id
 LIMIT
     1",
             Self::get_query_joins()
@@ -318,6 +330,66 @@ SELECT
     ai.icao_prefix AS airline_icao,"
     }
 
+    /// Start of a query where the callsign isn't known in advance, so is built from the stored ICAO callsign
+    const fn get_query_selects_stored() -> &'static str {
+        r"
+SELECT
+    fl.flightroute_id,
+    concat(
+        ai.icao_prefix,
+        (
+            SELECT
+                callsign
+            FROM
+                flightroute_callsign_inner
+            WHERE
+                flightroute_callsign_inner_id = flc.icao_prefix_id
+        )
+    ) AS callsign,
+    concat(
+        ai.iata_prefix,
+        (
+            SELECT
+                callsign
+            FROM
+                flightroute_callsign_inner
+            WHERE
+                flightroute_callsign_inner_id = flc.iata_prefix_id
+        )
+    ) AS callsign_iata,
+    concat(
+        ai.icao_prefix,
+        (
+            SELECT
+                callsign
+            FROM
+                flightroute_callsign_inner
+            WHERE
+                flightroute_callsign_inner_id = flc.icao_prefix_id
+        )
+    ) AS callsign_icao,
+    (
+        SELECT
+            country_iso_name
+        FROM
+            country
+        WHERE
+            country_id = ai.country_id
+    ) AS airline_country_iso_name,
+    (
+        SELECT
+            country_name
+        FROM
+            country
+        WHERE
+            country_id = ai.country_id
+    ) AS airline_country_name,
+    ai.airline_callsign,
+    ai.airline_name,
+    ai.iata_prefix AS airline_iata,
+    ai.icao_prefix AS airline_icao,"
+    }
+
     /// Query for flightroute based on IATA callsign
     /// TODO where iata prefix is not null?
     fn get_query_iata() -> String {
@@ -334,7 +406,11 @@ WHERE
             flightroute_callsign_inner
         WHERE
             callsign = $2
-    )",
+    )
+ORDER BY
+    fl.flightroute_id
+LIMIT
+    1",
             Self::get_query_selects(),
             Self::get_query_joins()
         )
@@ -362,13 +438,18 @@ WHERE
             flightroute_callsign_inner
         WHERE
             callsign = $2
-    )",
+    )
+ORDER BY
+    fl.flightroute_id
+LIMIT
+    1",
             Self::get_query_selects(),
             Self::get_query_joins()
         )
     }
 
     /// Query for a fully joined Option<ModelFlightRoute>
+    /// A callsign can have more than one flightroute row, so the lowest flightroute_id is used, as it is in `get_many()`
     /// Don't return result, as issues with nulls in the database, that I can't be bothered to deal with at the moment
     pub async fn get(db: &PgPool, callsign: &Callsign) -> Option<Self> {
         //
@@ -411,6 +492,239 @@ WHERE
         }
     }
 
+    /// Query for multiple callsigns in a single set based query.
+    /// ICAO and IATA callsigns are matched on airline prefix and suffix, and fall back to a direct callsign match, as per `get()`.
+    /// Where a callsign has more than one flightroute row, the lowest flightroute_id is used, so that the result is the same as `get()`.
+    /// Returned map is keyed by the callsign's `to_string()`, callsigns without a flightroute will be absent
+    pub async fn get_many(
+        db: &PgPool,
+        callsigns: &[Callsign],
+    ) -> Result<HashMap<String, Self>, AppError> {
+        if callsigns.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let (mut full, mut prefix, mut suffix, mut kind) = (vec![], vec![], vec![], vec![]);
+        for callsign in callsigns {
+            full.push(callsign.to_string());
+            match callsign {
+                Callsign::Icao(x) | Callsign::Iata(x) => {
+                    prefix.push(Some(x.0.clone()));
+                    suffix.push(Some(x.1.clone()));
+                }
+                Callsign::Other(_) => {
+                    prefix.push(None);
+                    suffix.push(None);
+                }
+            }
+            kind.push(match callsign {
+                Callsign::Icao(_) => "icao",
+                Callsign::Iata(_) => "iata",
+                Callsign::Other(_) => "other",
+            });
+        }
+
+        let query = format!(
+            r"
+WITH search AS (
+    SELECT
+        *
+    FROM
+        UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[]) AS s(search_callsign, prefix, suffix, kind)
+),
+matched AS (
+    SELECT DISTINCT ON (search_callsign)
+        search_callsign,
+        flightroute_id,
+        other
+    FROM (
+        SELECT
+            s.search_callsign,
+            fl.flightroute_id,
+            FALSE AS other
+        FROM
+            search s
+            INNER JOIN airline ai ON ai.icao_prefix = s.prefix
+            INNER JOIN flightroute_callsign flc ON flc.airline_id = ai.airline_id
+            INNER JOIN flightroute_callsign_inner fci ON fci.flightroute_callsign_inner_id = flc.icao_prefix_id
+            INNER JOIN flightroute fl USING(flightroute_callsign_id)
+        WHERE
+            s.kind = 'icao'
+            AND fci.callsign = s.suffix
+        UNION ALL
+        SELECT
+            s.search_callsign,
+            fl.flightroute_id,
+            FALSE AS other
+        FROM
+            search s
+            INNER JOIN airline ai ON ai.iata_prefix = s.prefix
+            INNER JOIN flightroute_callsign flc ON flc.airline_id = ai.airline_id
+            INNER JOIN flightroute_callsign_inner fci ON fci.flightroute_callsign_inner_id = flc.iata_prefix_id
+            INNER JOIN flightroute fl USING(flightroute_callsign_id)
+        WHERE
+            s.kind = 'iata'
+            AND fci.callsign = s.suffix
+        UNION ALL
+        SELECT
+            s.search_callsign,
+            fl.flightroute_id,
+            TRUE AS other
+        FROM
+            search s
+            INNER JOIN flightroute_callsign_inner fci ON fci.callsign = s.search_callsign
+            INNER JOIN flightroute_callsign flc ON flc.callsign_id = fci.flightroute_callsign_inner_id
+            INNER JOIN flightroute fl USING(flightroute_callsign_id)
+    ) m
+    ORDER BY
+        search_callsign,
+        other,
+        flightroute_id
+)
+SELECT
+    m.search_callsign,
+    fl.flightroute_id,
+    m.search_callsign AS callsign,
+    CASE WHEN m.other THEN NULL ELSE concat(
+        ai.iata_prefix,
+        (
+            SELECT
+                callsign
+            FROM
+                flightroute_callsign_inner
+            WHERE
+                flightroute_callsign_inner_id = flc.iata_prefix_id
+        )
+    ) END AS callsign_iata,
+    CASE WHEN m.other THEN NULL ELSE concat(
+        ai.icao_prefix,
+        (
+            SELECT
+                callsign
+            FROM
+                flightroute_callsign_inner
+            WHERE
+                flightroute_callsign_inner_id = flc.icao_prefix_id
+        )
+    ) END AS callsign_icao,
+    CASE WHEN m.other THEN NULL ELSE (
+        SELECT
+            country_iso_name
+        FROM
+            country
+        WHERE
+            country_id = ai.country_id
+    ) END AS airline_country_iso_name,
+    CASE WHEN m.other THEN NULL ELSE (
+        SELECT
+            country_name
+        FROM
+            country
+        WHERE
+            country_id = ai.country_id
+    ) END AS airline_country_name,
+    CASE WHEN m.other THEN NULL ELSE ai.airline_callsign END AS airline_callsign,
+    CASE WHEN m.other THEN NULL ELSE ai.airline_name END AS airline_name,
+    CASE WHEN m.other THEN NULL ELSE ai.iata_prefix END AS airline_iata,
+    CASE WHEN m.other THEN NULL ELSE ai.icao_prefix END AS airline_icao,
+    {}
+    INNER JOIN matched m ON m.flightroute_id = fl.flightroute_id",
+            Self::get_query_joins()
+        );
+
+        Ok(sqlx::query_as::<_, BatchFlightroute>(AssertSqlSafe(query))
+            .bind(full)
+            .bind(prefix)
+            .bind(suffix)
+            .bind(kind)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|i| (i.search_callsign, i.flightroute))
+            .collect())
+    }
+
+    /// Get a page of flightroutes that use a given airport, ordered by flightroute_id.
+    /// Only flightroutes with a flightroute_id greater than the cursor are returned
+    pub async fn get_by_airport(
+        db: &PgPool,
+        airport: &ModelAirport,
+        direction: RouteDirection,
+        cursor: Option<FlightrouteId>,
+        limit: i64,
+    ) -> Result<Vec<Self>, AppError> {
+        let route_ids = match direction {
+            RouteDirection::Origin => {
+                "SELECT flightroute_id FROM flightroute WHERE airport_origin_id = $1 AND flightroute_id > $2"
+            }
+            RouteDirection::Destination => {
+                "SELECT flightroute_id FROM flightroute WHERE airport_destination_id = $1 AND flightroute_id > $2"
+            }
+            RouteDirection::Any => {
+                "
+    SELECT flightroute_id FROM flightroute WHERE airport_origin_id = $1 AND flightroute_id > $2
+    UNION
+    SELECT flightroute_id FROM flightroute WHERE airport_midpoint_id = $1 AND flightroute_id > $2
+    UNION
+    SELECT flightroute_id FROM flightroute WHERE airport_destination_id = $1 AND flightroute_id > $2"
+            }
+        };
+        let query = format!(
+            r"
+WITH route_ids AS (
+    {route_ids}
+)
+{}
+{}
+    INNER JOIN route_ids ri ON ri.flightroute_id = fl.flightroute_id
+ORDER BY
+    fl.flightroute_id
+LIMIT
+    $3",
+            Self::get_query_selects_stored(),
+            Self::get_query_joins()
+        );
+        Ok(sqlx::query_as::<_, Self>(AssertSqlSafe(query))
+            .bind(airport.airport_id.get())
+            .bind(cursor.map_or(0, |i| i.get()))
+            .bind(limit)
+            .fetch_all(db)
+            .await?)
+    }
+
+    /// Get a page of flightroutes operated by the given airlines, ordered by flightroute_id.
+    /// Only flightroutes with a flightroute_id greater than the cursor are returned
+    pub async fn get_by_airlines(
+        db: &PgPool,
+        airlines: &[ModelAirline],
+        cursor: Option<FlightrouteId>,
+        limit: i64,
+    ) -> Result<Vec<Self>, AppError> {
+        let airline_ids = airlines
+            .iter()
+            .map(|i| i.airline_id.get())
+            .collect::<Vec<_>>();
+        let query = format!(
+            r"
+{}
+{}
+WHERE
+    flc.airline_id = ANY($1)
+    AND fl.flightroute_id > $2
+ORDER BY
+    fl.flightroute_id
+LIMIT
+    $3",
+            Self::get_query_selects_stored(),
+            Self::get_query_joins()
+        );
+        Ok(sqlx::query_as::<_, Self>(AssertSqlSafe(query))
+            .bind(airline_ids)
+            .bind(cursor.map_or(0, |i| i.get()))
+            .bind(limit)
+            .fetch_all(db)
+            .await?)
+    }
+
     /// Transaction to insert, and return, a new flightroute,
     pub async fn insert_scraped_flightroute(
         db: &PgPool,
@@ -421,7 +735,13 @@ WHERE
         {
             let origin = ModelAirport::get(db, &scraped_flightroute.origin).await?;
             let destination = ModelAirport::get(db, &scraped_flightroute.destination).await?;
-            if let (Some(origin), Some(destination)) = (origin, destination) {
+            let midpoint = match scraped_flightroute.midpoint.as_ref() {
+                Some(midpoint) => ModelAirport::get(db, midpoint).await?.map(Some),
+                None => Some(None),
+            };
+            if let (Some(origin), Some(midpoint), Some(destination)) =
+                (origin, midpoint, destination)
+            {
                 let mut transaction = db.begin().await?;
                 sqlx::query!(
                     "
@@ -499,12 +819,15 @@ RETURNING
 INSERT INTO
     flightroute (
         airport_origin_id,
+        airport_midpoint_id,
         airport_destination_id,
-        flightroute_callsign_id
+        flightroute_callsign_id,
+        validated_at
     )
 VALUES
-    ($1, $2, $3)",
+    ($1, $2, $3, $4, CURRENT_TIMESTAMP)",
                     origin.airport_id.get(),
+                    midpoint.map(|i| i.airport_id.get()),
                     destination.airport_id.get(),
                     flighroute_callsign_id.id,
                 )
@@ -516,14 +839,67 @@ VALUES
         Ok(Self::get(db, &scraped_flightroute.callsign_icao).await)
     }
 
+    /// Get the scraped flightroute that was least recently validated, as long as it's older than max_age_days
+    pub async fn get_stale(db: &PgPool, max_age_days: u16) -> Result<Option<Self>, AppError> {
+        let query = format!(
+            r"
+{}
+{}
+WHERE
+    fl.validated_at < CURRENT_TIMESTAMP - make_interval(days => $1)
+ORDER BY
+    fl.validated_at
+LIMIT
+    1",
+            Self::get_query_selects_stored(),
+            Self::get_query_joins()
+        );
+        Ok(sqlx::query_as::<_, Self>(AssertSqlSafe(query))
+            .bind(i32::from(max_age_days))
+            .fetch_optional(db)
+            .await?)
+    }
+
+    /// Mark the flightroute as validated, without changing any airports
+    pub async fn validated(&self, postgres: &PgPool) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE flightroute SET validated_at = CURRENT_TIMESTAMP WHERE flightroute_id = $1",
+            self.flightroute_id.get()
+        )
+        .execute(postgres)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark the flightroute as validated, such that it'll be stale again, given the same max_age_days, after the given seconds
+    pub async fn stale_in(
+        &self,
+        postgres: &PgPool,
+        max_age_days: u16,
+        secs: i64,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE flightroute SET validated_at = CURRENT_TIMESTAMP - make_interval(days => $1) + make_interval(secs => $2::BIGINT) WHERE flightroute_id = $3",
+            i32::from(max_age_days),
+            secs,
+            self.flightroute_id.get()
+        )
+        .execute(postgres)
+        .await?;
+        Ok(())
+    }
+
+    /// Change the airports, and clear validated_at, so that an admin correction isn't overwritten by revalidation,
+    /// the revalidator marks its own updates as validated
     pub async fn update(
         &self,
         postgres: &PgPool,
         origin: ModelAirport,
+        midpoint: Option<ModelAirport>,
         destination: ModelAirport,
     ) -> Result<(), AppError> {
-        sqlx::query!("UPDATE flightroute SET airport_origin_id = $1, airport_destination_id = $2 WHERE flightroute_id = $3",
-        origin.airport_id.get(), destination.airport_id.get(), self.flightroute_id.get())
+        sqlx::query!("UPDATE flightroute SET airport_origin_id = $1, airport_midpoint_id = $2, airport_destination_id = $3, validated_at = NULL WHERE flightroute_id = $4",
+        origin.airport_id.get(), midpoint.map(|i| i.airport_id.get()), destination.airport_id.get(), self.flightroute_id.get())
         .execute(postgres)
         .await?;
 
@@ -566,6 +942,7 @@ mod tests {
             callsign_icao: Callsign::Icao((S!("ANA"), S!("000"))),
             callsign_iata: Callsign::Iata((S!("NH"), S!("000"))),
             origin: S!("ROAH"),
+            midpoint: None,
             destination: S!("RJTT"),
         };
 
@@ -626,6 +1003,89 @@ mod tests {
         remove_flightroute(&setup.1, &scraped_flightroute).await;
     }
 
+    #[tokio::test]
+    async fn model_flightroute_scraped_flightroute_midpoint() {
+        let setup = setup().await;
+
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("002"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("002"))),
+            origin: S!("ROAH"),
+            midpoint: Some(S!("RJFF")),
+            destination: S!("RJTT"),
+        };
+        let result = ModelFlightroute::insert_scraped_flightroute(&setup.1, &scraped_flightroute)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.origin_airport_icao_code, "ROAH");
+        assert_eq!(result.midpoint_airport_icao_code, Some(S!("RJFF")));
+        assert_eq!(result.midpoint_airport_iata_code, Some(S!("FUK")));
+        assert_eq!(result.destination_airport_icao_code, "RJTT");
+        remove_flightroute(&setup.1, &scraped_flightroute).await;
+
+        // Unknown midpoint, so nothing is inserted
+        let scraped_flightroute = ScrapedFlightroute {
+            midpoint: Some(S!("ZZZZ")),
+            ..scraped_flightroute
+        };
+        let result = ModelFlightroute::insert_scraped_flightroute(&setup.1, &scraped_flightroute)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn model_flightroute_get_stale() {
+        let setup = setup().await;
+
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("001"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("001"))),
+            origin: S!("ROAH"),
+            midpoint: None,
+            destination: S!("RJTT"),
+        };
+        let flightroute =
+            ModelFlightroute::insert_scraped_flightroute(&setup.1, &scraped_flightroute)
+                .await
+                .unwrap()
+                .unwrap();
+
+        // Just scraped, so not stale
+        let result = ModelFlightroute::get_stale(&setup.1, 1).await.unwrap();
+        assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+
+        sqlx::query("UPDATE flightroute SET validated_at = '2000-01-01' WHERE flightroute_id = $1")
+            .bind(flightroute.flightroute_id.get())
+            .execute(&setup.1)
+            .await
+            .unwrap();
+        let result = ModelFlightroute::get_stale(&setup.1, 1).await.unwrap();
+        assert_eq!(result, Some(flightroute.clone()));
+
+        flightroute.validated(&setup.1).await.unwrap();
+        let result = ModelFlightroute::get_stale(&setup.1, 1).await.unwrap();
+        assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+
+        // An update, e.g. an admin correction, clears validated_at, so is never stale
+        sqlx::query("UPDATE flightroute SET validated_at = '2000-01-01' WHERE flightroute_id = $1")
+            .bind(flightroute.flightroute_id.get())
+            .execute(&setup.1)
+            .await
+            .unwrap();
+        let origin = ModelAirport::get(&setup.1, "ROAH").await.unwrap().unwrap();
+        let destination = ModelAirport::get(&setup.1, "RJAA").await.unwrap().unwrap();
+        flightroute
+            .update(&setup.1, origin, None, destination)
+            .await
+            .unwrap();
+        let result = ModelFlightroute::get_stale(&setup.1, 1).await.unwrap();
+        assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+
+        remove_flightroute(&setup.1, &scraped_flightroute).await;
+    }
+
     const ICAO_CALLSIGNS: [(&str, &str); 106] = [
         ("AAL177", "AA177"),
         ("AAL2251", "AA2251"),
@@ -762,6 +1222,30 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    /// Check that the batch query matches the single query, for both ICAO and IATA callsigns, and unknown callsigns are absent
+    async fn flightroute_get_many() {
+        let setup = setup().await;
+
+        let mut callsigns = ICAO_CALLSIGNS
+            .iter()
+            .flat_map(|i| [i.0, i.1])
+            .map(|i| Callsign::validate(i).unwrap())
+            .collect::<Vec<_>>();
+        callsigns.push(Callsign::validate("ZZZ9999").unwrap());
+
+        let result = ModelFlightroute::get_many(&setup.1, &callsigns)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), ICAO_CALLSIGNS.len() * 2);
+        assert!(!result.contains_key("ZZZ9999"));
+
+        for callsign in &callsigns[..callsigns.len() - 1] {
+            let single = ModelFlightroute::get(&setup.1, callsign).await.unwrap();
+            assert_eq!(&single, result.get(&callsign.to_string()).unwrap());
+        }
+    }
+
     #[tokio::test]
     /// Just check that a large sample of random flightroutes can be found correctly
     /// This can still fail:(
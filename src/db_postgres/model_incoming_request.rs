@@ -56,7 +56,9 @@ impl From<&Parts> for MsgIncomingRequest {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, utoipa::ToSchema,
+)]
 pub struct EntryCount {
     url: String,
     count: i64,
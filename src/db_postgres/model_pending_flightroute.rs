@@ -0,0 +1,267 @@
+use sqlx::PgPool;
+
+use crate::{
+    api::{AppError, Callsign, Validate},
+    generic_id,
+    scraper::{ScrapeFailure, ScrapedFlightroute},
+};
+
+generic_id!(PendingFlightrouteId);
+
+/// A scraped flightroute that couldn't be inserted, e.g. an unknown airport, waiting to be accepted or rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelPendingFlightroute {
+    pub pending_flightroute_id: PendingFlightrouteId,
+    pub timestamp: jiff_sqlx::Timestamp,
+    pub callsign_icao: String,
+    pub callsign_iata: String,
+    pub origin: String,
+    pub midpoint: Option<String>,
+    pub destination: String,
+    pub reason: String,
+}
+
+impl ModelPendingFlightroute {
+    /// Every pending flightroute that hasn't been rejected, oldest first
+    pub async fn get_all(db: &PgPool) -> Result<Vec<Self>, AppError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    pending_flightroute_id,
+    timestamp AS "timestamp: _",
+    callsign_icao,
+    callsign_iata,
+    origin,
+    midpoint,
+    destination,
+    reason
+FROM
+    pending_flightroute
+WHERE
+    rejected_at IS NULL
+ORDER BY
+    pending_flightroute_id"#
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Get a pending flightroute, None if it doesn't exist, or has been rejected
+    pub async fn get(
+        db: &PgPool,
+        pending_flightroute_id: PendingFlightrouteId,
+    ) -> Result<Option<Self>, AppError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    pending_flightroute_id,
+    timestamp AS "timestamp: _",
+    callsign_icao,
+    callsign_iata,
+    origin,
+    midpoint,
+    destination,
+    reason
+FROM
+    pending_flightroute
+WHERE
+    pending_flightroute_id = $1
+    AND rejected_at IS NULL"#,
+            pending_flightroute_id.get()
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Upsert a scraped flightroute that couldn't be inserted.
+    /// A rejected flightroute is only pending again if it's been scraped with different airports
+    pub async fn insert(
+        db: &PgPool,
+        scraped_flightroute: &ScrapedFlightroute,
+        failure: ScrapeFailure,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "
+INSERT INTO
+    pending_flightroute(callsign_icao, callsign_iata, origin, midpoint, destination, reason)
+VALUES
+    ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (callsign_icao) DO UPDATE SET
+    timestamp = CURRENT_TIMESTAMP,
+    callsign_iata = EXCLUDED.callsign_iata,
+    origin = EXCLUDED.origin,
+    midpoint = EXCLUDED.midpoint,
+    destination = EXCLUDED.destination,
+    reason = EXCLUDED.reason,
+    rejected_at = NULL
+WHERE
+    pending_flightroute.rejected_at IS NULL
+    OR (pending_flightroute.origin, pending_flightroute.midpoint, pending_flightroute.destination)
+        IS DISTINCT FROM (EXCLUDED.origin, EXCLUDED.midpoint, EXCLUDED.destination)",
+            scraped_flightroute.callsign_icao.to_string(),
+            scraped_flightroute.callsign_iata.to_string(),
+            scraped_flightroute.origin,
+            scraped_flightroute.midpoint,
+            scraped_flightroute.destination,
+            failure.as_str()
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove the pending flightroute for an ICAO, or IATA, callsign, after it's been inserted into the flightroute table
+    pub async fn delete_callsign(db: &PgPool, callsign: &Callsign) -> Result<(), AppError> {
+        sqlx::query!(
+            "DELETE FROM pending_flightroute WHERE callsign_icao = $1 OR callsign_iata = $1",
+            callsign.to_string()
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Keep the rejected flightroute, so that it isn't pending again when the callsign is next scraped
+    pub async fn reject(&self, db: &PgPool) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE pending_flightroute SET rejected_at = CURRENT_TIMESTAMP WHERE pending_flightroute_id = $1",
+            self.pending_flightroute_id.get()
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Rebuild the scraped flightroute, None if a stored callsign is no longer valid
+    pub fn scraped_flightroute(&self) -> Option<ScrapedFlightroute> {
+        Some(ScrapedFlightroute {
+            callsign_icao: Callsign::validate(&self.callsign_icao).ok()?,
+            callsign_iata: Callsign::validate(&self.callsign_iata).ok()?,
+            origin: self.origin.clone(),
+            midpoint: self.midpoint.clone(),
+            destination: self.destination.clone(),
+        })
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test model_pending_flightroute '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{S, db_postgres, parse_env::AppEnv};
+
+    async fn setup() -> (PgPool, ScrapedFlightroute) {
+        let app_env = AppEnv::get_env();
+        let db = db_postgres::get_pool(&app_env).await.unwrap();
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: Callsign::validate("ANA003").unwrap(),
+            callsign_iata: Callsign::validate("NH003").unwrap(),
+            origin: S!("ROAH"),
+            midpoint: None,
+            destination: S!("ZZZZ"),
+        };
+        ModelPendingFlightroute::delete_callsign(&db, &scraped_flightroute.callsign_icao)
+            .await
+            .unwrap();
+        (db, scraped_flightroute)
+    }
+
+    /// Pending flightroute for the test callsign, from get_all
+    async fn get_pending(
+        db: &PgPool,
+        scraped_flightroute: &ScrapedFlightroute,
+    ) -> Option<ModelPendingFlightroute> {
+        ModelPendingFlightroute::get_all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|i| i.callsign_icao == scraped_flightroute.callsign_icao.to_string())
+    }
+
+    #[tokio::test]
+    async fn model_pending_flightroute_insert() {
+        let (db, scraped_flightroute) = setup().await;
+
+        ModelPendingFlightroute::insert(&db, &scraped_flightroute, ScrapeFailure::UnknownAirport)
+            .await
+            .unwrap();
+        let result = get_pending(&db, &scraped_flightroute).await.unwrap();
+        assert_eq!(result.callsign_iata, "NH003");
+        assert_eq!(result.origin, "ROAH");
+        assert_eq!(result.destination, "ZZZZ");
+        assert_eq!(result.reason, "unknown_airport");
+        assert_eq!(
+            result.scraped_flightroute(),
+            Some(scraped_flightroute.clone())
+        );
+
+        // Scraped again, so the existing pending flightroute is updated
+        let scraped_flightroute = ScrapedFlightroute {
+            destination: S!("ROAH"),
+            ..scraped_flightroute
+        };
+        ModelPendingFlightroute::insert(&db, &scraped_flightroute, ScrapeFailure::AirportClash)
+            .await
+            .unwrap();
+        let updated = get_pending(&db, &scraped_flightroute).await.unwrap();
+        assert_eq!(
+            updated.pending_flightroute_id,
+            result.pending_flightroute_id
+        );
+        assert_eq!(updated.destination, "ROAH");
+        assert_eq!(updated.reason, "airport_clash");
+
+        ModelPendingFlightroute::delete_callsign(&db, &scraped_flightroute.callsign_icao)
+            .await
+            .unwrap();
+        assert!(get_pending(&db, &scraped_flightroute).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn model_pending_flightroute_reject() {
+        let (db, scraped_flightroute) = setup().await;
+
+        ModelPendingFlightroute::insert(&db, &scraped_flightroute, ScrapeFailure::UnknownAirport)
+            .await
+            .unwrap();
+        let pending = get_pending(&db, &scraped_flightroute).await.unwrap();
+        pending.reject(&db).await.unwrap();
+        assert!(get_pending(&db, &scraped_flightroute).await.is_none());
+        assert!(
+            ModelPendingFlightroute::get(&db, pending.pending_flightroute_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Same airports, so stays rejected
+        ModelPendingFlightroute::insert(&db, &scraped_flightroute, ScrapeFailure::UnknownAirport)
+            .await
+            .unwrap();
+        assert!(get_pending(&db, &scraped_flightroute).await.is_none());
+
+        // Different airports, so pending again
+        let scraped_flightroute = ScrapedFlightroute {
+            destination: S!("YYYY"),
+            ..scraped_flightroute
+        };
+        ModelPendingFlightroute::insert(&db, &scraped_flightroute, ScrapeFailure::UnknownAirport)
+            .await
+            .unwrap();
+        let result = get_pending(&db, &scraped_flightroute).await.unwrap();
+        assert_eq!(
+            result.pending_flightroute_id,
+            pending.pending_flightroute_id
+        );
+        assert_eq!(result.destination, "YYYY");
+
+        ModelPendingFlightroute::delete_callsign(&db, &scraped_flightroute.callsign_icao)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use sqlx::PgPool;
+
+use crate::{
+    api::AppError,
+    generic_id,
+    scraper::{FailedScrape, ScrapeFailure, ScrapeKind},
+};
+
+/// The most recent scrape for a callsign or mode_s, and when it can next be scraped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelScrapeAttempt {
+    pub scrape_attempt_id: ScrapeAttemptId,
+    pub outcome: String,
+    pub reason: Option<String>,
+    /// Consecutive failed attempts, reset on success
+    pub attempts: i32,
+    /// Seconds until next_eligible, negative once passed, None after a successful scrape
+    pub retry_in: Option<i64>,
+}
+
+generic_id!(ScrapeAttemptId);
+
+impl ModelScrapeAttempt {
+    pub async fn get(
+        db: &PgPool,
+        scrape_kind: ScrapeKind,
+        search: &str,
+    ) -> Result<Option<Self>, AppError> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"
+SELECT
+    scrape_attempt_id,
+    outcome,
+    reason,
+    attempts,
+    CEIL(EXTRACT(EPOCH FROM next_eligible - CURRENT_TIMESTAMP))::BIGINT AS retry_in
+FROM
+    scrape_attempt
+WHERE
+    scrape_kind = $1 AND search = $2"#,
+            scrape_kind.as_str(),
+            search
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// If false, then the upstream source shouldn't be requested again yet
+    pub fn eligible(&self) -> bool {
+        self.retry_in.is_none_or(|retry_in| retry_in <= 0)
+    }
+
+    /// None if the most recent scrape was successful
+    pub fn failure(&self) -> Option<ScrapeFailure> {
+        self.outcome.parse().ok()
+    }
+
+    /// Upsert the outcome of a scrape, previous is the existing attempt, used to count consecutive failures
+    pub async fn insert(
+        db: &PgPool,
+        scrape_kind: ScrapeKind,
+        search: &str,
+        previous: Option<&Self>,
+        result: Result<(), &FailedScrape>,
+    ) -> Result<(), AppError> {
+        let (outcome, reason, attempts, backoff) = match result {
+            Ok(()) => ("success", None, 0, None),
+            Err(failed) => {
+                let attempts = previous.map_or(0, |i| i.attempts).saturating_add(1);
+                (
+                    failed.failure.as_str(),
+                    Some(failed.reason.as_str()),
+                    attempts,
+                    Some(failed.failure.backoff(attempts)),
+                )
+            }
+        };
+        sqlx::query!(
+            r#"
+INSERT INTO
+    scrape_attempt(scrape_kind, search, outcome, reason, attempts, next_eligible)
+VALUES
+    ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(secs => $6::BIGINT))
+ON CONFLICT
+    (scrape_kind, search)
+DO UPDATE SET
+    outcome = EXCLUDED.outcome,
+    reason = EXCLUDED.reason,
+    attempts = EXCLUDED.attempts,
+    attempted_at = CURRENT_TIMESTAMP,
+    next_eligible = EXCLUDED.next_eligible"#,
+            scrape_kind.as_str(),
+            search,
+            outcome,
+            reason,
+            attempts,
+            backoff
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test model_scrape_attempt '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{db_postgres, parse_env::AppEnv};
+
+    const TEST_SEARCH: &str = "TEST_SCRAPE_ATTEMPT";
+
+    async fn setup() -> PgPool {
+        let app_env = AppEnv::get_env();
+        let db = db_postgres::get_pool(&app_env).await.unwrap();
+        remove(&db).await;
+        db
+    }
+
+    async fn remove(db: &PgPool) {
+        sqlx::query("DELETE FROM scrape_attempt WHERE search = $1")
+            .bind(TEST_SEARCH)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn model_scrape_attempt_backoff() {
+        let db = setup().await;
+        let kind = ScrapeKind::Callsign;
+
+        let result = ModelScrapeAttempt::get(&db, kind, TEST_SEARCH)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+
+        let failed = FailedScrape::new(ScrapeFailure::Timeout, "fixture");
+        ModelScrapeAttempt::insert(&db, kind, TEST_SEARCH, None, Err(&failed))
+            .await
+            .unwrap();
+        let first = ModelScrapeAttempt::get(&db, kind, TEST_SEARCH)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.outcome, "timeout");
+        assert_eq!(first.failure(), Some(ScrapeFailure::Timeout));
+        assert_eq!(first.reason.as_deref(), Some("fixture"));
+        assert_eq!(first.attempts, 1);
+        assert!(!first.eligible());
+        assert!((59..=60).contains(&first.retry_in.unwrap()));
+
+        // Consecutive failures double the backoff
+        ModelScrapeAttempt::insert(&db, kind, TEST_SEARCH, Some(&first), Err(&failed))
+            .await
+            .unwrap();
+        let second = ModelScrapeAttempt::get(&db, kind, TEST_SEARCH)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.scrape_attempt_id, first.scrape_attempt_id);
+        assert_eq!(second.attempts, 2);
+        assert!((119..=120).contains(&second.retry_in.unwrap()));
+
+        // A photo with the same search is a different attempt
+        let result = ModelScrapeAttempt::get(&db, ScrapeKind::Photo, TEST_SEARCH)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+
+        // Success resets the attempts
+        ModelScrapeAttempt::insert(&db, kind, TEST_SEARCH, Some(&second), Ok(()))
+            .await
+            .unwrap();
+        let result = ModelScrapeAttempt::get(&db, kind, TEST_SEARCH)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.outcome, "success");
+        assert!(result.failure().is_none());
+        assert_eq!(result.attempts, 0);
+        assert!(result.reason.is_none());
+        assert!(result.retry_in.is_none());
+        assert!(result.eligible());
+
+        remove(&db).await;
+    }
+}
@@ -1,14 +1,16 @@
 use crate::{
     S,
-    api::{AircraftSearch, AirlineCode, AppError, Callsign, ModeS, Registration},
-    db_postgres::{PathID, QueryID, RE_SEED_TIME, VersionID},
+    api::{
+        AircraftSearch, AirlineCode, AirportCode, AppError, Callsign, ModeS, Registration, Validate,
+    },
+    db_postgres::{ModelFlightroute, PathID, QueryID, RE_SEED_TIME, VersionID},
     parse_env::AppEnv,
 };
 use fred::{
     clients::Pool,
     interfaces::{ClientLike, HashesInterface, KeysInterface},
     prelude::ReconnectPolicy,
-    types::FromValue,
+    types::{FromValue, Value},
 };
 use serde::{Serialize, de::DeserializeOwned};
 // use tower_http::ServiceExt;
@@ -18,6 +20,8 @@ pub mod ratelimit;
 pub const ONE_MINUTE_AS_SEC: i64 = 60;
 pub const ONE_WEEK_AS_SEC: i64 = ONE_MINUTE_AS_SEC * 60 * 24 * 7;
 pub const HASH_FIELD: &str = "data";
+/// Time to live of every cached lookup, also used as the Cache-Control max-age of lookup responses
+pub const LOOKUP_TTL: i64 = ONE_WEEK_AS_SEC;
 
 /// Macro to convert a stringified struct back into the struct
 #[macro_export]
@@ -41,6 +45,48 @@ macro_rules! redis_hash_to_struct {
     };
 }
 
+/// Remove the cached flightroute, for each of its callsigns, and their normalised and base flight variants, after it has been changed
+pub async fn delete_flightroute_cache(
+    redis: &Pool,
+    flightroute: &ModelFlightroute,
+) -> Result<(), AppError> {
+    let callsigns = [
+        Some(&flightroute.callsign),
+        flightroute.callsign_iata.as_ref(),
+        flightroute.callsign_icao.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|callsign| Callsign::validate(callsign).ok())
+    .collect::<Vec<_>>();
+    let mut keys = vec![];
+    for callsign in &callsigns {
+        keys.push(RedisKey::Callsign(callsign).to_string());
+        for (fallback, _) in callsign.fallbacks() {
+            keys.push(RedisKey::Callsign(&fallback).to_string());
+        }
+    }
+    keys.sort_unstable();
+    keys.dedup();
+    redis.del::<(), _>(keys).await?;
+    Ok(())
+}
+
+/// Remove the cached aircraft, for both its mode_s and registration, after its photos have been changed
+pub async fn delete_aircraft_cache(
+    redis: &Pool,
+    mode_s: &ModeS,
+    registration: &Registration,
+) -> Result<(), AppError> {
+    redis
+        .del::<(), _>(vec![
+            RedisKey::ModeS(mode_s).to_string(),
+            RedisKey::Registration(registration).to_string(),
+        ])
+        .await?;
+    Ok(())
+}
+
 /// Insert an Option<model> into cache, using redis hashset
 pub async fn insert_cache<T: Serialize + Send + Sync>(
     redis: &Pool,
@@ -85,6 +131,83 @@ pub async fn get_cache<T: DeserializeOwned + Send + FromValue>(
     Ok(None)
 }
 
+/// Pipelined version of `get_cache`, results are returned in the same order as the given keys.
+/// An absent key is `None`, whereas a cached unknown, stored as an empty string, is `Some(None)`
+pub async fn get_cache_many<T: DeserializeOwned + Send + FromValue>(
+    redis: &Pool,
+    keys: &[RedisKey<'_>],
+) -> Result<Vec<Option<Option<T>>>, AppError> {
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+    let keys = keys
+        .iter()
+        .map(|i| (i.get_expire(), i.to_string()))
+        .collect::<Vec<_>>();
+
+    let pipeline = redis.next().pipeline();
+    for (_, key) in &keys {
+        pipeline
+            .hget::<(), &str, &str>(key.as_str(), HASH_FIELD)
+            .await?;
+    }
+    // Read as raw values, so that a nil reply, and an empty string, can't be conflated when converted
+    let values = pipeline.all::<Vec<Value>>().await?;
+    if values.len() != keys.len() {
+        return Err(AppError::Internal(S!("redis pipeline length")));
+    }
+
+    let pipeline = redis.next().pipeline();
+    let mut output = Vec::with_capacity(keys.len());
+    let mut to_expire = false;
+    for ((set_expire, key), value) in keys.iter().zip(values) {
+        if value.is_null() {
+            output.push(None);
+            continue;
+        }
+        let Some(value) = value.as_string() else {
+            return Err(AppError::Internal(S!("redis value")));
+        };
+        if *set_expire {
+            pipeline
+                .expire::<(), &str>(key.as_str(), ONE_WEEK_AS_SEC, None)
+                .await?;
+            to_expire = true;
+        }
+        if value.is_empty() {
+            output.push(Some(None));
+        } else {
+            output.push(Some(Some(serde_json::from_str::<T>(&value)?)));
+        }
+    }
+    if to_expire {
+        pipeline.all::<()>().await?;
+    }
+    Ok(output)
+}
+
+/// Pipelined version of `insert_cache`, each Option\<model> is inserted, and given a ttl, in a single round trip
+pub async fn insert_cache_many<T: Serialize + Send + Sync>(
+    redis: &Pool,
+    to_insert: &[(Option<&T>, RedisKey<'_>)],
+) -> Result<(), AppError> {
+    if to_insert.is_empty() {
+        return Ok(());
+    }
+    let pipeline = redis.next().pipeline();
+    for (value, key) in to_insert {
+        let ttl = key.get_ttl();
+        let key = key.to_string();
+        let serialized =
+            value.map_or_else(|| S!(), |i| serde_json::to_string(i).unwrap_or_default());
+        pipeline
+            .hset::<(), _, _>(key.as_str(), HashMap::from([(HASH_FIELD, serialized)]))
+            .await?;
+        pipeline.expire::<(), &str>(key.as_str(), ttl, None).await?;
+    }
+    Ok(pipeline.all::<()>().await?)
+}
+
 pub async fn get_pool(app_env: &AppEnv) -> Result<Pool, AppError> {
     let redis_url = format!(
         "redis://:{password}@{host}:{port}/{db}",
@@ -134,6 +257,7 @@ impl fmt::Display for IncomingRequestKey<'_> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RedisKey<'a> {
     Airline(&'a AirlineCode),
+    Airport(&'a AirportCode),
     Callsign(&'a Callsign),
     IncomingRequest(IncomingRequestKey<'a>),
     ModeS(&'a ModeS),
@@ -147,7 +271,7 @@ impl<'a> RedisKey<'a> {
         match self {
             // Want this to be double the RE_SEED_TIME, so that there is always a cache available
             Self::Stats => RE_SEED_TIME.wrapping_mul(2),
-            _ => ONE_WEEK_AS_SEC,
+            _ => LOOKUP_TTL,
         }
     }
 
@@ -160,6 +284,7 @@ impl fmt::Display for RedisKey<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Airline(airline) => write!(f, "airline::{airline}"),
+            Self::Airport(airport) => write!(f, "airport::{airport}"),
             Self::Callsign(callsign) => write!(f, "callsign::{callsign}"),
             Self::ModeS(mode_s) => write!(f, "mode_s::{mode_s}"),
             Self::RateLimit(ip) => write!(f, "ratelimit::{ip}"),
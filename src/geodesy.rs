@@ -0,0 +1,217 @@
+// Great-circle calculations on a spherical earth, accurate enough for route estimates, but not for navigation
+
+/// Mean earth radius, IUGG
+const EARTH_RADIUS_KM: f64 = 6_371.008_8;
+
+const KM_PER_NM: f64 = 1.852;
+
+/// Length of one degree of latitude, and of longitude at the equator
+const KM_PER_DEGREE: f64 = EARTH_RADIUS_KM * std::f64::consts::PI / 180.0;
+
+/// Typical jet cruise speed, used for a straight-line duration estimate
+const CRUISE_SPEED_KMH: f64 = 800.0;
+
+/// Fixed time per leg for taxi, climb, and descent
+const LEG_OVERHEAD_MINUTES: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinate {
+    pub const fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Great-circle distance in kilometres, using the haversine formula
+    pub fn distance_km(&self, other: &Self) -> f64 {
+        let (lat_a, lat_b) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat_b - lat_a;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a =
+            (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+
+    /// Initial bearing, in degrees 0..360, from self towards other
+    pub fn initial_bearing(&self, other: &Self) -> f64 {
+        let (lat_a, lat_b) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let y = d_lon.sin() * lat_b.cos();
+        let x = lat_a.cos() * lat_b.sin() - lat_a.sin() * lat_b.cos() * d_lon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Smallest lat/lon box that contains every point within radius_km of self, for use as an indexed pre-filter
+    pub fn bounding_box(&self, radius_km: f64) -> BoundingBox {
+        let d_lat = radius_km / KM_PER_DEGREE;
+        let min_latitude = self.latitude - d_lat;
+        let max_latitude = self.latitude + d_lat;
+
+        // Box contains a pole, so every longitude is in range
+        if min_latitude <= -90.0 || max_latitude >= 90.0 {
+            return BoundingBox {
+                min_latitude: min_latitude.max(-90.0),
+                max_latitude: max_latitude.min(90.0),
+                min_longitude: -180.0,
+                max_longitude: 180.0,
+            };
+        }
+
+        // Use the widest latitude in the box, where a degree of longitude is shortest
+        let widest = self.latitude.abs() + d_lat;
+        let d_lon = radius_km / (KM_PER_DEGREE * widest.to_radians().cos());
+        if d_lon >= 180.0 {
+            return BoundingBox {
+                min_latitude,
+                max_latitude,
+                min_longitude: -180.0,
+                max_longitude: 180.0,
+            };
+        }
+
+        let wrap = |longitude: f64| (longitude + 180.0).rem_euclid(360.0) - 180.0;
+        BoundingBox {
+            min_latitude,
+            max_latitude,
+            min_longitude: wrap(self.longitude - d_lon),
+            max_longitude: wrap(self.longitude + d_lon),
+        }
+    }
+}
+
+/// If min_longitude is greater than max_longitude, then the box crosses the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+pub fn km_to_nm(km: f64) -> f64 {
+    km / KM_PER_NM
+}
+
+/// Estimated block time, in minutes, of a single leg
+pub fn estimated_leg_minutes(distance_km: f64) -> f64 {
+    LEG_OVERHEAD_MINUTES + distance_km / CRUISE_SPEED_KMH * 60.0
+}
+
+/// Round to a given number of decimal places
+pub fn round(value: f64, places: i32) -> f64 {
+    let factor = 10_f64.powi(places);
+    (value * factor).round() / factor
+}
+
+/// cargo watch -q -c -w src/ -x 'test geodesy -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const EGLL: Coordinate = Coordinate::new(51.4775, -0.461_389);
+    const KJFK: Coordinate = Coordinate::new(40.639_801, -73.7789);
+
+    /// Point reached by travelling distance_km from origin along an initial bearing
+    fn destination(origin: Coordinate, bearing: f64, distance_km: f64) -> Coordinate {
+        let (lat, lon, bearing) = (
+            origin.latitude.to_radians(),
+            origin.longitude.to_radians(),
+            bearing.to_radians(),
+        );
+        let angle = distance_km / EARTH_RADIUS_KM;
+        let lat_b = (lat.sin() * angle.cos() + lat.cos() * angle.sin() * bearing.cos()).asin();
+        let lon_b = lon
+            + (bearing.sin() * angle.sin() * lat.cos())
+                .atan2(angle.cos() - lat.sin() * lat_b.sin());
+        Coordinate::new(lat_b.to_degrees(), lon_b.to_degrees())
+    }
+
+    #[test]
+    fn geodesy_distance() {
+        assert_eq!(round(EGLL.distance_km(&KJFK), 0), 5_539.0);
+        assert_eq!(round(KJFK.distance_km(&EGLL), 0), 5_539.0);
+        assert_eq!(EGLL.distance_km(&EGLL), 0.0);
+        assert_eq!(round(km_to_nm(EGLL.distance_km(&KJFK)), 0), 2_991.0);
+
+        // Antipodal points are half the circumference apart
+        let result = Coordinate::new(0.0, 0.0).distance_km(&Coordinate::new(0.0, 180.0));
+        assert_eq!(
+            round(result, 3),
+            round(std::f64::consts::PI * EARTH_RADIUS_KM, 3)
+        );
+    }
+
+    #[test]
+    fn geodesy_initial_bearing() {
+        assert_eq!(round(EGLL.initial_bearing(&KJFK), 1), 287.9);
+        assert_eq!(round(KJFK.initial_bearing(&EGLL), 1), 51.3);
+
+        let origin = Coordinate::new(0.0, 0.0);
+        assert_eq!(origin.initial_bearing(&Coordinate::new(1.0, 0.0)), 0.0);
+        assert_eq!(
+            round(origin.initial_bearing(&Coordinate::new(0.0, 1.0)), 6),
+            90.0
+        );
+        assert_eq!(
+            round(origin.initial_bearing(&Coordinate::new(-1.0, 0.0)), 6),
+            180.0
+        );
+        assert_eq!(
+            round(origin.initial_bearing(&Coordinate::new(0.0, -1.0)), 6),
+            270.0
+        );
+    }
+
+    #[test]
+    fn geodesy_estimated_leg_minutes() {
+        assert_eq!(estimated_leg_minutes(0.0), 30.0);
+        assert_eq!(estimated_leg_minutes(800.0), 90.0);
+        assert_eq!(
+            round(estimated_leg_minutes(EGLL.distance_km(&KJFK)), 0),
+            445.0
+        );
+    }
+
+    #[test]
+    fn geodesy_bounding_box() {
+        let result = EGLL.bounding_box(100.0);
+        assert_eq!(round(result.min_latitude, 3), 50.578);
+        assert_eq!(round(result.max_latitude, 3), 52.377);
+        assert!(result.min_longitude < EGLL.longitude && result.max_longitude > EGLL.longitude);
+
+        // Every point on the radius is inside the box
+        for bearing in (0..360).step_by(15) {
+            let point = destination(EGLL, f64::from(bearing), 99.99);
+            assert_eq!(round(EGLL.distance_km(&point), 6), 99.99);
+            assert!((result.min_latitude..=result.max_latitude).contains(&point.latitude));
+            assert!((result.min_longitude..=result.max_longitude).contains(&point.longitude));
+        }
+
+        // Crosses the antimeridian
+        let result = Coordinate::new(0.0, 179.5).bounding_box(200.0);
+        assert!(result.min_longitude > result.max_longitude);
+        assert_eq!(round(result.min_longitude, 3), 177.7);
+        assert_eq!(round(result.max_longitude, 3), -178.7);
+
+        // Contains the north pole
+        let result = Coordinate::new(89.5, 10.0).bounding_box(100.0);
+        assert_eq!(result.max_latitude, 90.0);
+        assert_eq!(result.min_longitude, -180.0);
+        assert_eq!(result.max_longitude, 180.0);
+    }
+
+    #[test]
+    fn geodesy_round() {
+        assert_eq!(round(1.234_56, 1), 1.2);
+        assert_eq!(round(1.25, 1), 1.3);
+        assert_eq!(round(-1.234_56, 2), -1.23);
+        assert_eq!(round(1.5, 0), 2.0);
+    }
+}
@@ -0,0 +1,404 @@
+// ICAO 24-bit aircraft address allocations, from ICAO Annex 10, Volume III, Chapter 9
+// Each state of registry is allocated a block of addresses, a mode_s within that block was assigned by that state
+// Military sub-blocks are not part of the ICAO allocation, these are the well known ranges used by feeders, and are not exhaustive
+use serde::Serialize;
+
+use crate::api::ModeS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationKind {
+    Civil,
+    Military,
+    Special,
+    Unallocated,
+}
+
+/// The block that a mode_s belongs to, start & end are inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Allocation {
+    pub start: u32,
+    pub end: u32,
+    pub country_iso_name: Option<&'static str>,
+    pub country_name: Option<&'static str>,
+    pub kind: AllocationKind,
+}
+
+type Block = (u32, u32, &'static str, &'static str);
+
+/// Country blocks, ordered by start address, must not overlap
+const COUNTRY_BLOCKS: &[Block] = &[
+    (0x00_4000, 0x00_43FF, "ZW", "Zimbabwe"),
+    (0x00_6000, 0x00_6FFF, "MZ", "Mozambique"),
+    (0x00_8000, 0x00_FFFF, "ZA", "South Africa"),
+    (0x01_0000, 0x01_7FFF, "EG", "Egypt"),
+    (0x01_8000, 0x01_FFFF, "LY", "Libya"),
+    (0x02_0000, 0x02_7FFF, "MA", "Morocco"),
+    (0x02_8000, 0x02_FFFF, "TN", "Tunisia"),
+    (0x03_0000, 0x03_03FF, "BW", "Botswana"),
+    (0x03_2000, 0x03_2FFF, "BI", "Burundi"),
+    (0x03_4000, 0x03_4FFF, "CM", "Cameroon"),
+    (0x03_5000, 0x03_53FF, "KM", "Comoros"),
+    (0x03_6000, 0x03_6FFF, "CG", "Congo"),
+    (0x03_8000, 0x03_8FFF, "CI", "Côte d'Ivoire"),
+    (0x03_E000, 0x03_EFFF, "GA", "Gabon"),
+    (0x04_0000, 0x04_0FFF, "ET", "Ethiopia"),
+    (0x04_2000, 0x04_2FFF, "GQ", "Equatorial Guinea"),
+    (0x04_4000, 0x04_4FFF, "GH", "Ghana"),
+    (0x04_6000, 0x04_6FFF, "GN", "Guinea"),
+    (0x04_8000, 0x04_83FF, "GW", "Guinea-Bissau"),
+    (0x04_A000, 0x04_A3FF, "LS", "Lesotho"),
+    (0x04_C000, 0x04_CFFF, "KE", "Kenya"),
+    (0x05_0000, 0x05_0FFF, "LR", "Liberia"),
+    (0x05_4000, 0x05_4FFF, "MG", "Madagascar"),
+    (0x05_8000, 0x05_8FFF, "MW", "Malawi"),
+    (0x05_A000, 0x05_A3FF, "MV", "Maldives"),
+    (0x05_C000, 0x05_CFFF, "ML", "Mali"),
+    (0x05_E000, 0x05_E3FF, "MR", "Mauritania"),
+    (0x06_0000, 0x06_03FF, "MU", "Mauritius"),
+    (0x06_2000, 0x06_2FFF, "NE", "Niger"),
+    (0x06_4000, 0x06_4FFF, "NG", "Nigeria"),
+    (0x06_8000, 0x06_8FFF, "UG", "Uganda"),
+    (0x06_A000, 0x06_A3FF, "QA", "Qatar"),
+    (0x06_C000, 0x06_CFFF, "CF", "Central African Republic"),
+    (0x06_E000, 0x06_EFFF, "RW", "Rwanda"),
+    (0x07_0000, 0x07_0FFF, "SN", "Senegal"),
+    (0x07_4000, 0x07_43FF, "SC", "Seychelles"),
+    (0x07_6000, 0x07_63FF, "SL", "Sierra Leone"),
+    (0x07_8000, 0x07_8FFF, "SO", "Somalia"),
+    (0x07_A000, 0x07_A3FF, "SZ", "Eswatini"),
+    (0x07_C000, 0x07_CFFF, "SD", "Sudan"),
+    (0x08_0000, 0x08_0FFF, "TZ", "Tanzania"),
+    (0x08_4000, 0x08_4FFF, "TD", "Chad"),
+    (0x08_8000, 0x08_8FFF, "TG", "Togo"),
+    (0x08_A000, 0x08_AFFF, "ZM", "Zambia"),
+    (
+        0x08_C000,
+        0x08_CFFF,
+        "CD",
+        "Democratic Republic of the Congo",
+    ),
+    (0x09_0000, 0x09_0FFF, "AO", "Angola"),
+    (0x09_4000, 0x09_43FF, "BJ", "Benin"),
+    (0x09_6000, 0x09_63FF, "CV", "Cape Verde"),
+    (0x09_8000, 0x09_83FF, "DJ", "Djibouti"),
+    (0x09_A000, 0x09_AFFF, "GM", "Gambia"),
+    (0x09_C000, 0x09_CFFF, "BF", "Burkina Faso"),
+    (0x09_E000, 0x09_E3FF, "ST", "Sao Tome and Principe"),
+    (0x0A_0000, 0x0A_7FFF, "DZ", "Algeria"),
+    (0x0A_8000, 0x0A_8FFF, "BS", "Bahamas"),
+    (0x0A_A000, 0x0A_A3FF, "BB", "Barbados"),
+    (0x0A_B000, 0x0A_B3FF, "BZ", "Belize"),
+    (0x0A_C000, 0x0A_CFFF, "CO", "Colombia"),
+    (0x0A_E000, 0x0A_EFFF, "CR", "Costa Rica"),
+    (0x0B_0000, 0x0B_0FFF, "CU", "Cuba"),
+    (0x0B_2000, 0x0B_2FFF, "SV", "El Salvador"),
+    (0x0B_4000, 0x0B_4FFF, "GT", "Guatemala"),
+    (0x0B_6000, 0x0B_6FFF, "GY", "Guyana"),
+    (0x0B_8000, 0x0B_8FFF, "HT", "Haiti"),
+    (0x0B_A000, 0x0B_AFFF, "HN", "Honduras"),
+    (
+        0x0B_C000,
+        0x0B_C3FF,
+        "VC",
+        "Saint Vincent and the Grenadines",
+    ),
+    (0x0B_E000, 0x0B_EFFF, "JM", "Jamaica"),
+    (0x0C_0000, 0x0C_0FFF, "NI", "Nicaragua"),
+    (0x0C_2000, 0x0C_2FFF, "PA", "Panama"),
+    (0x0C_4000, 0x0C_4FFF, "DO", "Dominican Republic"),
+    (0x0C_6000, 0x0C_6FFF, "TT", "Trinidad and Tobago"),
+    (0x0C_8000, 0x0C_8FFF, "SR", "Suriname"),
+    (0x0C_A000, 0x0C_A3FF, "AG", "Antigua and Barbuda"),
+    (0x0C_C000, 0x0C_C3FF, "GD", "Grenada"),
+    (0x0D_0000, 0x0D_7FFF, "MX", "Mexico"),
+    (0x0D_8000, 0x0D_FFFF, "VE", "Venezuela"),
+    (0x10_0000, 0x1F_FFFF, "RU", "Russia"),
+    (0x20_1000, 0x20_13FF, "NA", "Namibia"),
+    (0x20_2000, 0x20_23FF, "ER", "Eritrea"),
+    (0x30_0000, 0x33_FFFF, "IT", "Italy"),
+    (0x34_0000, 0x37_FFFF, "ES", "Spain"),
+    (0x38_0000, 0x3B_FFFF, "FR", "France"),
+    (0x3C_0000, 0x3F_FFFF, "DE", "Germany"),
+    (0x40_0000, 0x43_FFFF, "GB", "United Kingdom"),
+    (0x44_0000, 0x44_7FFF, "AT", "Austria"),
+    (0x44_8000, 0x44_FFFF, "BE", "Belgium"),
+    (0x45_0000, 0x45_7FFF, "BG", "Bulgaria"),
+    (0x45_8000, 0x45_FFFF, "DK", "Denmark"),
+    (0x46_0000, 0x46_7FFF, "FI", "Finland"),
+    (0x46_8000, 0x46_FFFF, "GR", "Greece"),
+    (0x47_0000, 0x47_7FFF, "HU", "Hungary"),
+    (0x47_8000, 0x47_FFFF, "NO", "Norway"),
+    (0x48_0000, 0x48_7FFF, "NL", "Netherlands"),
+    (0x48_8000, 0x48_FFFF, "PL", "Poland"),
+    (0x49_0000, 0x49_7FFF, "PT", "Portugal"),
+    (0x49_8000, 0x49_FFFF, "CZ", "Czech Republic"),
+    (0x4A_0000, 0x4A_7FFF, "RO", "Romania"),
+    (0x4A_8000, 0x4A_FFFF, "SE", "Sweden"),
+    (0x4B_0000, 0x4B_7FFF, "CH", "Switzerland"),
+    (0x4B_8000, 0x4B_FFFF, "TR", "Turkey"),
+    (0x4C_0000, 0x4C_7FFF, "RS", "Serbia"),
+    (0x4C_8000, 0x4C_83FF, "CY", "Cyprus"),
+    (0x4C_A000, 0x4C_AFFF, "IE", "Ireland"),
+    (0x4C_C000, 0x4C_CFFF, "IS", "Iceland"),
+    (0x4D_0000, 0x4D_03FF, "LU", "Luxembourg"),
+    (0x4D_2000, 0x4D_23FF, "MT", "Malta"),
+    (0x4D_4000, 0x4D_43FF, "MC", "Monaco"),
+    (0x50_0000, 0x50_03FF, "SM", "San Marino"),
+    (0x50_1000, 0x50_13FF, "AL", "Albania"),
+    (0x50_1C00, 0x50_1FFF, "HR", "Croatia"),
+    (0x50_2C00, 0x50_2FFF, "LV", "Latvia"),
+    (0x50_3C00, 0x50_3FFF, "LT", "Lithuania"),
+    (0x50_4C00, 0x50_4FFF, "MD", "Moldova"),
+    (0x50_5C00, 0x50_5FFF, "SK", "Slovakia"),
+    (0x50_6C00, 0x50_6FFF, "SI", "Slovenia"),
+    (0x50_7C00, 0x50_7FFF, "UZ", "Uzbekistan"),
+    (0x50_8000, 0x50_FFFF, "UA", "Ukraine"),
+    (0x51_0000, 0x51_03FF, "BY", "Belarus"),
+    (0x51_1000, 0x51_13FF, "EE", "Estonia"),
+    (0x51_2000, 0x51_23FF, "MK", "North Macedonia"),
+    (0x51_3000, 0x51_33FF, "BA", "Bosnia and Herzegovina"),
+    (0x51_4000, 0x51_43FF, "GE", "Georgia"),
+    (0x51_5000, 0x51_53FF, "TJ", "Tajikistan"),
+    (0x51_6000, 0x51_63FF, "ME", "Montenegro"),
+    (0x60_0000, 0x60_03FF, "AM", "Armenia"),
+    (0x60_0800, 0x60_0BFF, "AZ", "Azerbaijan"),
+    (0x60_1000, 0x60_13FF, "KG", "Kyrgyzstan"),
+    (0x60_1800, 0x60_1BFF, "TM", "Turkmenistan"),
+    (0x68_0000, 0x68_03FF, "BT", "Bhutan"),
+    (0x68_1000, 0x68_13FF, "FM", "Micronesia"),
+    (0x68_2000, 0x68_23FF, "MN", "Mongolia"),
+    (0x68_3000, 0x68_33FF, "KZ", "Kazakhstan"),
+    (0x68_4000, 0x68_43FF, "PW", "Palau"),
+    (0x70_0000, 0x70_0FFF, "AF", "Afghanistan"),
+    (0x70_2000, 0x70_2FFF, "BD", "Bangladesh"),
+    (0x70_4000, 0x70_4FFF, "MM", "Myanmar"),
+    (0x70_6000, 0x70_6FFF, "KW", "Kuwait"),
+    (0x70_8000, 0x70_8FFF, "LA", "Laos"),
+    (0x70_A000, 0x70_AFFF, "NP", "Nepal"),
+    (0x70_C000, 0x70_C3FF, "OM", "Oman"),
+    (0x70_E000, 0x70_EFFF, "KH", "Cambodia"),
+    (0x71_0000, 0x71_7FFF, "SA", "Saudi Arabia"),
+    (0x71_8000, 0x71_FFFF, "KR", "South Korea"),
+    (0x72_0000, 0x72_7FFF, "KP", "North Korea"),
+    (0x72_8000, 0x72_FFFF, "IQ", "Iraq"),
+    (0x73_0000, 0x73_7FFF, "IR", "Iran"),
+    (0x73_8000, 0x73_FFFF, "IL", "Israel"),
+    (0x74_0000, 0x74_7FFF, "JO", "Jordan"),
+    (0x74_8000, 0x74_FFFF, "LB", "Lebanon"),
+    (0x75_0000, 0x75_7FFF, "MY", "Malaysia"),
+    (0x75_8000, 0x75_FFFF, "PH", "Philippines"),
+    (0x76_0000, 0x76_7FFF, "PK", "Pakistan"),
+    (0x76_8000, 0x76_FFFF, "SG", "Singapore"),
+    (0x77_0000, 0x77_7FFF, "LK", "Sri Lanka"),
+    (0x77_8000, 0x77_FFFF, "SY", "Syria"),
+    (0x78_0000, 0x7B_FFFF, "CN", "China"),
+    (0x7C_0000, 0x7F_FFFF, "AU", "Australia"),
+    (0x80_0000, 0x83_FFFF, "IN", "India"),
+    (0x84_0000, 0x87_FFFF, "JP", "Japan"),
+    (0x88_0000, 0x88_7FFF, "TH", "Thailand"),
+    (0x88_8000, 0x88_FFFF, "VN", "Viet Nam"),
+    (0x89_0000, 0x89_0FFF, "YE", "Yemen"),
+    (0x89_4000, 0x89_4FFF, "BH", "Bahrain"),
+    (0x89_5000, 0x89_53FF, "BN", "Brunei"),
+    (0x89_6000, 0x89_6FFF, "AE", "United Arab Emirates"),
+    (0x89_7000, 0x89_73FF, "SB", "Solomon Islands"),
+    (0x89_8000, 0x89_8FFF, "PG", "Papua New Guinea"),
+    (0x89_9000, 0x89_93FF, "TW", "Taiwan"),
+    (0x8A_0000, 0x8A_7FFF, "ID", "Indonesia"),
+    (0x90_0000, 0x90_03FF, "MH", "Marshall Islands"),
+    (0x90_1000, 0x90_13FF, "CK", "Cook Islands"),
+    (0x90_2000, 0x90_23FF, "WS", "Samoa"),
+    (0xA0_0000, 0xAF_FFFF, "US", "United States"),
+    (0xC0_0000, 0xC3_FFFF, "CA", "Canada"),
+    (0xC8_0000, 0xC8_7FFF, "NZ", "New Zealand"),
+    (0xC8_8000, 0xC8_8FFF, "FJ", "Fiji"),
+    (0xC8_A000, 0xC8_A3FF, "NR", "Nauru"),
+    (0xC8_C000, 0xC8_C3FF, "LC", "Saint Lucia"),
+    (0xC8_D000, 0xC8_D3FF, "TO", "Tonga"),
+    (0xC8_E000, 0xC8_E3FF, "KI", "Kiribati"),
+    (0xC9_0000, 0xC9_03FF, "VU", "Vanuatu"),
+    (0xE0_0000, 0xE3_FFFF, "AR", "Argentina"),
+    (0xE4_0000, 0xE7_FFFF, "BR", "Brazil"),
+    (0xE8_0000, 0xE8_0FFF, "CL", "Chile"),
+    (0xE8_4000, 0xE8_4FFF, "EC", "Ecuador"),
+    (0xE8_8000, 0xE8_8FFF, "PY", "Paraguay"),
+    (0xE8_C000, 0xE8_CFFF, "PE", "Peru"),
+    (0xE9_0000, 0xE9_0FFF, "UY", "Uruguay"),
+    (0xE9_4000, 0xE9_4FFF, "BO", "Bolivia"),
+];
+
+/// Military sub-blocks, each must be inside a single country block
+const MILITARY_BLOCKS: &[(u32, u32)] = &[
+    // Above N99999, the highest mode_s that maps to a US N-Number
+    (0xAD_F7C8, 0xAF_FFFF),
+    (0x43_C000, 0x43_CFFF),
+    (0xC2_0000, 0xC3_FFFF),
+];
+
+/// Blocks allocated to ICAO itself, for temporary and special use
+const SPECIAL_BLOCKS: &[(u32, u32)] = &[(0xF0_0000, 0xF0_7FFF), (0xF0_9000, 0xF0_93FF)];
+
+/// Find the allocated block of a mode_s, any mode_s that isn't within a known block is unallocated
+pub fn allocation(mode_s: &ModeS) -> Allocation {
+    let Ok(address) = u32::from_str_radix(&mode_s.to_string(), 16) else {
+        return Allocation::unallocated(0, 0xFF_FFFF);
+    };
+
+    if let Some((start, end)) = SPECIAL_BLOCKS
+        .iter()
+        .find(|(start, end)| (*start..=*end).contains(&address))
+    {
+        return Allocation {
+            start: *start,
+            end: *end,
+            country_iso_name: None,
+            country_name: None,
+            kind: AllocationKind::Special,
+        };
+    }
+
+    let Some((start, end, iso, name)) = COUNTRY_BLOCKS
+        .iter()
+        .find(|(start, end, ..)| (*start..=*end).contains(&address))
+    else {
+        // The gap between the nearest allocated blocks either side of the address
+        let blocks = COUNTRY_BLOCKS
+            .iter()
+            .map(|(start, end, ..)| (*start, *end))
+            .chain(SPECIAL_BLOCKS.iter().copied());
+        let start = blocks
+            .clone()
+            .filter(|(_, end)| *end < address)
+            .map(|(_, end)| end + 1)
+            .max()
+            .unwrap_or(0);
+        let end = blocks
+            .filter(|(start, _)| *start > address)
+            .map(|(start, _)| start - 1)
+            .min()
+            .unwrap_or(0xFF_FFFF);
+        return Allocation::unallocated(start, end);
+    };
+
+    let kind = if MILITARY_BLOCKS
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&address))
+    {
+        AllocationKind::Military
+    } else {
+        AllocationKind::Civil
+    };
+
+    Allocation {
+        start: *start,
+        end: *end,
+        country_iso_name: Some(iso),
+        country_name: Some(name),
+        kind,
+    }
+}
+
+impl Allocation {
+    const fn unallocated(start: u32, end: u32) -> Self {
+        Self {
+            start,
+            end,
+            country_iso_name: None,
+            country_name: None,
+            kind: AllocationKind::Unallocated,
+        }
+    }
+}
+
+/// cargo watch -q -c -w src/ -x 'test icao_allocation -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::api::Validate;
+
+    fn test(mode_s: &str) -> Allocation {
+        allocation(&ModeS::validate(mode_s).unwrap())
+    }
+
+    #[test]
+    fn icao_allocation_blocks_ordered() {
+        for pair in COUNTRY_BLOCKS.windows(2) {
+            assert!(pair[0].0 <= pair[0].1);
+            assert!(pair[0].1 < pair[1].0, "{:X} overlaps", pair[1].0);
+        }
+        for (start, end) in MILITARY_BLOCKS {
+            assert!(
+                COUNTRY_BLOCKS
+                    .iter()
+                    .any(|(c_start, c_end, ..)| c_start <= start && end <= c_end)
+            );
+        }
+    }
+
+    #[test]
+    fn icao_allocation_civil() {
+        let result = test("400F1A");
+        assert_eq!(
+            result,
+            Allocation {
+                start: 0x40_0000,
+                end: 0x43_FFFF,
+                country_iso_name: Some("GB"),
+                country_name: Some("United Kingdom"),
+                kind: AllocationKind::Civil,
+            }
+        );
+
+        let result = test("a00001");
+        assert_eq!(result.country_iso_name, Some("US"));
+        assert_eq!(result.kind, AllocationKind::Civil);
+
+        // Block boundaries
+        assert_eq!(test("3FFFFF").country_iso_name, Some("DE"));
+        assert_eq!(test("440000").country_iso_name, Some("AT"));
+        assert_eq!(test("004000").country_iso_name, Some("ZW"));
+        assert_eq!(test("E94FFF").country_iso_name, Some("BO"));
+        assert_eq!(test("7C6DB8").country_name, Some("Australia"));
+    }
+
+    #[test]
+    fn icao_allocation_military() {
+        let result = test("ADF7C8");
+        assert_eq!(result.country_iso_name, Some("US"));
+        assert_eq!(result.kind, AllocationKind::Military);
+        assert_eq!(test("ADF7C7").kind, AllocationKind::Civil);
+        assert_eq!(test("43C6E1").kind, AllocationKind::Military);
+        assert_eq!(test("43C6E1").country_iso_name, Some("GB"));
+    }
+
+    #[test]
+    fn icao_allocation_special() {
+        let result = test("F00001");
+        assert_eq!(result.kind, AllocationKind::Special);
+        assert!(result.country_name.is_none());
+        assert_eq!((result.start, result.end), (0xF0_0000, 0xF0_7FFF));
+    }
+
+    #[test]
+    fn icao_allocation_unallocated() {
+        let result = test("000000");
+        assert_eq!(result, Allocation::unallocated(0, 0x00_3FFF));
+
+        // Between Zimbabwe and Mozambique
+        let result = test("005000");
+        assert_eq!(result, Allocation::unallocated(0x00_4400, 0x00_5FFF));
+
+        // After Bolivia, before the ICAO blocks
+        let result = test("EA0000");
+        assert_eq!(result, Allocation::unallocated(0xE9_5000, 0xEF_FFFF));
+
+        // Between the two ICAO blocks
+        let result = test("F08000");
+        assert_eq!(result, Allocation::unallocated(0xF0_8000, 0xF0_8FFF));
+
+        let result = test("FFFFFF");
+        assert_eq!(result, Allocation::unallocated(0xF0_9400, 0xFF_FFFF));
+    }
+}
@@ -7,8 +7,11 @@ mod api;
 mod argon;
 mod db_postgres;
 mod db_redis;
+mod geodesy;
+mod icao_allocation;
 mod n_number;
 mod parse_env;
+mod registration_converter;
 mod scraper;
 
 use api::AppError;
@@ -17,7 +20,7 @@ use tracing_subscriber::{fmt, prelude::__tracing_subscriber_SubscriberExt};
 
 use crate::{
     db_postgres::{ModelIncomingRequest, MsgIncomingRequest},
-    scraper::{MsgScraper, Scraper},
+    scraper::{MsgScraper, PhotoBackfill, Revalidator, Scraper, ScraperShared, Sources},
 };
 
 /// Simple macro to create an empty String, or create String from a &str - to get rid of .to_owned() / String::from() etc
@@ -55,9 +58,12 @@ fn setup_tracing(app_env: &AppEnv) -> Result<(), AppError> {
     }
 }
 
-async fn start_scraper(app_env: &AppEnv) -> Result<async_channel::Sender<MsgScraper>, AppError> {
+async fn start_scraper(
+    app_env: &AppEnv,
+    sources: Sources,
+) -> Result<(async_channel::Sender<MsgScraper>, ScraperShared), AppError> {
     let postgres = db_postgres::get_pool(app_env).await?;
-    Ok(Scraper::start(app_env, postgres))
+    Ok(Scraper::start(app_env, postgres, sources))
 }
 
 /// This initial seeding is slow, will block until complete
@@ -79,10 +85,24 @@ async fn start() -> Result<(), AppError> {
         db_redis::get_pool(&app_env),
     )?;
 
-    let (tx_scraper, tx_stats) =
-        tokio::try_join!(start_scraper(&app_env), start_incoming_requests(&app_env),)?;
+    let sources = Sources::new(&app_env)?;
+
+    let ((tx_scraper, scraper_shared), tx_stats) = tokio::try_join!(
+        start_scraper(&app_env, sources.clone()),
+        start_incoming_requests(&app_env),
+    )?;
+    Revalidator::start(&app_env, postgres.clone(), redis.clone(), sources.clone());
+    PhotoBackfill::start(&app_env, postgres.clone(), redis.clone(), sources);
 
-    api::serve(app_env, postgres, redis, tx_scraper, tx_stats).await
+    api::serve(
+        app_env,
+        postgres,
+        redis,
+        tx_scraper,
+        scraper_shared,
+        tx_stats,
+    )
+    .await
 }
 
 #[tokio::main]
@@ -1,14 +1,25 @@
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, env, str::FromStr};
 use thiserror::Error;
 
-use crate::argon::ArgonHash;
+use crate::{
+    argon::ArgonHash,
+    scraper::{FlightrouteProvider, PhotoProvider},
+};
 
 type EnvHashMap = HashMap<String, String>;
 
+/// Used when LOCATION_PHOTO_CACHE isn't set
+const LOCATION_PHOTO_CACHE: &str = "/photo_cache";
+
+/// Used when PHOTO_CACHE_MAX_MB isn't set
+const PHOTO_CACHE_MAX_MB: u16 = 256;
+
 #[derive(Debug, Error)]
 enum EnvError {
     #[error("missing env: '{0}'")]
     NotFound(String),
+    #[error("invalid env: '{0}'")]
+    Invalid(String),
 }
 
 #[derive(Debug, Clone)]
@@ -19,16 +30,23 @@ pub struct AppEnv {
     pub api_host: String,
     pub api_port: u16,
     pub location_logs: String,
+    pub location_photo_cache: String,
     pub log_level: tracing::Level,
     pub pg_database: String,
     pub pg_host: String,
     pub pg_pass: String,
     pub pg_port: u16,
     pub pg_user: String,
+    /// Size cap of the proxied photo cache, the least recently used photos are evicted once it's reached
+    pub photo_cache_max_mb: u16,
     pub redis_database: u16,
     pub redis_host: String,
     pub redis_password: String,
     pub redis_port: u16,
+    /// Re-scrape scraped flightroutes that haven't been validated in this many days, None to disable
+    pub scrape_revalidate_days: Option<u16>,
+    pub scrape_flightroute_sources: Vec<FlightrouteProvider>,
+    pub scrape_photo_sources: Vec<PhotoProvider>,
     pub url_aircraft_photo: String,
     pub url_callsign: String,
     pub url_photo_prefix: String,
@@ -71,6 +89,30 @@ impl AppEnv {
         Self::parse_boolean(key, map).then_some(())
     }
 
+    /// Parse a comma separated list, in order, ignoring duplicates, else return the default
+    fn parse_list<T: FromStr + PartialEq>(
+        key: &str,
+        map: &EnvHashMap,
+        default: T,
+    ) -> Result<Vec<T>, EnvError> {
+        let Some(value) = map.get(key) else {
+            return Ok(vec![default]);
+        };
+        let mut output = vec![];
+        for item in value.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let item = item
+                .parse::<T>()
+                .map_err(|_| EnvError::Invalid(key.into()))?;
+            if !output.contains(&item) {
+                output.push(item);
+            }
+        }
+        if output.is_empty() {
+            return Err(EnvError::Invalid(key.into()));
+        }
+        Ok(output)
+    }
+
     /// Load, and parse .env file, return AppEnv
     fn generate() -> Result<Self, EnvError> {
         let map = env::vars()
@@ -84,16 +126,33 @@ impl AppEnv {
             api_host: Self::parse_string("API_HOST", &map)?,
             api_port: Self::parse_number("API_PORT", &map)?,
             location_logs: Self::parse_string("LOCATION_LOGS", &map)?,
+            location_photo_cache: Self::parse_string("LOCATION_PHOTO_CACHE", &map)
+                .unwrap_or_else(|_| LOCATION_PHOTO_CACHE.to_owned()),
             log_level: Self::parse_log(&map),
             pg_database: Self::parse_string("PG_DATABASE", &map)?,
             pg_host: Self::parse_string("PG_HOST", &map)?,
             pg_pass: Self::parse_string("PG_PASS", &map)?,
             pg_port: Self::parse_number("PG_PORT", &map)?,
             pg_user: Self::parse_string("PG_USER", &map)?,
+            photo_cache_max_mb: Self::parse_number("PHOTO_CACHE_MAX_MB", &map)
+                .unwrap_or(PHOTO_CACHE_MAX_MB),
             redis_database: Self::parse_number("REDIS_DATABASE", &map)?,
             redis_host: Self::parse_string("REDIS_HOST", &map)?,
             redis_password: Self::parse_string("REDIS_PASSWORD", &map)?,
             redis_port: Self::parse_number("REDIS_PORT", &map)?,
+            scrape_revalidate_days: Self::parse_number("SCRAPE_REVALIDATE_DAYS", &map)
+                .ok()
+                .filter(|days| *days > 0),
+            scrape_flightroute_sources: Self::parse_list(
+                "SCRAPE_FLIGHTROUTE_SOURCES",
+                &map,
+                FlightrouteProvider::CallsignHtml,
+            )?,
+            scrape_photo_sources: Self::parse_list(
+                "SCRAPE_PHOTO_SOURCES",
+                &map,
+                PhotoProvider::PhotoJson,
+            )?,
             url_aircraft_photo: Self::parse_string("URL_AIRCRAFT_PHOTO", &map)?,
             url_callsign: Self::parse_string("URL_CALLSIGN", &map)?,
             url_photo_prefix: Self::parse_string("URL_PHOTO_PREFIX", &map)?,
@@ -314,6 +373,26 @@ mod tests {
         assert!(result.is_err());
         match result.unwrap_err() {
             EnvError::NotFound(value) => assert_eq!(value, "U16_TEST"),
+            EnvError::Invalid(_) => unreachable!(),
         }
     }
+
+    #[test]
+    fn env_parse_list() {
+        let map = HashMap::new();
+        let result = AppEnv::parse_list("SOURCES", &map, FlightrouteProvider::CallsignHtml);
+        assert_eq!(result.unwrap(), [FlightrouteProvider::CallsignHtml]);
+
+        let map = HashMap::from([(S!("SOURCES"), S!(" photo_json , photo_json,"))]);
+        let result = AppEnv::parse_list("SOURCES", &map, PhotoProvider::PhotoJson);
+        assert_eq!(result.unwrap(), [PhotoProvider::PhotoJson]);
+
+        let map = HashMap::from([(S!("SOURCES"), S!("callsign_html,unknown"))]);
+        let result = AppEnv::parse_list("SOURCES", &map, FlightrouteProvider::CallsignHtml);
+        assert_eq!(result.unwrap_err().to_string(), "invalid env: 'SOURCES'");
+
+        let map = HashMap::from([(S!("SOURCES"), S!(" , "))]);
+        let result = AppEnv::parse_list("SOURCES", &map, FlightrouteProvider::CallsignHtml);
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,486 @@
+// Algorithmic conversion between a registration and a mode_s, for the registries that assign mode_s addresses from the registration
+// Based on the registration tables in readsb/dump1090/tar1090, each converter handles a single registration scheme
+use crate::{
+    api::{ModeS, NNumber, Registration, Validate},
+    n_number::{mode_s_to_n_number, n_number_to_mode_s},
+};
+
+/// Alphabet without I and O
+const LIMITED_ALPHABET: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ";
+const FULL_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// A single registration scheme, addresses are the numeric value of a mode_s, registrations are uppercase and have had any '-' removed
+trait RegistrationConverter: Sync {
+    /// Return None if the address isn't handled by this converter
+    fn to_registration(&self, address: u32) -> Option<String>;
+
+    /// Return None if the registration isn't handled by this converter
+    fn to_address(&self, registration: &str) -> Option<u32>;
+}
+
+/// United States N-Numbers, see n_number.rs
+struct UnitedStates;
+
+impl UnitedStates {
+    /// N1 to N99999
+    const FIRST: u32 = 0xA0_0001;
+    const LAST: u32 = 0xAD_F7C7;
+}
+
+impl RegistrationConverter for UnitedStates {
+    fn to_registration(&self, address: u32) -> Option<String> {
+        if !(Self::FIRST..=Self::LAST).contains(&address) {
+            return None;
+        }
+        let mode_s = ModeS::validate(&format!("{address:06X}")).ok()?;
+        mode_s_to_n_number(&mode_s).ok().map(|i| i.to_string())
+    }
+
+    fn to_address(&self, registration: &str) -> Option<u32> {
+        let n_number = NNumber::validate(registration).ok()?;
+        let mode_s = n_number_to_mode_s(&n_number).ok()?;
+        u32::from_str_radix(&mode_s.to_string(), 16).ok()
+    }
+}
+
+/// Japan, JA followed by two digits, then either a digit and a digit or letter, or two letters
+struct Japan;
+
+impl Japan {
+    const START: u32 = 0x84_0000;
+    const DIGIT_1: u32 = 22_984;
+    const DIGIT_2: u32 = 916;
+    const DIGIT_3: u32 = 34;
+    /// Offset, within a DIGIT_2 stride, where two letter suffixes start
+    const LETTERS: u32 = 340;
+}
+
+impl RegistrationConverter for Japan {
+    fn to_registration(&self, address: u32) -> Option<String> {
+        let mut offset = address.checked_sub(Self::START)?;
+        let digit_1 = offset / Self::DIGIT_1;
+        offset %= Self::DIGIT_1;
+        let digit_2 = offset / Self::DIGIT_2;
+        offset %= Self::DIGIT_2;
+        if digit_1 > 9 || digit_2 > 9 {
+            return None;
+        }
+
+        let suffix = if offset < Self::LETTERS {
+            let digit_3 = offset / Self::DIGIT_3;
+            offset %= Self::DIGIT_3;
+            if offset < 10 {
+                format!("{digit_3}{offset}")
+            } else {
+                format!("{digit_3}{}", letter(LIMITED_ALPHABET, offset - 10)?)
+            }
+        } else {
+            offset -= Self::LETTERS;
+            format!(
+                "{}{}",
+                letter(LIMITED_ALPHABET, offset / 24)?,
+                letter(LIMITED_ALPHABET, offset % 24)?
+            )
+        };
+        Some(format!("JA{digit_1}{digit_2}{suffix}"))
+    }
+
+    fn to_address(&self, registration: &str) -> Option<u32> {
+        let suffix = registration.strip_prefix("JA")?.chars().collect::<Vec<_>>();
+        let [digit_1, digit_2, third, fourth] = suffix.as_slice() else {
+            return None;
+        };
+        let mut offset =
+            digit_1.to_digit(10)? * Self::DIGIT_1 + digit_2.to_digit(10)? * Self::DIGIT_2;
+        if let Some(digit_3) = third.to_digit(10) {
+            offset += digit_3 * Self::DIGIT_3;
+            offset += fourth
+                .to_digit(10)
+                .or_else(|| letter_index(LIMITED_ALPHABET, *fourth).map(|i| i + 10))?;
+        } else {
+            offset += Self::LETTERS
+                + letter_index(LIMITED_ALPHABET, *third)? * 24
+                + letter_index(LIMITED_ALPHABET, *fourth)?;
+        }
+        Some(Self::START + offset)
+    }
+}
+
+/// South Korea, HL followed by four digits, which are the hex digits of an offset into one of a few small blocks
+struct SouthKorea;
+
+impl SouthKorea {
+    /// First address, last address, and the registration number of the first address, read as hex
+    const BLOCKS: [(u32, u32, u32); 3] = [
+        (0x71_BA00, 0x71_BF99, 0x7200),
+        (0x71_C000, 0x71_C099, 0x8000),
+        (0x71_C200, 0x71_C299, 0x8200),
+    ];
+}
+
+impl RegistrationConverter for SouthKorea {
+    fn to_registration(&self, address: u32) -> Option<String> {
+        let (start, _, base) = Self::BLOCKS
+            .iter()
+            .find(|(start, end, _)| (*start..=*end).contains(&address))?;
+        let number = format!("{:04X}", address - start + base);
+        number
+            .chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| format!("HL{number}"))
+    }
+
+    fn to_address(&self, registration: &str) -> Option<u32> {
+        let number = registration.strip_prefix("HL")?;
+        if number.len() != 4 || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let number = u32::from_str_radix(number, 16).ok()?;
+        Self::BLOCKS
+            .iter()
+            .find(|(start, end, base)| (*base..=base + (end - start)).contains(&number))
+            .map(|(start, _, base)| start + number - base)
+    }
+}
+
+/// A prefix followed by a three letter suffix, where each letter is a fixed stride of addresses
+/// Only suffixes between first and last, inclusive, are part of the scheme
+struct Stride {
+    start: u32,
+    major: u32,
+    minor: u32,
+    prefix: &'static str,
+    alphabet: &'static str,
+    first: &'static str,
+    last: &'static str,
+}
+
+impl Stride {
+    const fn new(start: u32, major: u32, minor: u32, prefix: &'static str) -> Self {
+        Self {
+            start,
+            major,
+            minor,
+            prefix,
+            alphabet: FULL_ALPHABET,
+            first: "AAA",
+            last: "ZZZ",
+        }
+    }
+
+    const fn range(self, first: &'static str, last: &'static str) -> Self {
+        Self {
+            first,
+            last,
+            ..self
+        }
+    }
+
+    /// Offset of a suffix, from an imaginary AAA at offset 0
+    fn suffix_offset(&self, suffix: &str) -> Option<u32> {
+        let suffix = suffix
+            .chars()
+            .map(|c| letter_index(self.alphabet, c))
+            .collect::<Option<Vec<_>>>()?;
+        let [first, second, third] = suffix.as_slice() else {
+            return None;
+        };
+        Some(first * self.major + second * self.minor + third)
+    }
+
+    fn offsets(&self) -> Option<(u32, u32)> {
+        Some((
+            self.suffix_offset(self.first)?,
+            self.suffix_offset(self.last)?,
+        ))
+    }
+}
+
+impl RegistrationConverter for Stride {
+    fn to_registration(&self, address: u32) -> Option<String> {
+        let (first, last) = self.offsets()?;
+        let offset = address.checked_sub(self.start)? + first;
+        if offset > last {
+            return None;
+        }
+        let suffix = [
+            offset / self.major,
+            offset % self.major / self.minor,
+            offset % self.major % self.minor,
+        ]
+        .into_iter()
+        .map(|i| letter(self.alphabet, i))
+        .collect::<Option<String>>()?;
+        Some(format!("{}{suffix}", self.prefix))
+    }
+
+    fn to_address(&self, registration: &str) -> Option<u32> {
+        let (first, last) = self.offsets()?;
+        let offset =
+            self.suffix_offset(registration.strip_prefix(&self.prefix.replace('-', ""))?)?;
+        (first..=last)
+            .contains(&offset)
+            .then(|| self.start + offset - first)
+    }
+}
+
+/// A prefix followed by a fixed width number, where each number is the next address
+struct Numeric {
+    start: u32,
+    first: u32,
+    count: u32,
+    prefix: &'static str,
+    width: usize,
+}
+
+impl RegistrationConverter for Numeric {
+    fn to_registration(&self, address: u32) -> Option<String> {
+        let offset = address.checked_sub(self.start)?;
+        (offset < self.count).then(|| {
+            format!(
+                "{}{:0width$}",
+                self.prefix,
+                self.first + offset,
+                width = self.width
+            )
+        })
+    }
+
+    fn to_address(&self, registration: &str) -> Option<u32> {
+        let number = registration.strip_prefix(&self.prefix.replace('-', ""))?;
+        if number.len() != self.width || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let offset = number.parse::<u32>().ok()?.checked_sub(self.first)?;
+        (offset < self.count).then(|| self.start + offset)
+    }
+}
+
+const STRIDES: [Stride; 29] = [
+    Stride::new(0x38_0000, 1024, 32, "F-B"),
+    Stride::new(0x38_8000, 1024, 32, "F-I"),
+    Stride::new(0x39_0000, 1024, 32, "F-G"),
+    Stride::new(0x39_8000, 1024, 32, "F-H"),
+    Stride::new(0x3A_0000, 1024, 32, "F-O"),
+    Stride::new(0x3C_4421, 1024, 32, "D-A").range("AAA", "OZZ"),
+    Stride::new(0x3C_0001, 676, 26, "D-A").range("PAA", "ZZZ"),
+    Stride::new(0x3C_8421, 1024, 32, "D-B").range("AAA", "OZZ"),
+    Stride::new(0x3C_2001, 676, 26, "D-B").range("PAA", "ZZZ"),
+    Stride::new(0x3C_C000, 676, 26, "D-C"),
+    Stride::new(0x3D_04A8, 676, 26, "D-E"),
+    Stride::new(0x3D_4950, 676, 26, "D-F"),
+    Stride::new(0x3D_8DF8, 676, 26, "D-G"),
+    Stride::new(0x3D_D2A0, 676, 26, "D-H"),
+    Stride::new(0x3E_1748, 676, 26, "D-I"),
+    Stride::new(0x44_8421, 1024, 32, "OO-"),
+    Stride::new(0x45_8421, 1024, 32, "OY-"),
+    Stride::new(0x46_0000, 676, 26, "OH-"),
+    Stride::new(0x46_8421, 1024, 32, "SX-"),
+    Stride::new(0x49_0421, 1024, 32, "CS-"),
+    Stride::new(0x4A_0421, 1024, 32, "YR-"),
+    Stride::new(0x4B_8421, 1024, 32, "TC-"),
+    Stride::new(0x74_0421, 1024, 32, "JY-"),
+    Stride::new(0x76_0421, 1024, 32, "AP-"),
+    Stride::new(0x76_8421, 1024, 32, "9V-"),
+    Stride::new(0x77_8421, 1024, 32, "YK-"),
+    Stride::new(0xC0_0001, 676, 26, "C-F"),
+    Stride::new(0xC0_44A9, 676, 26, "C-G"),
+    Stride::new(0xE0_1041, 4096, 64, "LV-"),
+];
+
+const NUMERICS: [Numeric; 2] = [
+    Numeric {
+        start: 0x14_0000,
+        first: 0,
+        count: 100_000,
+        prefix: "RA-",
+        width: 5,
+    },
+    Numeric {
+        start: 0x0B_03E8,
+        first: 1000,
+        count: 1000,
+        prefix: "CU-T",
+        width: 4,
+    },
+];
+
+fn letter(alphabet: &str, index: u32) -> Option<char> {
+    alphabet.chars().nth(usize::try_from(index).ok()?)
+}
+
+fn letter_index(alphabet: &str, c: char) -> Option<u32> {
+    alphabet
+        .chars()
+        .position(|i| i == c)
+        .and_then(|i| u32::try_from(i).ok())
+}
+
+/// Every converter, the address ranges, and registration prefixes, of each converter don't overlap
+fn converters() -> impl Iterator<Item = &'static dyn RegistrationConverter> {
+    [
+        &UnitedStates as &dyn RegistrationConverter,
+        &Japan,
+        &SouthKorea,
+    ]
+    .into_iter()
+    .chain(STRIDES.iter().map(|i| i as &dyn RegistrationConverter))
+    .chain(NUMERICS.iter().map(|i| i as &dyn RegistrationConverter))
+}
+
+/// Convert a mode_s to a registration, if the mode_s is in a block that is assigned algorithmically
+pub fn mode_s_to_registration(mode_s: &ModeS) -> Option<Registration> {
+    let address = u32::from_str_radix(&mode_s.to_string(), 16).ok()?;
+    converters()
+        .find_map(|converter| converter.to_registration(address))
+        .and_then(|registration| Registration::validate(&registration).ok())
+}
+
+/// Convert a registration to a mode_s, if the registration is part of a scheme that is assigned algorithmically
+pub fn registration_to_mode_s(registration: &Registration) -> Option<ModeS> {
+    let registration = registration.to_string().replace('-', "");
+    converters()
+        .find_map(|converter| converter.to_address(&registration))
+        .and_then(|address| ModeS::validate(&format!("{address:06X}")).ok())
+}
+
+/// cargo watch -q -c -w src/ -x 'test registration_converter -- --nocapture'
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::S;
+
+    fn to_registration(mode_s: &str) -> Option<String> {
+        mode_s_to_registration(&ModeS::validate(mode_s).unwrap()).map(|i| i.to_string())
+    }
+
+    fn to_mode_s(registration: &str) -> Option<String> {
+        registration_to_mode_s(&Registration::validate(registration).unwrap())
+            .map(|i| i.to_string())
+    }
+
+    /// Every address that a converter handles must convert back to the same address
+    fn round_trip(converter: &dyn RegistrationConverter, range: impl Iterator<Item = u32>) {
+        let mut count = 0;
+        for address in range {
+            if let Some(registration) = converter.to_registration(address) {
+                assert_eq!(
+                    converter.to_address(&registration.replace('-', "")),
+                    Some(address),
+                    "{registration}"
+                );
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn registration_converter_united_states() {
+        assert_eq!(to_registration("A00001"), Some(S!("N1")));
+        assert_eq!(to_registration("ADF7C7"), Some(S!("N99999")));
+        assert_eq!(to_registration("A00000"), None);
+        // US military
+        assert_eq!(to_registration("ADF7C8"), None);
+        assert_eq!(to_mode_s("N343NB"), Some(S!("A3C9A1")));
+        assert_eq!(to_mode_s("n90mc"), Some(S!("AC6DE9")));
+    }
+
+    #[test]
+    fn registration_converter_canada() {
+        assert_eq!(to_registration("C00001"), Some(S!("C-FAAA")));
+        assert_eq!(to_registration("C044A8"), Some(S!("C-FZZZ")));
+        assert_eq!(to_registration("C044A9"), Some(S!("C-GAAA")));
+        assert_eq!(to_registration("C08950"), Some(S!("C-GZZZ")));
+        assert_eq!(to_registration("C08951"), None);
+        assert_eq!(to_mode_s("C-FAAA"), Some(S!("C00001")));
+        assert_eq!(to_mode_s("CGAAB"), Some(S!("C044AA")));
+        round_trip(&STRIDES[26], 0xC0_0000..=0xC0_9000);
+        round_trip(&STRIDES[27], 0xC0_0000..=0xC0_9000);
+    }
+
+    #[test]
+    fn registration_converter_japan() {
+        assert_eq!(to_registration("840000"), Some(S!("JA0000")));
+        assert_eq!(to_registration("84000A"), Some(S!("JA000A")));
+        assert_eq!(to_mode_s("JA0000"), Some(S!("840000")));
+        assert_eq!(to_mode_s("JA0010"), Some(S!("840022")));
+        // I and O aren't used
+        assert_eq!(to_mode_s("JA01IO"), None);
+        assert_eq!(to_mode_s("JA0AAA"), None);
+        round_trip(&Japan, 0x84_0000..=0x87_FFFF);
+    }
+
+    #[test]
+    fn registration_converter_south_korea() {
+        assert_eq!(to_registration("71BA00"), Some(S!("HL7200")));
+        assert_eq!(to_registration("71BA99"), Some(S!("HL7299")));
+        // Hex digits aren't valid registrations
+        assert_eq!(to_registration("71BA0A"), None);
+        assert_eq!(to_registration("71C000"), Some(S!("HL8000")));
+        assert_eq!(to_mode_s("HL7299"), Some(S!("71BA99")));
+        assert_eq!(to_mode_s("HL8201"), Some(S!("71C201")));
+        assert_eq!(to_mode_s("HL8100"), None);
+        round_trip(&SouthKorea, 0x71_8000..=0x71_FFFF);
+    }
+
+    #[test]
+    fn registration_converter_stride() {
+        assert_eq!(to_registration("448421"), Some(S!("OO-AAA")));
+        assert_eq!(to_registration("380000"), Some(S!("F-BAAA")));
+        assert_eq!(to_mode_s("F-GKXA"), Some(S!("392AE0")));
+        assert_eq!(to_mode_s("D-AIAB"), Some(S!("3C6422")));
+        assert_eq!(to_mode_s("D-APAA"), Some(S!("3C0001")));
+        for (index, stride) in STRIDES.iter().enumerate() {
+            let (first, last) = stride.offsets().unwrap();
+            let end = stride.start + last - first;
+            round_trip(
+                stride,
+                (stride.start..stride.start + 2048).chain(end - 2048..=end),
+            );
+            assert_eq!(
+                stride.to_registration(stride.start),
+                Some(format!("{}{}", stride.prefix, stride.first)),
+                "{index}"
+            );
+        }
+    }
+
+    #[test]
+    fn registration_converter_numeric() {
+        assert_eq!(to_registration("140000"), Some(S!("RA-00000")));
+        assert_eq!(to_registration("140001"), Some(S!("RA-00001")));
+        assert_eq!(to_mode_s("RA-89001"), Some(S!("155BA9")));
+        assert_eq!(to_registration("0B03E8"), Some(S!("CU-T1000")));
+        assert_eq!(to_mode_s("CU-T1999"), Some(S!("0B07CF")));
+        assert_eq!(to_mode_s("CU-T2000"), None);
+        for numeric in &NUMERICS {
+            round_trip(numeric, numeric.start..=numeric.start + numeric.count);
+        }
+    }
+
+    #[test]
+    /// Only a single stride should handle any given address
+    fn registration_converter_no_overlap() {
+        let ranges = STRIDES
+            .iter()
+            .map(|stride| {
+                let (first, last) = stride.offsets().unwrap();
+                (stride.start, stride.start + last - first)
+            })
+            .collect::<Vec<_>>();
+        for (index, (start, end)) in ranges.iter().enumerate() {
+            for (other_start, other_end) in &ranges[index + 1..] {
+                assert!(end < other_start || other_end < start, "{start:06X}");
+            }
+        }
+    }
+
+    #[test]
+    fn registration_converter_unknown() {
+        assert_eq!(to_registration("400F1A"), None);
+        assert_eq!(to_mode_s("G-EUPT"), None);
+        assert_eq!(to_mode_s("N"), None);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{fmt, str::FromStr};
+
+use crate::db_redis::{LOOKUP_TTL, ONE_MINUTE_AS_SEC};
+
+use super::ScrapedFlightroute;
+
+const ONE_HOUR_AS_SEC: i64 = ONE_MINUTE_AS_SEC * 60;
+const ONE_DAY_AS_SEC: i64 = ONE_HOUR_AS_SEC * 24;
+
+/// Permanent failures backoff for at most this many lookup cache ttls
+const MAX_PERMANENT_MULTIPLIER: i64 = 8;
+
+/// What was being scraped, stored in the scrape_attempt table alongside the search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeKind {
+    Callsign,
+    Photo,
+}
+
+impl ScrapeKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Callsign => "callsign",
+            Self::Photo => "photo",
+        }
+    }
+}
+
+/// Why a scrape didn't produce a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrapeFailure {
+    /// Source didn't respond within SCRAPE_TIMEOUT
+    Timeout,
+    /// Request to the source failed, or the response body couldn't be read
+    Request,
+    /// Source's circuit breaker is open, after too many consecutive failures, so it wasn't requested
+    CircuitOpen,
+    /// Source responded, but a result couldn't be extracted from the response, e.g. a truncated page, or a changed layout
+    Parse,
+    /// Source responded, without a result
+    NotFound,
+    /// Flightroute origin and destination are the same airport
+    AirportClash,
+    /// Flightroute origin, or destination, isn't in the database
+    UnknownAirport,
+    /// Flightroute airline isn't in the database
+    UnknownAirline,
+}
+
+impl ScrapeFailure {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Request => "request",
+            Self::CircuitOpen => "circuit_open",
+            Self::Parse => "parse",
+            Self::NotFound => "not_found",
+            Self::AirportClash => "airport_clash",
+            Self::UnknownAirport => "unknown_airport",
+            Self::UnknownAirline => "unknown_airline",
+        }
+    }
+
+    /// Timeouts, request errors, open circuits, and responses that couldn't be parsed, might succeed if tried again soon
+    pub const fn is_transient(self) -> bool {
+        matches!(
+            self,
+            Self::Timeout | Self::Request | Self::CircuitOpen | Self::Parse
+        )
+    }
+
+    /// Seconds until the next scrape is allowed, doubling with each consecutive failed attempt.
+    /// Parse failures start at an hour, up to a day, as a changed layout won't be fixed within minutes.
+    /// Other transient failures start at one minute, up to an hour.
+    /// Everything else starts at the lookup cache ttl, so the source isn't requested again as soon as the cached unknown expires
+    pub fn backoff(self, attempts: i32) -> i64 {
+        let (base, max) = match self {
+            Self::Parse => (ONE_HOUR_AS_SEC, ONE_DAY_AS_SEC),
+            _ if self.is_transient() => (ONE_MINUTE_AS_SEC, ONE_HOUR_AS_SEC),
+            _ => (
+                LOOKUP_TTL,
+                LOOKUP_TTL.saturating_mul(MAX_PERMANENT_MULTIPLIER),
+            ),
+        };
+        let exponent = u32::try_from(attempts.saturating_sub(1)).unwrap_or_default();
+        base.saturating_mul(2_i64.saturating_pow(exponent)).min(max)
+    }
+}
+
+impl FromStr for ScrapeFailure {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "timeout" => Ok(Self::Timeout),
+            "request" => Ok(Self::Request),
+            "circuit_open" => Ok(Self::CircuitOpen),
+            "parse" => Ok(Self::Parse),
+            "not_found" => Ok(Self::NotFound),
+            "airport_clash" => Ok(Self::AirportClash),
+            "unknown_airport" => Ok(Self::UnknownAirport),
+            "unknown_airline" => Ok(Self::UnknownAirline),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ScrapeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A failed scrape, with a human readable reason, e.g. which source failed, or the clashing airports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedScrape {
+    pub failure: ScrapeFailure,
+    pub reason: String,
+    /// The scraped flightroute, if it was scraped but couldn't be inserted, so that it can be reviewed
+    pub flightroute: Option<ScrapedFlightroute>,
+}
+
+impl FailedScrape {
+    pub fn new(failure: ScrapeFailure, reason: impl Into<String>) -> Self {
+        Self {
+            failure,
+            reason: reason.into(),
+            flightroute: None,
+        }
+    }
+
+    /// Prefix the reason with the name of the source that failed
+    pub fn with_source(self, name: &str) -> Self {
+        Self {
+            reason: format!("{name}: {}", self.reason),
+            ..self
+        }
+    }
+
+    pub fn with_flightroute(self, flightroute: &ScrapedFlightroute) -> Self {
+        Self {
+            flightroute: Some(flightroute.clone()),
+            ..self
+        }
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_attempt '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scraper_attempt_backoff_transient() {
+        assert_eq!(ScrapeFailure::Timeout.backoff(1), 60);
+        assert_eq!(ScrapeFailure::Timeout.backoff(2), 120);
+        assert_eq!(ScrapeFailure::Request.backoff(3), 240);
+        assert_eq!(ScrapeFailure::CircuitOpen.backoff(1), 60);
+        assert_eq!(ScrapeFailure::Timeout.backoff(7), 3_600);
+        assert_eq!(ScrapeFailure::Timeout.backoff(i32::MAX), 3_600);
+        // attempts should always be at least 1, but never backoff less than the base
+        assert_eq!(ScrapeFailure::Timeout.backoff(0), 60);
+        assert_eq!(ScrapeFailure::Timeout.backoff(-1), 60);
+    }
+
+    #[test]
+    fn scraper_attempt_backoff_parse() {
+        assert!(ScrapeFailure::Parse.is_transient());
+        assert_eq!(ScrapeFailure::Parse.backoff(1), 3_600);
+        assert_eq!(ScrapeFailure::Parse.backoff(2), 7_200);
+        assert_eq!(ScrapeFailure::Parse.backoff(5), 57_600);
+        assert_eq!(ScrapeFailure::Parse.backoff(6), 86_400);
+        assert_eq!(ScrapeFailure::Parse.backoff(i32::MAX), 86_400);
+    }
+
+    #[test]
+    fn scraper_attempt_backoff_permanent() {
+        for failure in [
+            ScrapeFailure::NotFound,
+            ScrapeFailure::AirportClash,
+            ScrapeFailure::UnknownAirport,
+            ScrapeFailure::UnknownAirline,
+        ] {
+            assert!(!failure.is_transient());
+            assert_eq!(failure.backoff(1), 604_800);
+            assert_eq!(failure.backoff(2), 1_209_600);
+            assert_eq!(failure.backoff(3), 2_419_200);
+            assert_eq!(failure.backoff(4), 4_838_400);
+            assert_eq!(failure.backoff(5), 4_838_400);
+            assert_eq!(failure.backoff(i32::MAX), 4_838_400);
+        }
+    }
+
+    #[test]
+    fn scraper_attempt_from_str() {
+        for failure in [
+            ScrapeFailure::Timeout,
+            ScrapeFailure::Request,
+            ScrapeFailure::CircuitOpen,
+            ScrapeFailure::Parse,
+            ScrapeFailure::NotFound,
+            ScrapeFailure::AirportClash,
+            ScrapeFailure::UnknownAirport,
+            ScrapeFailure::UnknownAirline,
+        ] {
+            assert_eq!(failure.as_str().parse::<ScrapeFailure>(), Ok(failure));
+        }
+        assert!("success".parse::<ScrapeFailure>().is_err());
+    }
+
+    #[test]
+    fn scraper_attempt_as_str() {
+        assert_eq!(ScrapeKind::Callsign.as_str(), "callsign");
+        assert_eq!(ScrapeKind::Photo.as_str(), "photo");
+        assert_eq!(ScrapeFailure::Timeout.to_string(), "timeout");
+        assert_eq!(ScrapeFailure::CircuitOpen.to_string(), "circuit_open");
+        assert_eq!(ScrapeFailure::NotFound.to_string(), "not_found");
+        assert_eq!(ScrapeFailure::AirportClash.to_string(), "airport_clash");
+        assert_eq!(ScrapeFailure::UnknownAirport.to_string(), "unknown_airport");
+        assert_eq!(ScrapeFailure::UnknownAirline.to_string(), "unknown_airline");
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use fred::clients::Pool;
+use sqlx::PgPool;
+
+use crate::{
+    api::AppError,
+    db_postgres::{AircraftId, ModelAircraft, ModelMissingPhotographer},
+    db_redis::delete_aircraft_cache,
+    parse_env::AppEnv,
+};
+
+use super::{Scraper, Sources};
+
+/// Wait between each re-scrape, so that the backfill doesn't take upstream capacity away from requested photos
+const BACKFILL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Result of re-scraping the photos of a single aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backfilled {
+    /// Photos have been re-scraped, or can't be, continue after this aircraft
+    Next(AircraftId),
+    /// Transient failure, re-scrape the same aircraft after BACKFILL_INTERVAL
+    Retry,
+    /// No aircraft left with a photo that has no photographer
+    Complete,
+}
+
+/// Background job to re-scrape the photos of aircraft whose gallery has a photo without a photographer, one aircraft every BACKFILL_INTERVAL.
+/// Makes a single pass, in aircraft_id order, so a photo that no source can credit is only re-scraped once per start
+#[derive(Debug)]
+pub struct PhotoBackfill {
+    postgres: PgPool,
+    redis: Pool,
+    sources: Sources,
+}
+
+impl PhotoBackfill {
+    /// Spawn the backfill in a tokio thread, only if photo scraping is enabled
+    pub fn start(app_env: &AppEnv, postgres: PgPool, redis: Pool, sources: Sources) {
+        if app_env.allow_scrape_photo.is_none() {
+            return;
+        }
+        let backfill = Self {
+            postgres,
+            redis,
+            sources,
+        };
+        tokio::spawn(async move {
+            backfill.run().await;
+        });
+    }
+
+    async fn run(&self) {
+        let mut after = None;
+        loop {
+            match self.backfill_next(after).await {
+                Ok(Backfilled::Next(aircraft_id)) => after = Some(aircraft_id),
+                Ok(Backfilled::Retry) => (),
+                Ok(Backfilled::Complete) => {
+                    tracing::info!("photo backfill: complete");
+                    return;
+                }
+                Err(e) => tracing::error!("{e}"),
+            }
+            tokio::time::sleep(BACKFILL_INTERVAL).await;
+        }
+    }
+
+    /// Re-scrape the photos of the next aircraft, after the given aircraft_id, that has a photo without a photographer
+    async fn backfill_next(&self, after: Option<AircraftId>) -> Result<Backfilled, AppError> {
+        let Some(aircraft) = ModelAircraft::get_missing_photographer(&self.postgres, after).await?
+        else {
+            return Ok(Backfilled::Complete);
+        };
+        self.backfill(&aircraft).await
+    }
+
+    /// Replace the aircraft's gallery with freshly scraped photos, which will include the photographer when the source knows it
+    async fn backfill(&self, aircraft: &ModelMissingPhotographer) -> Result<Backfilled, AppError> {
+        let mode_s = &aircraft.mode_s;
+        match Scraper::scrape_photo(&self.sources, mode_s).await {
+            Ok(photos) => {
+                ModelAircraft::insert_photo(&self.postgres, &photos, mode_s).await?;
+                delete_aircraft_cache(&self.redis, mode_s, &aircraft.registration).await?;
+                tracing::info!("{mode_s}: photo backfill: {} photos", photos.len());
+            }
+            Err(failed) => {
+                tracing::info!(
+                    "{mode_s}: photo backfill: {}: {}",
+                    failed.failure,
+                    failed.reason
+                );
+                if failed.failure.is_transient() {
+                    return Ok(Backfilled::Retry);
+                }
+            }
+        }
+        Ok(Backfilled::Next(aircraft.aircraft_id))
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_backfill '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        S,
+        api::{AircraftSearch, ModeS},
+        db_postgres, db_redis,
+        scraper::tests::remove_scraped_data,
+    };
+
+    async fn setup() -> (AppEnv, PhotoBackfill) {
+        let mut app_env = AppEnv::get_env();
+        let postgres = db_postgres::get_pool(&app_env).await.unwrap();
+        let redis = db_redis::get_pool(&app_env).await.unwrap();
+        remove_scraped_data(&postgres).await;
+        let backfill = PhotoBackfill {
+            postgres,
+            redis,
+            sources: Sources::mock(&mut app_env).await,
+        };
+        (app_env, backfill)
+    }
+
+    async fn get_aircraft(
+        app_env: &AppEnv,
+        backfill: &PhotoBackfill,
+        mode_s: &ModeS,
+    ) -> ModelAircraft {
+        ModelAircraft::get(
+            &backfill.postgres,
+            &AircraftSearch::ModeS(mode_s.clone()),
+            &app_env.url_photo_prefix,
+        )
+        .await
+        .unwrap()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    /// A photo stored without a photographer is credited once re-scraped
+    async fn scraper_backfill_photographer() {
+        let (app_env, backfill) = setup().await;
+        let mode_s = ModeS::from(S!("393C00"));
+
+        let mut photos = Scraper::scrape_photo(&backfill.sources, &mode_s)
+            .await
+            .unwrap();
+        for photo in &mut photos {
+            photo.photographer = None;
+        }
+        ModelAircraft::insert_photo(&backfill.postgres, &photos, &mode_s)
+            .await
+            .unwrap();
+        let aircraft = get_aircraft(&app_env, &backfill, &mode_s).await;
+        assert!(aircraft.photos.0[0].photographer.is_none());
+
+        let before = AircraftId::from(aircraft.aircraft_id.get() - 1);
+        let result = backfill.backfill_next(Some(before)).await.unwrap();
+        assert_eq!(result, Backfilled::Next(aircraft.aircraft_id));
+
+        let aircraft = get_aircraft(&app_env, &backfill, &mode_s).await;
+        assert_eq!(
+            aircraft.photos.0[0].photographer.as_deref(),
+            Some("Example Photographer")
+        );
+
+        // The second photo has no photographer upstream, but the aircraft isn't re-scraped again in the same pass
+        let result = backfill
+            .backfill_next(Some(aircraft.aircraft_id))
+            .await
+            .unwrap();
+        assert_ne!(result, Backfilled::Next(aircraft.aircraft_id));
+        remove_scraped_data(&backfill.postgres).await;
+    }
+}
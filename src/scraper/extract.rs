@@ -0,0 +1,209 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use ::scraper::{Html, Selector};
+use thiserror::Error;
+
+use crate::api::{Callsign, Validate};
+
+use super::ScrapedFlightroute;
+
+static TITLE: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("head > title").unwrap_or_else(|_| unreachable!()));
+static SCRIPT: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("script").unwrap_or_else(|_| unreachable!()));
+
+/// Function that sets the ad targeting, with the airports as key/value pairs
+const TARGETING: &str = "setTargeting(";
+
+/// What was missing, or invalid, in a scraped flightroute page
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExtractError {
+    #[error("missing title")]
+    MissingTitle,
+    #[error("missing {0} callsign in title: {1}")]
+    MissingCallsign(&'static str, String),
+    #[error("missing {0} airport")]
+    MissingAirport(&'static str),
+    #[error("invalid {0} airport: {1}")]
+    InvalidAirport(&'static str, String),
+}
+
+/// Read a single or double quoted string from the start of the input, returning the string and the rest of the input
+fn quoted(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start();
+    let quote = input.chars().next().filter(|i| *i == '\'' || *i == '"')?;
+    input[1..].split_once(quote)
+}
+
+/// Every `setTargeting('key', 'value')` pair, in any script element, the first value for each key is kept
+fn targeting(document: &Html) -> HashMap<String, String> {
+    let mut output = HashMap::new();
+    for script in document.select(&SCRIPT) {
+        let text = script.text().collect::<String>();
+        for (_, call) in text
+            .match_indices(TARGETING)
+            .map(|(i, _)| text.split_at(i + TARGETING.len()))
+        {
+            if let Some((key, rest)) = quoted(call)
+                && let Some(rest) = rest.trim_start().strip_prefix(',')
+                && let Some((value, _)) = quoted(rest)
+            {
+                output
+                    .entry(key.trim().to_owned())
+                    .or_insert_with(|| value.trim().to_owned());
+            }
+        }
+    }
+    output
+}
+
+/// The title starts with the IATA callsign, followed by the ICAO callsign in brackets, e.g. `NH460 (ANA460) ...`
+fn callsigns(document: &Html) -> Result<(Callsign, Callsign), ExtractError> {
+    let title = document
+        .select(&TITLE)
+        .next()
+        .map(|i| i.text().collect::<String>())
+        .filter(|i| !i.trim().is_empty())
+        .ok_or(ExtractError::MissingTitle)?;
+    let title = title.trim();
+    let callsigns = title
+        .split_once(')')
+        .map_or(title, |(callsigns, _)| callsigns)
+        .replace('(', " ");
+    let mut callsigns = callsigns.split_whitespace();
+    let (iata, icao) = (
+        callsigns.next().unwrap_or_default(),
+        callsigns.next().unwrap_or_default(),
+    );
+
+    let callsign_iata = Callsign::validate(iata)
+        .ok()
+        .filter(|i| matches!(i, Callsign::Iata(_)))
+        .ok_or_else(|| ExtractError::MissingCallsign("iata", title.to_owned()))?;
+    let callsign_icao = Callsign::validate(icao)
+        .ok()
+        .filter(|i| matches!(i, Callsign::Icao(_)))
+        .ok_or_else(|| ExtractError::MissingCallsign("icao", title.to_owned()))?;
+    Ok((callsign_iata, callsign_icao))
+}
+
+/// Get, and check, an airport from the targeting, an ICAO airport code is four ascii letters or numbers
+fn airport(
+    targeting: &HashMap<String, String>,
+    key: &'static str,
+) -> Result<Option<String>, ExtractError> {
+    match targeting.get(key).filter(|i| !i.is_empty()) {
+        None => Ok(None),
+        Some(code)
+            if code.len() == 4
+                && code
+                    .chars()
+                    .all(|i| i.is_ascii_uppercase() || i.is_ascii_digit()) =>
+        {
+            Ok(Some(code.to_owned()))
+        }
+        Some(code) => Err(ExtractError::InvalidAirport(key, code.to_owned())),
+    }
+}
+
+/// Extract the callsigns, from the page title, and the ICAO origin, optional midpoint, and destination airports, from the ad targeting scripts
+pub fn flightroute(html: &str) -> Result<ScrapedFlightroute, ExtractError> {
+    let document = Html::parse_document(html);
+    let (callsign_iata, callsign_icao) = callsigns(&document)?;
+    let targeting = targeting(&document);
+
+    let origin = airport(&targeting, "origin")?.ok_or(ExtractError::MissingAirport("origin"))?;
+    let midpoint = airport(&targeting, "midpoint")?;
+    let destination =
+        airport(&targeting, "destination")?.ok_or(ExtractError::MissingAirport("destination"))?;
+
+    Ok(ScrapedFlightroute {
+        callsign_icao,
+        callsign_iata,
+        origin,
+        midpoint,
+        destination,
+    })
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_extract '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        S,
+        scraper::tests::{TEST_DESTINATION, TEST_ORIGIN},
+    };
+
+    fn ana460(midpoint: Option<&str>) -> ScrapedFlightroute {
+        ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
+            origin: S!(TEST_ORIGIN),
+            midpoint: midpoint.map(ToOwned::to_owned),
+            destination: S!(TEST_DESTINATION),
+        }
+    }
+
+    #[test]
+    fn scraper_extract_fixtures() {
+        for (fixture, expected) in [
+            (
+                include_str!("./fixtures/flightroute.html"),
+                Ok(ana460(None)),
+            ),
+            (
+                include_str!("./fixtures/flightroute_midpoint.html"),
+                Ok(ana460(Some("RJFF"))),
+            ),
+            (
+                include_str!("./fixtures/flightroute_double_quotes.html"),
+                Ok(ana460(None)),
+            ),
+            (
+                include_str!("./fixtures/no_title.html"),
+                Err(ExtractError::MissingTitle),
+            ),
+            (
+                include_str!("./fixtures/no_icao_callsign.html"),
+                Err(ExtractError::MissingCallsign(
+                    "icao",
+                    S!("NH460 All Nippon Airways Flight Tracking"),
+                )),
+            ),
+            (
+                include_str!("./fixtures/no_destination.html"),
+                Err(ExtractError::MissingAirport("destination")),
+            ),
+            (
+                include_str!("./fixtures/invalid_origin.html"),
+                Err(ExtractError::InvalidAirport("origin", S!("Tokyo"))),
+            ),
+        ] {
+            assert_eq!(flightroute(fixture), expected);
+        }
+    }
+
+    #[test]
+    fn scraper_extract_error_message() {
+        assert_eq!(
+            ExtractError::MissingAirport("origin").to_string(),
+            "missing origin airport"
+        );
+        assert_eq!(
+            ExtractError::InvalidAirport("midpoint", S!("rjff")).to_string(),
+            "invalid midpoint airport: rjff"
+        );
+    }
+
+    #[test]
+    fn scraper_extract_quoted() {
+        assert_eq!(quoted(" 'origin', 'RJTT'"), Some(("origin", ", 'RJTT'")));
+        assert_eq!(quoted("\"RJTT\")"), Some(("RJTT", ")")));
+        assert_eq!(quoted("origin"), None);
+        assert_eq!(quoted("'origin"), None);
+    }
+}
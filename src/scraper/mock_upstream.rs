@@ -0,0 +1,328 @@
+// Local stand-in for the third party callsign and photo sites, so that tests use the production sources, and upstream client,
+// against scripted responses, rather than hitting a live site
+use std::{collections::HashMap, net::SocketAddr};
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+};
+use serde_json::json;
+use tower_http::compression::CompressionLayer;
+
+use crate::parse_env::AppEnv;
+
+use super::{FlightrouteProvider, PhotoProvider, SCRAPE_TIMEOUT, Sources, tests::TEST_CALLSIGN};
+
+/// Responds with the flightroute fixture, or photos, after SCRAPE_TIMEOUT
+pub const CALLSIGN_SLOW: &str = "ANA901";
+/// Responds with a 500
+pub const CALLSIGN_ERROR: &str = "ANA902";
+/// Responds with a truncated page, with callsigns but without airports
+pub const CALLSIGN_MALFORMED: &str = "ANA903";
+/// Redirects to TEST_CALLSIGN
+pub const CALLSIGN_REDIRECT: &str = "ANA904";
+
+/// Has two photos
+pub const MODE_S: &str = "393C00";
+pub const MODE_S_SLOW: &str = "393C01";
+pub const MODE_S_ERROR: &str = "393C02";
+pub const MODE_S_MALFORMED: &str = "393C03";
+pub const MODE_S_REDIRECT: &str = "393C04";
+
+/// Photo image urls have a 56 char prefix, including the slash before the path, which is removed by deserialize_url
+const PHOTO_PREFIX: &str = "https://www.xxxxxxxxxxxx.xxx/xxxxxx/xxxxxxxx/xxxxxxxxxx";
+
+/// Served for every photo, and thumbnail, path, just the start and end of image markers
+pub const PHOTO_BYTES: &[u8] = &[0xFF, 0xD8, 0xFF, 0xD9];
+
+const FLIGHTROUTE: &str = include_str!("./fixtures/flightroute.html");
+
+/// Longer than SCRAPE_TIMEOUT, so the scrape times out
+async fn slow() {
+    tokio::time::sleep(SCRAPE_TIMEOUT + std::time::Duration::from_secs(1)).await;
+}
+
+/// Gzip compressed html, for every callsign that isn't scripted the page doesn't contain a flightroute
+async fn flightroute(Path(callsign): Path<String>) -> Response {
+    match callsign.as_str() {
+        TEST_CALLSIGN => Html(FLIGHTROUTE).into_response(),
+        CALLSIGN_SLOW => {
+            slow().await;
+            Html(FLIGHTROUTE).into_response()
+        }
+        CALLSIGN_ERROR => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        CALLSIGN_MALFORMED => Html("<html><head><title>NH460 (ANA460) ").into_response(),
+        CALLSIGN_REDIRECT => {
+            Redirect::temporary(&format!("/flightroute/{TEST_CALLSIGN}")).into_response()
+        }
+        _ => (
+            StatusCode::NOT_FOUND,
+            Html("<html><head><title>Flight Not Found</title></head></html>"),
+        )
+            .into_response(),
+    }
+}
+
+fn photos() -> Response {
+    Json(json!({
+        "status": 200,
+        "count": 2,
+        "data": [
+            {
+                "image": format!("{PHOTO_PREFIX}/001/001/example.jpg"),
+                "link": "https://www.example.com/aircraft/photo/001001.html",
+                "photographer": "Example Photographer"
+            },
+            {
+                "image": format!("{PHOTO_PREFIX}/001/002/example.jpg")
+            }
+        ]
+    }))
+    .into_response()
+}
+
+/// Brotli compressed json, every mode_s that isn't scripted has no photos
+async fn photo(Query(query): Query<HashMap<String, String>>) -> Response {
+    match query.get("m").map(String::as_str) {
+        Some(MODE_S) => photos(),
+        Some(MODE_S_SLOW) => {
+            slow().await;
+            photos()
+        }
+        Some(MODE_S_ERROR) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        Some(MODE_S_MALFORMED) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            r#"{"status":200,"count":2,"data":[{"image":"#,
+        )
+            .into_response(),
+        Some(MODE_S_REDIRECT) => {
+            Redirect::temporary(&format!("/photo/ac_thumb.json?m={MODE_S}&n=1")).into_response()
+        }
+        _ => Json(json!({"status": 404, "count": 0})).into_response(),
+    }
+}
+
+/// The image behind any photo url composed with url_photo_prefix
+async fn image() -> Response {
+    ([(header::CONTENT_TYPE, "image/jpeg")], PHOTO_BYTES).into_response()
+}
+
+/// Serves on an ephemeral port until the test's runtime is dropped
+#[derive(Debug, Clone, Copy)]
+pub struct MockUpstream {
+    addr: SocketAddr,
+}
+
+impl MockUpstream {
+    pub async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route(
+                "/flightroute/{callsign}",
+                get(flightroute).layer(CompressionLayer::new().no_br().no_deflate().no_zstd()),
+            )
+            .route(
+                "/photo/ac_thumb.json",
+                get(photo).layer(CompressionLayer::new().no_gzip().no_deflate().no_zstd()),
+            )
+            .route("/images/{*path}", get(image));
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        Self { addr }
+    }
+
+    pub fn url_callsign(&self) -> String {
+        format!("http://{}/flightroute", self.addr)
+    }
+
+    pub fn url_aircraft_photo(&self) -> String {
+        format!("http://{}/photo/", self.addr)
+    }
+
+    pub fn url_photo_prefix(&self) -> String {
+        format!("http://{}/images/", self.addr)
+    }
+
+    /// Point the app_env urls at the mock, with every provider enabled
+    pub fn point(&self, app_env: &mut AppEnv) {
+        app_env.url_callsign = self.url_callsign();
+        app_env.url_aircraft_photo = self.url_aircraft_photo();
+        app_env.url_photo_prefix = self.url_photo_prefix();
+        app_env.scrape_flightroute_sources = vec![FlightrouteProvider::CallsignHtml];
+        app_env.scrape_photo_sources = vec![PhotoProvider::PhotoJson];
+    }
+}
+
+impl Sources {
+    /// Production sources, built from an app_env that's been pointed at a newly started mock upstream
+    pub async fn mock(app_env: &mut AppEnv) -> Self {
+        MockUpstream::start().await.point(app_env);
+        Self::new(app_env).unwrap()
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_mock_upstream '
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        S,
+        api::{Callsign, ModeS, Validate},
+        scraper::{
+            FailedScrape, ScrapeFailure, ScrapedFlightroute, Scraper,
+            source::{CallsignHtml, FlightrouteSource, PhotoJson, PhotoSource},
+            tests::{TEST_DESTINATION, TEST_ORIGIN},
+            upstream::Upstream,
+        },
+    };
+
+    /// A new upstream client for each source, so that the 500 responses don't open a shared circuit
+    fn sources(mock: &MockUpstream) -> (CallsignHtml, PhotoJson) {
+        (
+            CallsignHtml::new(&Upstream::new().unwrap(), &mock.url_callsign()),
+            PhotoJson::new(&Upstream::new().unwrap(), &mock.url_aircraft_photo()),
+        )
+    }
+
+    #[tokio::test]
+    async fn scraper_mock_upstream_compression() {
+        let mock = MockUpstream::start().await;
+        let client = reqwest::Client::builder()
+            .no_gzip()
+            .no_brotli()
+            .build()
+            .unwrap();
+
+        for (url, encoding) in [
+            (format!("{}/{TEST_CALLSIGN}", mock.url_callsign()), "gzip"),
+            (
+                format!("{}ac_thumb.json?m={MODE_S}&n=1", mock.url_aircraft_photo()),
+                "br",
+            ),
+        ] {
+            let resp = client
+                .get(url)
+                .header(header::ACCEPT_ENCODING, "gzip, br")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+                encoding
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn scraper_mock_upstream_flightroute() {
+        let mock = MockUpstream::start().await;
+        let (source, _) = sources(&mock);
+        let expected = ScrapedFlightroute {
+            callsign_icao: Callsign::validate(TEST_CALLSIGN).unwrap(),
+            callsign_iata: Callsign::validate("NH460").unwrap(),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!(TEST_DESTINATION),
+        };
+
+        for callsign in [TEST_CALLSIGN, CALLSIGN_REDIRECT] {
+            let callsign = Callsign::validate(callsign).unwrap();
+            assert_eq!(
+                source.flightroute(&callsign).await,
+                Ok(expected.clone()),
+                "{callsign}"
+            );
+        }
+
+        // The reason is the extract error, or the upstream response, and an unknown callsign is a 404
+        for (callsign, failure, reason) in [
+            (
+                CALLSIGN_MALFORMED,
+                ScrapeFailure::Parse,
+                "missing origin airport",
+            ),
+            (
+                CALLSIGN_ERROR,
+                ScrapeFailure::Request,
+                "127.0.0.1: upstream status 500 Internal Server Error",
+            ),
+            ("ANA999", ScrapeFailure::NotFound, "upstream status 404"),
+        ] {
+            let callsign = Callsign::validate(callsign).unwrap();
+            assert_eq!(
+                source.flightroute(&callsign).await,
+                Err(FailedScrape::new(failure, reason)),
+                "{callsign}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn scraper_mock_upstream_photo() {
+        let mock = MockUpstream::start().await;
+        let (_, source) = sources(&mock);
+
+        for mode_s in [MODE_S, MODE_S_REDIRECT] {
+            let result = source.photo(&ModeS::from(S!(mode_s))).await.unwrap();
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].image, "001/001/example.jpg");
+            assert_eq!(
+                result[0].photographer.as_deref(),
+                Some("Example Photographer")
+            );
+            assert_eq!(result[1].image, "001/002/example.jpg");
+            assert!(result[1].link.is_none());
+        }
+
+        for (mode_s, failure) in [
+            (MODE_S_MALFORMED, ScrapeFailure::Parse),
+            (MODE_S_ERROR, ScrapeFailure::Request),
+            ("393C99", ScrapeFailure::NotFound),
+        ] {
+            assert_eq!(
+                source
+                    .photo(&ModeS::from(S!(mode_s)))
+                    .await
+                    .map_err(|failed| failed.failure),
+                Err(failure),
+                "{mode_s}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    /// Takes SCRAPE_TIMEOUT, the flightroute and photo are scraped concurrently
+    async fn scraper_mock_upstream_timeout() {
+        let mock = MockUpstream::start().await;
+        let (flightroute, photo) = sources(&mock);
+        let sources = Sources {
+            flightroute: Arc::new([Arc::new(flightroute) as Arc<dyn FlightrouteSource>]),
+            photo: Arc::new([Arc::new(photo) as Arc<dyn PhotoSource>]),
+        };
+        let callsign = Callsign::validate(CALLSIGN_SLOW).unwrap();
+        let mode_s = ModeS::from(S!(MODE_S_SLOW));
+
+        let (flightroute, photo) = tokio::join!(
+            Scraper::scrape_flightroute(&sources, &callsign),
+            Scraper::scrape_photo(&sources, &mode_s)
+        );
+        let flightroute = flightroute.unwrap_err();
+        assert_eq!(flightroute.failure, ScrapeFailure::Timeout);
+        assert_eq!(
+            flightroute.reason,
+            "callsign_html: 127.0.0.1: request timeout"
+        );
+        let photo = photo.unwrap_err();
+        assert_eq!(photo.failure, ScrapeFailure::Timeout);
+        assert_eq!(photo.reason, "photo_json: 127.0.0.1: request timeout");
+    }
+}
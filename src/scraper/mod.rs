@@ -1,35 +1,46 @@
-use std::collections::HashMap;
-
-use reqwest::{Client, Response};
 use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::PgPool;
 use tokio::sync::{broadcast::Sender as BSender, oneshot};
 
-#[cfg(not(test))]
-use crate::api::UnknownAC;
-
 use crate::{
-    api::{AppError, Callsign, ModeS, Validate},
-    db_postgres::{ModelAircraft, ModelFlightroute},
+    api::{Callsign, ModeS},
+    db_postgres::{
+        ModelAircraft, ModelAirline, ModelFlightroute, ModelPendingFlightroute, ModelScrapeAttempt,
+    },
     parse_env::AppEnv,
 };
 
+mod attempt;
+mod backfill;
+mod extract;
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+pub mod mock_upstream;
+mod revalidate;
+mod source;
+mod status;
+mod upstream;
+pub use attempt::{FailedScrape, ScrapeFailure, ScrapeKind};
+pub use backfill::PhotoBackfill;
+pub use revalidate::Revalidator;
+pub use source::{FlightrouteProvider, PhotoProvider, Sources};
+use status::Metrics;
+pub use status::{ScraperShared, ScraperStatus};
+
 const SCRAPE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
-#[cfg(test)]
-use crate::sleep;
+/// Max number of photos stored per aircraft, the first is used as the aircraft's url_photo
+pub const MAX_PHOTOS: usize = 5;
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct PhotoData {
     #[serde(deserialize_with = "deserialize_url")]
     pub image: String,
-}
-
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
-struct PhotoResponse {
-    status: u16,
-    count: Option<u16>,
-    data: Option<[PhotoData; 1]>,
+    /// Page on the source site that the photo is displayed on
+    #[serde(default)]
+    pub link: Option<String>,
+    #[serde(default)]
+    pub photographer: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -37,9 +48,35 @@ pub struct ScrapedFlightroute {
     pub callsign_icao: Callsign,
     pub callsign_iata: Callsign,
     pub origin: String,
+    pub midpoint: Option<String>,
     pub destination: String,
 }
 
+impl ScrapedFlightroute {
+    /// The same airport is used more than once, e.g. origin and destination are the same
+    pub fn airport_clash(&self) -> bool {
+        self.origin == self.destination
+            || self
+                .midpoint
+                .as_ref()
+                .is_some_and(|i| i == &self.origin || i == &self.destination)
+    }
+
+    /// Every airport, in order, separated with a dash
+    fn airports(&self) -> String {
+        [
+            Some(&self.origin),
+            self.midpoint.as_ref(),
+            Some(&self.destination),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" - ")
+    }
+}
+
 fn deserialize_url<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
@@ -54,12 +91,10 @@ where
 
 #[derive(Debug)]
 pub struct Scraper {
-    callsign_requests: HashMap<Callsign, BSender<Option<ModelFlightroute>>>,
-    photo_requests: HashMap<ModeS, BSender<()>>,
-    flight_scrape_url: String,
+    shared: ScraperShared,
     allow_scrape_flightroute: Option<()>,
-    photo_url: String,
     allow_scrape_photo: Option<()>,
+    sources: Sources,
     postgres: PgPool,
     tx: async_channel::Sender<MsgScraper>,
 }
@@ -78,21 +113,17 @@ pub enum MsgScraper {
 }
 
 impl Scraper {
-    /// Remove item from the HashMap
-    fn msg_remove(&mut self, to_remove: ToRemove) {
+    /// Remove item from the in-flight searches
+    fn msg_remove(&self, to_remove: ToRemove) {
         match to_remove {
-            ToRemove::Callsign(callsign) => {
-                self.callsign_requests.remove(&callsign);
-            }
-            ToRemove::Photo(mode_s) => {
-                self.photo_requests.remove(&mode_s);
-            }
+            ToRemove::Callsign(callsign) => self.shared.callsign.remove(&callsign),
+            ToRemove::Photo(mode_s) => self.shared.photo.remove(&mode_s),
         }
     }
 
     /// Scrape for a flightroute, or if currently being scraper, wait for response
     fn msg_callsign(
-        &mut self,
+        &self,
         oneshot: oneshot::Sender<Option<ModelFlightroute>>,
         callsign: Callsign,
         tx: async_channel::Sender<MsgScraper>,
@@ -101,8 +132,7 @@ impl Scraper {
             oneshot.send(None).ok();
             return;
         }
-        if let Some(int_tx) = self.callsign_requests.get(&callsign) {
-            let mut int_rx = int_tx.subscribe();
+        if let Some(mut int_rx) = self.shared.callsign.subscribe(&callsign) {
             tokio::spawn(async move {
                 oneshot.send(int_rx.recv().await.unwrap_or(None)).ok();
                 tx.send(MsgScraper::Remove(ToRemove::Callsign(callsign)))
@@ -111,16 +141,14 @@ impl Scraper {
             });
         } else {
             let (int_tx, mut int_rx) = tokio::sync::broadcast::channel(128);
-            self.callsign_requests
+            self.shared
+                .callsign
                 .insert(callsign.clone(), int_tx.clone());
 
-            let data = (
-                self.postgres.clone(),
-                callsign,
-                self.flight_scrape_url.clone(),
-            );
+            let data = (self.postgres.clone(), callsign, self.sources.clone());
+            let metrics = self.shared.metrics.clone();
             tokio::spawn(async move {
-                Self::spawn_callsign(data.0, &data.1, data.2, int_tx).await;
+                Self::spawn_callsign(data.0, &data.1, data.2, &metrics, int_tx).await;
                 oneshot.send(int_rx.recv().await.unwrap_or(None)).ok();
                 tx.send(MsgScraper::Remove(ToRemove::Callsign(data.1)))
                     .await
@@ -131,7 +159,7 @@ impl Scraper {
 
     /// Scrape for a photo, or if currently being scraper, wait for response
     fn msg_photo(
-        &mut self,
+        &self,
         oneshot: oneshot::Sender<()>,
         mode_s: ModeS,
         tx: async_channel::Sender<MsgScraper>,
@@ -141,10 +169,9 @@ impl Scraper {
             return;
         }
 
-        if let Some(int_rx) = self.photo_requests.get(&mode_s) {
-            let mut int_tx = int_rx.subscribe();
+        if let Some(mut int_rx) = self.shared.photo.subscribe(&mode_s) {
             tokio::spawn(async move {
-                int_tx.recv().await.ok();
+                int_rx.recv().await.ok();
                 oneshot.send(()).ok();
                 tx.send(MsgScraper::Remove(ToRemove::Photo(mode_s)))
                     .await
@@ -153,10 +180,11 @@ impl Scraper {
         } else {
             // refactor with the scrpagmshCallsignas well
             let (int_tx, mut int_rx) = tokio::sync::broadcast::channel(128);
-            self.photo_requests.insert(mode_s.clone(), int_tx.clone());
-            let data = (self.postgres.clone(), mode_s, self.photo_url.clone());
+            self.shared.photo.insert(mode_s.clone(), int_tx.clone());
+            let data = (self.postgres.clone(), mode_s, self.sources.clone());
+            let metrics = self.shared.metrics.clone();
             tokio::spawn(async move {
-                Self::spawn_photo(data.0, &data.1, data.2, int_tx).await;
+                Self::spawn_photo(data.0, &data.1, data.2, &metrics, int_tx).await;
                 int_rx.recv().await.ok();
                 oneshot.send(()).ok();
                 tx.send(MsgScraper::Remove(ToRemove::Photo(data.1)))
@@ -180,237 +208,253 @@ impl Scraper {
         }
     }
 
-    /// Build a new scraper, and spawn in a tokio thread, return a Sender to be inserted into ApplicationState
-    pub fn start(app_env: &AppEnv, postgres: PgPool) -> async_channel::Sender<MsgScraper> {
+    /// Build a new scraper, and spawn in a tokio thread, return a Sender, and the state it shares, to be inserted into ApplicationState
+    pub fn start(
+        app_env: &AppEnv,
+        postgres: PgPool,
+        sources: Sources,
+    ) -> (async_channel::Sender<MsgScraper>, ScraperShared) {
         let (tx, rx) = async_channel::bounded(8192);
+        let shared = ScraperShared::default();
         let mut scraper = Self {
-            flight_scrape_url: app_env.url_callsign.clone(),
-            photo_url: app_env.url_aircraft_photo.clone(),
+            sources,
+            shared: shared.clone(),
             allow_scrape_flightroute: app_env.allow_scrape_flightroute,
             allow_scrape_photo: app_env.allow_scrape_photo,
-            callsign_requests: HashMap::new(),
-            photo_requests: HashMap::new(),
             postgres,
             tx: tx.clone(),
         };
         tokio::spawn(async move {
             scraper.listen(rx).await;
         });
-        tx
+        (tx, shared)
     }
 
-    /// Build a reqwest client, with a default timeout, and compression enabled
-    /// Then send a get request to the url given
-    async fn client_get(url: String) -> Result<Response, AppError> {
-        Ok(Client::builder()
-            .connect_timeout(SCRAPE_TIMEOUT)
-            .gzip(true)
-            .brotli(true)
-            .build()?
-            .get(url)
-            .send()
-            .await?)
+    /// Try each flightroute source in order, until one returns a valid flightroute.
+    /// Each upstream request times out after SCRAPE_TIMEOUT, not including time spent waiting for the upstream rate limit.
+    /// If every source fails, a failure with a scraped flightroute is preferred, so that it can be queued for review,
+    /// then a transient failure, so that the callsign will be retried sooner
+    async fn scrape_flightroute(
+        sources: &Sources,
+        callsign: &Callsign,
+    ) -> Result<ScrapedFlightroute, FailedScrape> {
+        let mut failed = FailedScrape::new(ScrapeFailure::NotFound, "no sources");
+        for source in sources.flightroute.iter() {
+            let name = source.name();
+            let failure = match source.flightroute(callsign).await {
+                Ok(scraped_flightroute) => {
+                    if !scraped_flightroute.airport_clash() {
+                        return Ok(scraped_flightroute);
+                    }
+                    tracing::error!(
+                        "{callsign}: {name}: airport clash: {}",
+                        scraped_flightroute.airports()
+                    );
+                    FailedScrape::new(
+                        ScrapeFailure::AirportClash,
+                        format!("{name}: {}", scraped_flightroute.origin),
+                    )
+                    .with_flightroute(&scraped_flightroute)
+                }
+                Err(failed) => failed.with_source(name),
+            };
+            if failed.flightroute.is_none()
+                && (failure.flightroute.is_some() || !failed.failure.is_transient())
+            {
+                failed = failure;
+            }
+        }
+        Err(failed)
     }
 
-    /// Try to extract the ICAO callsign, IATA callsign, and ICAO origin/destination airports
-    /// Return None is origin and destination are the same
-    fn extract_flightroute(html: &str) -> Option<ScrapedFlightroute> {
-        let title_callsigns = html
-            .split_once("<title>")
-            .unwrap_or_default()
-            .1
-            .split_once("</title>")
-            .unwrap_or_default()
-            .0
-            .split_once(')')
-            .unwrap_or_default()
-            .0
-            .replace('(', "");
-        let title_callsigns = title_callsigns.split_whitespace().collect::<Vec<_>>();
-
-        let icao_callsign =
-            Callsign::validate(title_callsigns.get(1).unwrap_or(&"")).map_or(None, |x| match x {
-                Callsign::Icao(_) => Some(x),
-                _ => None,
-            });
-
-        let iata_callsign =
-            Callsign::validate(title_callsigns.first().unwrap_or(&"")).map_or(None, |x| match x {
-                Callsign::Iata(_) => Some(x),
-                _ => None,
-            });
-
-        let origin = html
-            .split_once(r".setTargeting('origin', '")
-            .and_then(|i| i.1.split_once('\''))
-            .map(|i| i.0.to_owned());
-
-        let destination = html
-            .split_once(r".setTargeting('destination', '")
-            .and_then(|i| i.1.split_once('\''))
-            .map(|i| i.0.to_owned());
-
-        if let (Some(callsign_icao), Some(callsign_iata), Some(origin), Some(destination)) =
-            (icao_callsign, iata_callsign, origin, destination)
+    /// Insert a scraped flightroute, a missing airline or airport means that nothing is inserted
+    pub async fn insert_flightroute(
+        postgres: &PgPool,
+        scraped_flightroute: &ScrapedFlightroute,
+    ) -> Result<Result<ModelFlightroute, FailedScrape>, crate::api::AppError> {
+        if let Some(flightroute) =
+            ModelFlightroute::insert_scraped_flightroute(postgres, scraped_flightroute).await?
         {
-            Some(ScrapedFlightroute {
-                callsign_icao,
-                callsign_iata,
-                origin,
-                destination,
-            })
-        } else {
-            None
+            return Ok(Ok(flightroute));
         }
+        let callsign = &scraped_flightroute.callsign_icao;
+        Ok(Err(
+            if ModelAirline::get_by_icao_callsign(postgres, callsign)
+                .await?
+                .is_none()
+            {
+                FailedScrape::new(ScrapeFailure::UnknownAirline, callsign.to_string())
+            } else {
+                FailedScrape::new(
+                    ScrapeFailure::UnknownAirport,
+                    scraped_flightroute.airports(),
+                )
+            }
+            .with_flightroute(scraped_flightroute),
+        ))
     }
 
-    /// As above, but just return the test_scrape, instead of hitting a third party site
-    #[cfg(test)]
-    async fn request_callsign(callsign: &Callsign, _url: String) -> Result<String, AppError> {
-        use crate::S;
-        sleep!(500);
-        if callsign.to_string() == "ANA460" {
-            Ok(include_str!("./test_scrape.txt").to_owned())
-        } else {
-            Ok(S!())
+    /// Get the previous attempt, None means the search shouldn't be scraped yet
+    async fn previous_attempt(
+        postgres: &PgPool,
+        scrape_kind: ScrapeKind,
+        search: &str,
+    ) -> Option<Option<ModelScrapeAttempt>> {
+        match ModelScrapeAttempt::get(postgres, scrape_kind, search).await {
+            Ok(Some(attempt)) if !attempt.eligible() => {
+                tracing::debug!("{search}: {}: backing off", attempt.outcome);
+                None
+            }
+            Ok(attempt) => Some(attempt),
+            Err(e) => {
+                tracing::error!("{e}");
+                Some(None)
+            }
         }
     }
 
-    #[cfg(not(test))]
-    #[allow(clippy::cognitive_complexity)]
-    async fn request_callsign(callsign: &Callsign, url: String) -> Result<String, AppError> {
-        match Self::client_get(format!("{url}/{callsign}")).await {
-            Ok(response) => match response.text().await {
-                Ok(text) => Ok(text),
-                Err(e) => {
-                    tracing::error!("{e:?}");
-                    tracing::error!("can't transform callsign into text");
-                    Err(AppError::UnknownInDb(UnknownAC::Callsign))
-                }
-            },
-            Err(e) => {
-                tracing::error!("{e:?}");
-                tracing::error!("can't scrape callsign address");
-                Err(AppError::UnknownInDb(UnknownAC::Callsign))
-            }
+    /// Record the outcome of a scrape, errors are only logged, as the scrape itself is complete
+    async fn record_attempt(
+        postgres: &PgPool,
+        scrape_kind: ScrapeKind,
+        search: &str,
+        previous: Option<&ModelScrapeAttempt>,
+        result: Result<(), &FailedScrape>,
+    ) {
+        if let Err(failed) = result {
+            tracing::info!("{search}: {}: {}", failed.failure, failed.reason);
+        }
+        if let Err(e) =
+            ModelScrapeAttempt::insert(postgres, scrape_kind, search, previous, result).await
+        {
+            tracing::error!("{e}");
+        }
+    }
+
+    /// Queue a scraped flightroute that couldn't be inserted, to be reviewed, or remove it from the queue once it has been inserted
+    async fn update_pending(
+        postgres: &PgPool,
+        callsign: &Callsign,
+        result: &Result<ModelFlightroute, FailedScrape>,
+    ) {
+        let updated = match result {
+            Ok(_) => ModelPendingFlightroute::delete_callsign(postgres, callsign).await,
+            Err(FailedScrape {
+                failure,
+                flightroute: Some(scraped_flightroute),
+                ..
+            }) => ModelPendingFlightroute::insert(postgres, scraped_flightroute, *failure).await,
+            Err(_) => Ok(()),
+        };
+        if let Err(e) = updated {
+            tracing::error!("{e}");
         }
     }
 
     /// This is spawned in a tokio thread, scrapes the flightroute, inserts into postgres, and sends back modelflightroute
-    #[allow(clippy::cognitive_complexity)]
     async fn spawn_callsign(
         postgres: PgPool,
         callsign: &Callsign,
-        url: String,
+        sources: Sources,
+        metrics: &Metrics,
         b_sender: BSender<Option<ModelFlightroute>>,
     ) {
-        let return_none = || {
-            b_sender.send(None).ok();
-        };
-
-        let Ok(html) =
-            tokio::time::timeout(SCRAPE_TIMEOUT, Self::request_callsign(callsign, url)).await
+        let search = callsign.to_string();
+        let Some(previous) = Self::previous_attempt(&postgres, ScrapeKind::Callsign, &search).await
         else {
-            tracing::error!("{callsign}: scrape timeout");
-            return return_none();
-        };
-        let Ok(html) = html else {
-            tracing::error!("{callsign}: request error");
-            return return_none();
-        };
-        let Some(scraped_flightroute) = Self::extract_flightroute(&html) else {
-            return return_none();
+            b_sender.send(None).ok();
+            return;
         };
 
-        if scraped_flightroute.origin == scraped_flightroute.destination {
-            tracing::error!(
-                "{callsign}: airport clash: origin: {o}, destination: {d}",
-                o = scraped_flightroute.origin,
-                d = scraped_flightroute.destination
-            );
-            return_none();
-        } else {
-            match ModelFlightroute::insert_scraped_flightroute(&postgres, &scraped_flightroute)
-                .await
-            {
-                Ok(flightroute) => {
-                    b_sender.send(flightroute).ok();
-                }
-                Err(e) => {
-                    tracing::error!("{}", e);
-                    return_none();
+        let started = std::time::Instant::now();
+        let scraped = Self::scrape_flightroute(&sources, callsign).await;
+        metrics.record(
+            ScrapeKind::Callsign,
+            &search,
+            started.elapsed(),
+            scraped.as_ref().map(|_| ()),
+        );
+        let result = match scraped {
+            Ok(scraped_flightroute) => {
+                match Self::insert_flightroute(&postgres, &scraped_flightroute).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        tracing::error!("{e}");
+                        b_sender.send(None).ok();
+                        return;
+                    }
                 }
             }
-        }
-    }
-
-    /// This is spawned in a tokio thread, scrapes the flightroute, inserts into postgres, and sends back just a unit
-    async fn spawn_photo(postgres: PgPool, mode_s: &ModeS, url: String, b_sender: BSender<()>) {
-        let send_unit = || {
-            b_sender.send(()).ok();
+            Err(failed) => Err(failed),
         };
-        let Ok(photo) =
-            tokio::time::timeout(SCRAPE_TIMEOUT, Self::request_photo(mode_s, url)).await
-        else {
-            tracing::error!("{}: scrape timeout", mode_s);
-            return send_unit();
-        };
-
-        let Some(photo) = photo else {
-            return send_unit();
-        };
-        if let Some([data_0, ..]) = photo.data
-            && let Err(e) = ModelAircraft::insert_photo(&postgres, data_0, mode_s).await
-        {
-            tracing::error!("{e}");
-        }
-        send_unit();
+        Self::update_pending(&postgres, callsign, &result).await;
+
+        Self::record_attempt(
+            &postgres,
+            ScrapeKind::Callsign,
+            &search,
+            previous.as_ref(),
+            result.as_ref().map(|_| ()),
+        )
+        .await;
+        b_sender.send(result.ok()).ok();
     }
 
-    /// Scrape photo for testings
-    /// don't throw error as an internal process, but need to improve logging
-    #[cfg(test)]
-    async fn request_photo(mode_s: &ModeS, _url: String) -> Option<PhotoResponse> {
-        use crate::S;
-
-        sleep!(500);
-        match mode_s.to_string().as_str() {
-            "393C00" => Some(PhotoResponse {
-                status: 200,
-                count: Some(1),
-                data: Some([PhotoData {
-                    image: S!("001/001/example.jpg"),
-                }]),
-            }),
-            _ => None,
+    /// Try each photo source in order, until one returns at least one photo.
+    /// Each upstream request times out after SCRAPE_TIMEOUT, not including time spent waiting for the upstream rate limit
+    async fn scrape_photo(
+        sources: &Sources,
+        mode_s: &ModeS,
+    ) -> Result<Vec<PhotoData>, FailedScrape> {
+        let mut failed = FailedScrape::new(ScrapeFailure::NotFound, "no sources");
+        for source in sources.photo.iter() {
+            let name = source.name();
+            let failure = match source.photo(mode_s).await {
+                Ok(photo) => return Ok(photo),
+                Err(failed) => failed.with_source(name),
+            };
+            if !failed.failure.is_transient() {
+                failed = failure;
+            }
         }
+        Err(failed)
     }
 
-    /// Request for photo from third party site
-    #[cfg(not(test))]
-    #[allow(clippy::cognitive_complexity)]
-    async fn request_photo(mode_s: &ModeS, url: String) -> Option<PhotoResponse> {
-        match Self::client_get(format!("{url}ac_thumb.json?m={mode_s}&n=1")).await {
-            Ok(response) => match response.json::<PhotoResponse>().await {
-                Ok(photo) => {
-                    if photo.data.is_some() {
-                        Some(photo)
-                    } else {
-                        None
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("{e:?}");
-                    tracing::error!("can't transform photo into json");
-                    None
-                }
-            },
-            Err(e) => {
-                tracing::error!("{e:?}");
-                tracing::error!("can't scrape photo address");
-                None
+    /// This is spawned in a tokio thread, scrapes the photo, inserts into postgres, and sends back just a unit
+    async fn spawn_photo(
+        postgres: PgPool,
+        mode_s: &ModeS,
+        sources: Sources,
+        metrics: &Metrics,
+        b_sender: BSender<()>,
+    ) {
+        let search = mode_s.to_string();
+        if let Some(previous) = Self::previous_attempt(&postgres, ScrapeKind::Photo, &search).await
+        {
+            let started = std::time::Instant::now();
+            let result = Self::scrape_photo(&sources, mode_s).await;
+            metrics.record(
+                ScrapeKind::Photo,
+                &search,
+                started.elapsed(),
+                result.as_ref().map(|_| ()),
+            );
+            if let Ok(photos) = result.as_ref()
+                && let Err(e) = ModelAircraft::insert_photo(&postgres, photos, mode_s).await
+            {
+                tracing::error!("{e}");
+            } else {
+                Self::record_attempt(
+                    &postgres,
+                    ScrapeKind::Photo,
+                    &search,
+                    previous.as_ref(),
+                    result.as_ref().map(|_| ()),
+                )
+                .await;
             }
         }
+        b_sender.send(()).ok();
     }
 }
 
@@ -423,14 +467,16 @@ pub mod tests {
     use super::*;
     use crate::api::tests::delete_incoming_request;
     use crate::api::{AircraftSearch, ModeS, Validate};
+    use crate::scraper::source::FlightrouteSource;
     use crate::{S, db_postgres, db_redis};
     use fred::interfaces::ClientLike;
     use serde::de::IntoDeserializer;
     use serde::de::value::{Error as ValueError, StringDeserializer};
+    use std::sync::Arc;
 
     pub const TEST_CALLSIGN: &str = "ANA460";
-    const TEST_ORIGIN: &str = "ROAH";
-    const TEST_DESTINATION: &str = "RJTT";
+    pub const TEST_ORIGIN: &str = "ROAH";
+    pub const TEST_DESTINATION: &str = "RJTT";
 
     async fn test_setup() -> (AppEnv, PgPool) {
         let app_env = AppEnv::get_env();
@@ -442,28 +488,44 @@ pub mod tests {
         let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
         delete_incoming_request(db).await;
         if let Some(flightroute) = ModelFlightroute::get(db, &callsign).await {
-            sqlx::query!(
-                "DELETE FROM flightroute WHERE flightroute_id = $1",
-                flightroute.flightroute_id.get()
-            )
-            .execute(db)
-            .await
-            .unwrap();
+            sqlx::query("DELETE FROM flightroute WHERE flightroute_id = $1")
+                .bind(flightroute.flightroute_id.get())
+                .execute(db)
+                .await
+                .unwrap();
         }
+        let example_photos = ["001/001/example.jpg", "001/002/example.jpg"];
         let query = r#"
-        UPDATE aircraft SET aircraft_photo_id = NULL WHERE aircraft_photo_id = (
+        UPDATE aircraft SET aircraft_photo_id = NULL WHERE aircraft_photo_id IN (
             SELECT
                 ap.aircraft_photo_id
             FROM
                 aircraft_photo ap
             WHERE
-                ap.url_photo = '001/001/example.jpg'
+                ap.url_photo = ANY($1)
+        )"#;
+        sqlx::query(query)
+            .bind(example_photos)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM scrape_attempt WHERE search = ANY($1)")
+            .bind([TEST_CALLSIGN, "393C00"])
+            .execute(db)
+            .await
+            .unwrap();
+        let query = r#"
+        DELETE FROM aircraft_gallery WHERE aircraft_photo_id IN (
+            SELECT aircraft_photo_id FROM aircraft_photo WHERE url_photo = ANY($1)
         )"#;
-
-        sqlx::query(query).execute(db).await.unwrap();
-        let query = r#"DELETE FROM aircraft_photo WHERE url_photo = $1"#;
         sqlx::query(query)
-            .bind("001/001/example.jpg")
+            .bind(example_photos)
+            .execute(db)
+            .await
+            .unwrap();
+        let query = r#"DELETE FROM aircraft_photo WHERE url_photo = ANY($1)"#;
+        sqlx::query(query)
+            .bind(example_photos)
             .execute(db)
             .await
             .unwrap();
@@ -473,6 +535,113 @@ pub mod tests {
         redis.flushall::<()>(true).await.unwrap();
     }
 
+    #[test]
+    fn scraper_scraped_flightroute_airports() {
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!(TEST_DESTINATION),
+        };
+        assert!(!scraped_flightroute.airport_clash());
+        assert_eq!(scraped_flightroute.airports(), "ROAH - RJTT");
+
+        let scraped_flightroute = ScrapedFlightroute {
+            midpoint: Some(S!("RJFF")),
+            ..scraped_flightroute
+        };
+        assert!(!scraped_flightroute.airport_clash());
+        assert_eq!(scraped_flightroute.airports(), "ROAH - RJFF - RJTT");
+
+        let clash = ScrapedFlightroute {
+            midpoint: Some(S!(TEST_DESTINATION)),
+            ..scraped_flightroute.clone()
+        };
+        assert!(clash.airport_clash());
+
+        let clash = ScrapedFlightroute {
+            destination: S!(TEST_ORIGIN),
+            midpoint: None,
+            ..scraped_flightroute
+        };
+        assert!(clash.airport_clash());
+    }
+
+    /// Flightroute source that always returns the same result
+    #[derive(Debug)]
+    struct FixedSource(&'static str, Result<ScrapedFlightroute, FailedScrape>);
+
+    impl FlightrouteSource for FixedSource {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn flightroute<'a>(
+            &'a self,
+            _: &'a Callsign,
+        ) -> source::SourceFuture<'a, Result<ScrapedFlightroute, FailedScrape>> {
+            Box::pin(async move { self.1.clone() })
+        }
+    }
+
+    fn fixed_sources(flightroute: Vec<FixedSource>) -> Sources {
+        Sources {
+            flightroute: flightroute
+                .into_iter()
+                .map(|i| Arc::new(i) as Arc<dyn FlightrouteSource>)
+                .collect(),
+            photo: Arc::new([]),
+        }
+    }
+
+    #[tokio::test]
+    /// A clashing flightroute is kept for review, even when a later source fails
+    async fn scraper_flightroute_failure_precedence() {
+        let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
+        let clash = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!(TEST_ORIGIN),
+        };
+        let not_found = || FailedScrape::new(ScrapeFailure::NotFound, "not found");
+        let timeout = || FailedScrape::new(ScrapeFailure::Timeout, "request timeout");
+
+        for sources in [
+            vec![
+                FixedSource("first", Ok(clash.clone())),
+                FixedSource("second", Err(not_found())),
+            ],
+            vec![
+                FixedSource("first", Ok(clash.clone())),
+                FixedSource("second", Err(timeout())),
+            ],
+            vec![
+                FixedSource("first", Err(timeout())),
+                FixedSource("second", Ok(clash.clone())),
+            ],
+        ] {
+            let result = Scraper::scrape_flightroute(&fixed_sources(sources), &callsign)
+                .await
+                .unwrap_err();
+            assert_eq!(result.failure, ScrapeFailure::AirportClash);
+            assert_eq!(result.flightroute.as_ref(), Some(&clash));
+        }
+
+        // Without a flightroute, a transient failure is still preferred
+        let sources = vec![
+            FixedSource("first", Err(timeout())),
+            FixedSource("second", Err(not_found())),
+        ];
+        let result = Scraper::scrape_flightroute(&fixed_sources(sources), &callsign)
+            .await
+            .unwrap_err();
+        assert_eq!(result.failure, ScrapeFailure::Timeout);
+        assert_eq!(result.reason, "first: request timeout");
+    }
+
     #[test]
     fn scraper_deserialize_url() {
         let prefix = "https://www.xxxxxxxxxxxx.xxx/xxxxxx/xxxxxxxx/xxxxxxxxxxx";
@@ -492,58 +661,14 @@ pub mod tests {
         assert!(result.is_err());
     }
 
-    #[test]
-    fn scraper_extract_flightroute() {
-        let html_string = include_str!("./test_scrape.txt");
-        let result = Scraper::extract_flightroute(html_string);
-
-        let expected = ScrapedFlightroute {
-            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
-            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
-            origin: TEST_ORIGIN.to_owned(),
-            destination: TEST_DESTINATION.to_owned(),
-        };
-
-        assert!(result.is_some());
-        assert_eq!(result.unwrap(), expected);
-    }
-
     #[tokio::test]
-    #[allow(unreachable_code)]
-    // WARNING - this will test against a live, third party, website
-    async fn scraper_extract_flightroute_live() {
-        unimplemented!("`scraper_extract_flightroute_live` test currently disabled");
-
-        let setup = test_setup().await;
-
-        let url = format!("{}/{TEST_CALLSIGN}", setup.0.url_callsign);
-        let html = Scraper::client_get(url)
-            .await
-            .unwrap()
-            .text()
-            .await
-            .unwrap();
-
-        let result = Scraper::extract_flightroute(&html);
-        let expected = ScrapedFlightroute {
-            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
-            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
-            origin: S!(TEST_ORIGIN),
-            destination: S!(TEST_DESTINATION),
-        };
-
-        assert!(result.is_some());
-        assert_eq!(result.unwrap(), expected);
-        remove_scraped_data(&setup.1).await;
-    }
-
-    #[tokio::test]
-    /// in test mode, live site is actually just include_str()
+    /// Scraped from the mock upstream
     async fn scraper_scrape_for_route_insert() {
         let callsign = Callsign::validate(TEST_CALLSIGN).unwrap();
-        let setup = test_setup().await;
+        let mut setup = test_setup().await;
         remove_scraped_data(&setup.1).await;
-        let sender = Scraper::start(&setup.0, setup.1.clone());
+        let sources = Sources::mock(&mut setup.0).await;
+        let (sender, _) = Scraper::start(&setup.0, setup.1.clone(), sources);
         let (s, r) = oneshot::channel();
         sender.send(MsgScraper::CallSign((s, callsign))).await.ok();
 
@@ -604,7 +729,8 @@ pub mod tests {
         let mut setup = test_setup().await;
         remove_scraped_data(&setup.1).await;
         setup.0.allow_scrape_flightroute = None;
-        let sender = Scraper::start(&setup.0, setup.1.clone());
+        let sources = Sources::mock(&mut setup.0).await;
+        let (sender, _) = Scraper::start(&setup.0, setup.1.clone(), sources);
 
         let (s, r) = oneshot::channel();
         sender.send(MsgScraper::CallSign((s, callsign))).await.ok();
@@ -617,8 +743,9 @@ pub mod tests {
 
     #[tokio::test]
     async fn scraper_get_photo() {
-        let setup = test_setup().await;
-        let sender = Scraper::start(&setup.0, setup.1.clone());
+        let mut setup = test_setup().await;
+        let sources = Sources::mock(&mut setup.0).await;
+        let (sender, _) = Scraper::start(&setup.0, setup.1.clone(), sources);
 
         let mode_s = ModeS::from(S!("393C00"));
 
@@ -660,6 +787,25 @@ pub mod tests {
                 .unwrap()
                 .ends_with("/thumbnails/001/001/example.jpg")
         );
+
+        let photos = result.photos.0;
+        assert_eq!(photos.len(), 2);
+        assert!(photos[0].url_photo.ends_with("/001/001/example.jpg"));
+        assert_eq!(
+            photos[0].photographer.as_deref(),
+            Some("Example Photographer")
+        );
+        assert_eq!(
+            photos[0].url_page.as_deref(),
+            Some("https://www.example.com/aircraft/photo/001001.html")
+        );
+        assert!(photos[1].url_photo.ends_with("/001/002/example.jpg"));
+        assert!(
+            photos[1]
+                .url_photo_thumbnail
+                .ends_with("/thumbnails/001/002/example.jpg")
+        );
+        assert!(photos[1].photographer.is_none());
         remove_scraped_data(&setup.1).await;
     }
 
@@ -667,7 +813,8 @@ pub mod tests {
     async fn scraper_get_photo_null() {
         let mut setup = test_setup().await;
         setup.0.allow_scrape_photo = None;
-        let sender = Scraper::start(&setup.0, setup.1.clone());
+        let sources = Sources::mock(&mut setup.0).await;
+        let (sender, _) = Scraper::start(&setup.0, setup.1.clone(), sources);
 
         let mode_s = ModeS::from(S!("393C00"));
 
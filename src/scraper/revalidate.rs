@@ -0,0 +1,372 @@
+use std::time::Duration;
+
+use fred::clients::Pool;
+use sqlx::PgPool;
+
+use crate::{
+    api::{AppError, Callsign, Validate},
+    db_postgres::{ModelAirport, ModelFlightroute, ModelScrapeAttempt},
+    db_redis::delete_flightroute_cache,
+    parse_env::AppEnv,
+};
+
+use super::{ScrapeKind, Scraper, Sources};
+
+/// Wait between each re-scrape, so that re-validation doesn't take upstream capacity away from unknown callsigns
+const REVALIDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wait when there aren't any stale flightroutes
+const REVALIDATE_IDLE: Duration = Duration::from_secs(60 * 60);
+
+/// Result of re-scraping a single stale flightroute
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Revalidated {
+    /// Scraped airports match the stored airports
+    Unchanged,
+    /// Airports have been updated, and the cached flightroute removed
+    Updated,
+    /// Scraped flightroute can't be used, e.g. not found upstream, or an unknown airport, the stored airports are kept
+    Skipped,
+    /// Transient failure, the attempt is recorded, and the flightroute will be stale again once the failure's backoff has passed
+    Retry,
+}
+
+/// Background job to re-scrape flightroutes that were scraped over scrape_revalidate_days ago, oldest first, one every REVALIDATE_INTERVAL
+/// Flightroutes without a validated_at, i.e. seeded, admin corrected, or scraped before validated_at was added, are never re-scraped
+#[derive(Debug)]
+pub struct Revalidator {
+    max_age_days: u16,
+    postgres: PgPool,
+    redis: Pool,
+    sources: Sources,
+}
+
+impl Revalidator {
+    /// Spawn the revalidator in a tokio thread, only if flightroute scraping, and SCRAPE_REVALIDATE_DAYS, are both enabled
+    pub fn start(app_env: &AppEnv, postgres: PgPool, redis: Pool, sources: Sources) {
+        let (Some(()), Some(max_age_days)) = (
+            app_env.allow_scrape_flightroute,
+            app_env.scrape_revalidate_days,
+        ) else {
+            return;
+        };
+        let revalidator = Self {
+            max_age_days,
+            postgres,
+            redis,
+            sources,
+        };
+        tokio::spawn(async move {
+            revalidator.run().await;
+        });
+    }
+
+    async fn run(&self) {
+        loop {
+            let wait = match self.revalidate_next().await {
+                Ok(true) => REVALIDATE_INTERVAL,
+                Ok(false) => REVALIDATE_IDLE,
+                Err(e) => {
+                    tracing::error!("{e}");
+                    REVALIDATE_INTERVAL
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Re-scrape the least recently validated flightroute, false if there aren't any stale flightroutes
+    async fn revalidate_next(&self) -> Result<bool, AppError> {
+        let Some(flightroute) =
+            ModelFlightroute::get_stale(&self.postgres, self.max_age_days).await?
+        else {
+            return Ok(false);
+        };
+        self.revalidate(&flightroute).await?;
+        Ok(true)
+    }
+
+    /// Re-scrape a flightroute, and apply any airport changes in the same way as the callsign PATCH route
+    async fn revalidate(&self, flightroute: &ModelFlightroute) -> Result<Revalidated, AppError> {
+        let Ok(callsign) = Callsign::validate(&flightroute.callsign) else {
+            tracing::info!("{}: revalidate: invalid callsign", flightroute.callsign);
+            flightroute.validated(&self.postgres).await?;
+            return Ok(Revalidated::Skipped);
+        };
+
+        let scraped_flightroute = match Scraper::scrape_flightroute(&self.sources, &callsign).await
+        {
+            Ok(scraped_flightroute) => scraped_flightroute,
+            Err(failed) => {
+                tracing::info!(
+                    "{callsign}: revalidate: {}: {}",
+                    failed.failure,
+                    failed.reason
+                );
+                if failed.failure.is_transient() {
+                    let search = callsign.to_string();
+                    let previous =
+                        ModelScrapeAttempt::get(&self.postgres, ScrapeKind::Callsign, &search)
+                            .await?;
+                    let attempts = previous
+                        .as_ref()
+                        .map_or(0, |i| i.attempts)
+                        .saturating_add(1);
+                    Scraper::record_attempt(
+                        &self.postgres,
+                        ScrapeKind::Callsign,
+                        &search,
+                        previous.as_ref(),
+                        Err(&failed),
+                    )
+                    .await;
+                    flightroute
+                        .stale_in(
+                            &self.postgres,
+                            self.max_age_days,
+                            failed.failure.backoff(attempts),
+                        )
+                        .await?;
+                    return Ok(Revalidated::Retry);
+                }
+                flightroute.validated(&self.postgres).await?;
+                return Ok(Revalidated::Skipped);
+            }
+        };
+
+        if scraped_flightroute.origin == flightroute.origin_airport_icao_code
+            && scraped_flightroute.midpoint == flightroute.midpoint_airport_icao_code
+            && scraped_flightroute.destination == flightroute.destination_airport_icao_code
+        {
+            tracing::debug!("{callsign}: revalidate: unchanged");
+            flightroute.validated(&self.postgres).await?;
+            return Ok(Revalidated::Unchanged);
+        }
+
+        let new_midpoint = match scraped_flightroute.midpoint.as_ref() {
+            Some(midpoint) => ModelAirport::get(&self.postgres, midpoint).await?.map(Some),
+            None => Some(None),
+        };
+        let (Some(new_origin), Some(new_midpoint), Some(new_destination)) = (
+            ModelAirport::get(&self.postgres, &scraped_flightroute.origin).await?,
+            new_midpoint,
+            ModelAirport::get(&self.postgres, &scraped_flightroute.destination).await?,
+        ) else {
+            tracing::info!(
+                "{callsign}: revalidate: unknown airport: {}",
+                scraped_flightroute.airports()
+            );
+            flightroute.validated(&self.postgres).await?;
+            return Ok(Revalidated::Skipped);
+        };
+
+        flightroute
+            .update(&self.postgres, new_origin, new_midpoint, new_destination)
+            .await?;
+        flightroute.validated(&self.postgres).await?;
+        delete_flightroute_cache(&self.redis, flightroute).await?;
+        tracing::info!(
+            "{callsign}: revalidate: {} -> {}",
+            [
+                Some(&flightroute.origin_airport_icao_code),
+                flightroute.midpoint_airport_icao_code.as_ref(),
+                Some(&flightroute.destination_airport_icao_code)
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" - "),
+            scraped_flightroute.airports()
+        );
+        Ok(Revalidated::Updated)
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_revalidate '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{
+        S, db_postgres,
+        db_redis::{self, RedisKey, insert_cache},
+        scraper::{
+            ScrapedFlightroute,
+            source::{CallsignHtml, FlightrouteSource},
+            tests::{TEST_CALLSIGN, TEST_DESTINATION, TEST_ORIGIN, remove_scraped_data},
+            upstream::Upstream,
+        },
+    };
+    use fred::interfaces::KeysInterface;
+
+    async fn setup() -> Revalidator {
+        let mut app_env = AppEnv::get_env();
+        let postgres = db_postgres::get_pool(&app_env).await.unwrap();
+        let redis = db_redis::get_pool(&app_env).await.unwrap();
+        remove_scraped_data(&postgres).await;
+        Revalidator {
+            max_age_days: 1,
+            postgres,
+            redis,
+            sources: Sources::mock(&mut app_env).await,
+        }
+    }
+
+    /// Insert ANA460 with the given destination, validated long enough ago to be stale
+    async fn insert_stale(revalidator: &Revalidator, destination: &str) -> ModelFlightroute {
+        let scraped_flightroute = ScrapedFlightroute {
+            callsign_icao: Callsign::validate(TEST_CALLSIGN).unwrap(),
+            callsign_iata: Callsign::validate("NH460").unwrap(),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!(destination),
+        };
+        let flightroute = ModelFlightroute::insert_scraped_flightroute(
+            &revalidator.postgres,
+            &scraped_flightroute,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        sqlx::query("UPDATE flightroute SET validated_at = '2000-01-01' WHERE flightroute_id = $1")
+            .bind(flightroute.flightroute_id.get())
+            .execute(&revalidator.postgres)
+            .await
+            .unwrap();
+        flightroute
+    }
+
+    #[tokio::test]
+    async fn scraper_revalidate_unchanged() {
+        let revalidator = setup().await;
+        let flightroute = insert_stale(&revalidator, TEST_DESTINATION).await;
+
+        let result = revalidator.revalidate(&flightroute).await.unwrap();
+        assert_eq!(result, Revalidated::Unchanged);
+
+        let result = ModelFlightroute::get_stale(&revalidator.postgres, 1)
+            .await
+            .unwrap();
+        assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+
+        remove_scraped_data(&revalidator.postgres).await;
+    }
+
+    #[tokio::test]
+    async fn scraper_revalidate_updated() {
+        let revalidator = setup().await;
+        let flightroute = insert_stale(&revalidator, "RJAA").await;
+        assert_eq!(flightroute.destination_airport_icao_code, "RJAA");
+        let callsigns = [TEST_CALLSIGN, "NH460"].map(|i| Callsign::validate(i).unwrap());
+        for callsign in &callsigns {
+            insert_cache(
+                &revalidator.redis,
+                Some(&flightroute),
+                RedisKey::Callsign(callsign),
+            )
+            .await
+            .unwrap();
+        }
+
+        let result = revalidator.revalidate(&flightroute).await.unwrap();
+        assert_eq!(result, Revalidated::Updated);
+        for callsign in &callsigns {
+            let result: bool = revalidator
+                .redis
+                .exists(RedisKey::Callsign(callsign).to_string())
+                .await
+                .unwrap();
+            assert!(!result);
+        }
+
+        let result = ModelFlightroute::get(
+            &revalidator.postgres,
+            &Callsign::validate(TEST_CALLSIGN).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.flightroute_id, flightroute.flightroute_id);
+        assert_eq!(result.origin_airport_icao_code, TEST_ORIGIN);
+        assert_eq!(result.destination_airport_icao_code, TEST_DESTINATION);
+
+        let result = ModelFlightroute::get_stale(&revalidator.postgres, 1)
+            .await
+            .unwrap();
+        assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+
+        remove_scraped_data(&revalidator.postgres).await;
+    }
+
+    #[tokio::test]
+    async fn scraper_revalidate_not_found() {
+        let mut revalidator = setup().await;
+        let flightroute = insert_stale(&revalidator, "RJAA").await;
+        revalidator.sources = Sources {
+            flightroute: std::sync::Arc::new([]),
+            photo: std::sync::Arc::new([]),
+        };
+
+        // Without any sources, the scrape is not found, so the stored airports are kept
+        let result = revalidator.revalidate(&flightroute).await.unwrap();
+        assert_eq!(result, Revalidated::Skipped);
+
+        let result = ModelFlightroute::get(
+            &revalidator.postgres,
+            &Callsign::validate(TEST_CALLSIGN).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.destination_airport_icao_code, "RJAA");
+
+        remove_scraped_data(&revalidator.postgres).await;
+    }
+
+    #[tokio::test]
+    /// A transient failure backs off, rather than being re-scraped every REVALIDATE_INTERVAL
+    async fn scraper_revalidate_retry() {
+        let mut revalidator = setup().await;
+        let flightroute = insert_stale(&revalidator, "RJAA").await;
+        revalidator.sources = Sources {
+            flightroute: std::sync::Arc::new([std::sync::Arc::new(CallsignHtml::new(
+                &Upstream::new().unwrap(),
+                "not a url",
+            ))
+                as std::sync::Arc<dyn FlightrouteSource>]),
+            photo: std::sync::Arc::new([]),
+        };
+        let stale_in = || async {
+            sqlx::query_scalar::<_, i64>("SELECT CEIL(EXTRACT(EPOCH FROM validated_at - (CURRENT_TIMESTAMP - interval '1 day')))::BIGINT FROM flightroute WHERE flightroute_id = $1")
+                .bind(flightroute.flightroute_id.get())
+                .fetch_one(&revalidator.postgres)
+                .await
+                .unwrap()
+        };
+
+        for (attempts, backoff) in [(1, 60), (2, 120)] {
+            let result = revalidator.revalidate(&flightroute).await.unwrap();
+            assert_eq!(result, Revalidated::Retry);
+
+            let result = ModelFlightroute::get_stale(&revalidator.postgres, 1)
+                .await
+                .unwrap();
+            assert!(result.is_none_or(|i| i.flightroute_id != flightroute.flightroute_id));
+            assert!((backoff - 1..=backoff).contains(&stale_in().await));
+
+            let attempt =
+                ModelScrapeAttempt::get(&revalidator.postgres, ScrapeKind::Callsign, TEST_CALLSIGN)
+                    .await
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(attempt.attempts, attempts);
+            assert_eq!(
+                attempt.reason.as_deref(),
+                Some("callsign_html: invalid url")
+            );
+        }
+
+        remove_scraped_data(&revalidator.postgres).await;
+    }
+}
@@ -0,0 +1,346 @@
+use std::{fmt, future::Future, pin::Pin, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{Callsign, ModeS},
+    parse_env::AppEnv,
+};
+
+use super::{
+    FailedScrape, MAX_PHOTOS, PhotoData, ScrapeFailure, ScrapedFlightroute, extract,
+    upstream::Upstream,
+};
+
+/// Boxed future, so that the source traits can be used as trait objects
+pub type SourceFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Somewhere a flightroute can be found for a callsign, e.g. a third party website, or an internal database
+pub trait FlightrouteSource: fmt::Debug + Send + Sync {
+    /// Used in logs
+    fn name(&self) -> &'static str;
+
+    /// Errors should be logged by the source, the failure, and its reason, is recorded against the callsign
+    fn flightroute<'a>(
+        &'a self,
+        callsign: &'a Callsign,
+    ) -> SourceFuture<'a, Result<ScrapedFlightroute, FailedScrape>>;
+}
+
+/// Somewhere a photo can be found for an aircraft
+pub trait PhotoSource: fmt::Debug + Send + Sync {
+    /// Used in logs
+    fn name(&self) -> &'static str;
+
+    /// Errors should be logged by the source, the failure, and its reason, is recorded against the mode_s
+    fn photo<'a>(
+        &'a self,
+        mode_s: &'a ModeS,
+    ) -> SourceFuture<'a, Result<Vec<PhotoData>, FailedScrape>>;
+}
+
+/// Flightroute providers that can be selected, in order of priority, with the SCRAPE_FLIGHTROUTE_SOURCES env
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightrouteProvider {
+    /// HTML page at URL_CALLSIGN
+    CallsignHtml,
+}
+
+impl FromStr for FlightrouteProvider {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "callsign_html" => Ok(Self::CallsignHtml),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Photo providers that can be selected, in order of priority, with the SCRAPE_PHOTO_SOURCES env
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhotoProvider {
+    /// JSON api at URL_AIRCRAFT_PHOTO
+    PhotoJson,
+}
+
+impl FromStr for PhotoProvider {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "photo_json" => Ok(Self::PhotoJson),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Every source the scraper will try, in order, until one returns a result
+#[derive(Debug, Clone)]
+pub struct Sources {
+    pub flightroute: Arc<[Arc<dyn FlightrouteSource>]>,
+    pub photo: Arc<[Arc<dyn PhotoSource>]>,
+}
+
+impl Sources {
+    /// Every source shares a single upstream client, so rate limits and circuit breakers apply across sources
+    pub fn new(app_env: &AppEnv) -> Result<Self, reqwest::Error> {
+        let upstream = Upstream::new()?;
+        Ok(Self {
+            flightroute: app_env
+                .scrape_flightroute_sources
+                .iter()
+                .map(|provider| -> Arc<dyn FlightrouteSource> {
+                    match provider {
+                        FlightrouteProvider::CallsignHtml => {
+                            Arc::new(CallsignHtml::new(&upstream, &app_env.url_callsign))
+                        }
+                    }
+                })
+                .collect(),
+            photo: app_env
+                .scrape_photo_sources
+                .iter()
+                .map(|provider| -> Arc<dyn PhotoSource> {
+                    match provider {
+                        PhotoProvider::PhotoJson => {
+                            Arc::new(PhotoJson::new(&upstream, &app_env.url_aircraft_photo))
+                        }
+                    }
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Flightroute from a third party HTML page, with the callsigns in the title, and the airports in ad targeting scripts, see extract
+#[derive(Debug)]
+pub struct CallsignHtml {
+    upstream: Upstream,
+    url: String,
+}
+
+impl CallsignHtml {
+    pub fn new(upstream: &Upstream, url: &str) -> Self {
+        Self {
+            upstream: upstream.clone(),
+            url: url.to_owned(),
+        }
+    }
+
+    /// A 404 is an unknown callsign, rather than a page that couldn't be parsed
+    async fn request(&self, callsign: &Callsign) -> Result<String, FailedScrape> {
+        match self.upstream.get(&format!("{}/{callsign}", self.url)).await {
+            Ok((reqwest::StatusCode::NOT_FOUND, _)) => Err(FailedScrape::new(
+                ScrapeFailure::NotFound,
+                "upstream status 404",
+            )),
+            Ok((_, text)) => Ok(text),
+            Err(e) => {
+                tracing::error!("can't scrape callsign address: {}: {}", e.failure, e.reason);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl FlightrouteSource for CallsignHtml {
+    fn name(&self) -> &'static str {
+        "callsign_html"
+    }
+
+    fn flightroute<'a>(
+        &'a self,
+        callsign: &'a Callsign,
+    ) -> SourceFuture<'a, Result<ScrapedFlightroute, FailedScrape>> {
+        Box::pin(async move {
+            let html = self.request(callsign).await?;
+            extract::flightroute(&html).map_err(|e| {
+                tracing::error!("{callsign}: can't extract flightroute: {e}");
+                FailedScrape::new(ScrapeFailure::Parse, e.to_string())
+            })
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+struct PhotoResponse {
+    status: u16,
+    count: Option<u16>,
+    data: Option<Vec<PhotoData>>,
+}
+
+/// Photo from a third party JSON api
+#[derive(Debug)]
+pub struct PhotoJson {
+    upstream: Upstream,
+    url: String,
+}
+
+impl PhotoJson {
+    pub fn new(upstream: &Upstream, url: &str) -> Self {
+        Self {
+            upstream: upstream.clone(),
+            url: url.to_owned(),
+        }
+    }
+
+    #[allow(clippy::cognitive_complexity)]
+    async fn request(&self, mode_s: &ModeS) -> Result<Vec<PhotoData>, FailedScrape> {
+        match self
+            .upstream
+            .get(&format!(
+                "{}ac_thumb.json?m={mode_s}&n={MAX_PHOTOS}",
+                self.url
+            ))
+            .await
+        {
+            Ok((_, text)) => match serde_json::from_str::<PhotoResponse>(&text) {
+                Ok(photo) => photo
+                    .data
+                    .filter(|data| !data.is_empty())
+                    .map(|mut data| {
+                        data.truncate(MAX_PHOTOS);
+                        data
+                    })
+                    .ok_or_else(|| FailedScrape::new(ScrapeFailure::NotFound, "no photos")),
+                Err(e) => {
+                    tracing::error!("{e:?}");
+                    tracing::error!("can't transform photo into json");
+                    Err(FailedScrape::new(ScrapeFailure::Parse, e.to_string()))
+                }
+            },
+            Err(e) => {
+                tracing::error!("can't scrape photo address: {}: {}", e.failure, e.reason);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl PhotoSource for PhotoJson {
+    fn name(&self) -> &'static str {
+        "photo_json"
+    }
+
+    fn photo<'a>(
+        &'a self,
+        mode_s: &'a ModeS,
+    ) -> SourceFuture<'a, Result<Vec<PhotoData>, FailedScrape>> {
+        Box::pin(self.request(mode_s))
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_source '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+pub mod tests {
+    use super::*;
+    use crate::S;
+    use crate::api::Validate;
+    use crate::scraper::tests::{TEST_DESTINATION, TEST_ORIGIN};
+
+    #[test]
+    fn scraper_source_provider_from_str() {
+        assert_eq!(
+            FlightrouteProvider::from_str("callsign_html"),
+            Ok(FlightrouteProvider::CallsignHtml)
+        );
+        assert_eq!(
+            PhotoProvider::from_str("photo_json"),
+            Ok(PhotoProvider::PhotoJson)
+        );
+        assert!(FlightrouteProvider::from_str("photo_json").is_err());
+        assert!(PhotoProvider::from_str("").is_err());
+    }
+
+    #[test]
+    fn scraper_source_new() {
+        let mut app_env = AppEnv::get_env();
+        app_env.scrape_flightroute_sources = vec![FlightrouteProvider::CallsignHtml];
+        app_env.scrape_photo_sources = vec![];
+
+        let result = Sources::new(&app_env).unwrap();
+        assert_eq!(
+            result
+                .flightroute
+                .iter()
+                .map(|source| source.name())
+                .collect::<Vec<_>>(),
+            ["callsign_html"]
+        );
+        assert!(result.photo.is_empty());
+
+        app_env.scrape_photo_sources = vec![PhotoProvider::PhotoJson];
+        let result = Sources::new(&app_env).unwrap();
+        assert_eq!(result.photo[0].name(), "photo_json");
+    }
+
+    #[test]
+    fn scraper_source_photo_response() {
+        let prefix = "https://www.xxxxxxxxxxxx.xxx/xxxxxx/xxxxxxxx/xxxxxxxxxxx";
+        let json = format!(
+            r#"{{"status":200,"count":2,"data":[
+                {{"image":"{prefix}/001/001/example.jpg","link":"https://www.example.com/aircraft/photo/001001.html","photographer":"Example Photographer"}},
+                {{"image":"{prefix}/001/002/example.jpg"}}
+            ]}}"#
+        );
+        let result = serde_json::from_str::<PhotoResponse>(&json).unwrap();
+        assert_eq!(result.count, Some(2));
+        let data = result.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].image, "/001/001/example.jpg");
+        assert_eq!(
+            data[0].link.as_deref(),
+            Some("https://www.example.com/aircraft/photo/001001.html")
+        );
+        assert_eq!(
+            data[0].photographer.as_deref(),
+            Some("Example Photographer")
+        );
+        assert_eq!(data[1].image, "/001/002/example.jpg");
+        assert!(data[1].link.is_none());
+        assert!(data[1].photographer.is_none());
+
+        let result = serde_json::from_str::<PhotoResponse>(r#"{"status":404,"count":0}"#).unwrap();
+        assert!(result.data.is_none());
+    }
+
+    #[test]
+    fn scraper_extract_flightroute() {
+        let html_string = include_str!("./test_scrape.txt");
+        let result = extract::flightroute(html_string);
+
+        let expected = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
+            origin: TEST_ORIGIN.to_owned(),
+            midpoint: None,
+            destination: TEST_DESTINATION.to_owned(),
+        };
+
+        assert_eq!(result, Ok(expected));
+    }
+
+    #[tokio::test]
+    #[allow(unreachable_code)]
+    // WARNING - this will test against a live, third party, website
+    async fn scraper_extract_flightroute_live() {
+        unimplemented!("`scraper_extract_flightroute_live` test currently disabled");
+
+        let app_env = AppEnv::get_env();
+        let callsign = Callsign::validate("ANA460").unwrap();
+        let result = CallsignHtml::new(&Upstream::new().unwrap(), &app_env.url_callsign)
+            .flightroute(&callsign)
+            .await;
+        let expected = ScrapedFlightroute {
+            callsign_icao: Callsign::Icao((S!("ANA"), S!("460"))),
+            callsign_iata: Callsign::Iata((S!("NH"), S!("460"))),
+            origin: S!(TEST_ORIGIN),
+            midpoint: None,
+            destination: S!(TEST_DESTINATION),
+        };
+
+        assert_eq!(result, Ok(expected));
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use jiff::Timestamp;
+use serde::Serialize;
+use tokio::sync::broadcast::{Receiver, Sender as BSender};
+use utoipa::ToSchema;
+
+use super::{FailedScrape, ScrapeFailure, ScrapeKind};
+use crate::{
+    api::{Callsign, ModeS},
+    db_postgres::ModelFlightroute,
+};
+
+/// A poisoned lock only means a scrape panicked mid-update, the data is still usable
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Searches currently being scraped, each with a channel that concurrent requests for the same search can subscribe to
+#[derive(Debug)]
+pub struct InFlight<K, V>(Arc<Mutex<HashMap<K, BSender<V>>>>);
+
+impl<K, V> Default for InFlight<K, V> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(HashMap::new())))
+    }
+}
+
+impl<K, V> Clone for InFlight<K, V> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<K: Eq + Hash + Display, V> InFlight<K, V> {
+    /// Subscribe to the result of a search, if it's currently being scraped
+    pub fn subscribe(&self, key: &K) -> Option<Receiver<V>> {
+        lock(&self.0).get(key).map(BSender::subscribe)
+    }
+
+    pub fn insert(&self, key: K, tx: BSender<V>) {
+        lock(&self.0).insert(key, tx);
+    }
+
+    pub fn remove(&self, key: &K) {
+        lock(&self.0).remove(key);
+    }
+
+    /// Every in-flight search, sorted
+    fn keys(&self) -> Vec<String> {
+        let mut keys = lock(&self.0)
+            .keys()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys
+    }
+}
+
+/// Shared between the scraper and the api, so that the status can be read without queueing behind scrape requests
+#[derive(Debug, Clone, Default)]
+pub struct ScraperShared {
+    pub callsign: InFlight<Callsign, Option<ModelFlightroute>>,
+    pub photo: InFlight<ModeS, ()>,
+    pub metrics: Metrics,
+}
+
+impl ScraperShared {
+    /// In-flight searches, sorted, the given queue depth, and counters since start
+    pub fn status(&self, queue_depth: usize) -> ScraperStatus {
+        self.metrics
+            .status(queue_depth, self.callsign.keys(), self.photo.keys())
+    }
+}
+
+/// Counters for a single job type, since the scraper started
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    success: u64,
+    failure: u64,
+    timeout: u64,
+    latency: Duration,
+}
+
+impl Counters {
+    fn record(&mut self, latency: Duration, result: Result<(), &FailedScrape>) {
+        match result {
+            Ok(()) => self.success += 1,
+            Err(failed) if failed.failure == ScrapeFailure::Timeout => self.timeout += 1,
+            Err(_) => self.failure += 1,
+        }
+        self.latency = self.latency.saturating_add(latency);
+    }
+
+    fn status(self, in_flight: Vec<String>) -> JobStatus {
+        let scrapes = self.success + self.failure + self.timeout;
+        JobStatus {
+            in_flight,
+            success: self.success,
+            failure: self.failure,
+            timeout: self.timeout,
+            average_latency_ms: (scrapes > 0).then(|| {
+                u64::try_from(self.latency.as_millis() / u128::from(scrapes)).unwrap_or(u64::MAX)
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    callsign: Counters,
+    photo: Counters,
+    last_upstream_error: Option<UpstreamError>,
+}
+
+/// Scrape counters, shared between the scraper and each spawned scrape
+#[derive(Debug, Default, Clone)]
+pub struct Metrics(Arc<Mutex<MetricsInner>>);
+
+impl Metrics {
+    fn lock(&self) -> MutexGuard<'_, MetricsInner> {
+        lock(&self.0)
+    }
+
+    /// Record the result, and latency, of a completed scrape, searches that are backing off aren't scraped, so aren't recorded.
+    /// A transient failure is caused by the upstream source, rather than by the data it returned, so is kept as the last upstream error
+    pub fn record(
+        &self,
+        scrape_kind: ScrapeKind,
+        search: &str,
+        latency: Duration,
+        result: Result<(), &FailedScrape>,
+    ) {
+        let mut inner = self.lock();
+        match scrape_kind {
+            ScrapeKind::Callsign => inner.callsign.record(latency, result),
+            ScrapeKind::Photo => inner.photo.record(latency, result),
+        }
+        if let Err(failed) = result
+            && failed.failure.is_transient()
+        {
+            inner.last_upstream_error = Some(UpstreamError {
+                scrape_kind: scrape_kind.as_str(),
+                search: search.to_owned(),
+                failure: failed.failure.as_str(),
+                reason: failed.reason.clone(),
+                timestamp: Timestamp::now(),
+            });
+        }
+    }
+
+    pub fn status(
+        &self,
+        queue_depth: usize,
+        callsign_in_flight: Vec<String>,
+        photo_in_flight: Vec<String>,
+    ) -> ScraperStatus {
+        let inner = self.lock();
+        ScraperStatus {
+            queue_depth,
+            callsign: inner.callsign.status(callsign_in_flight),
+            photo: inner.photo.status(photo_in_flight),
+            last_upstream_error: inner.last_upstream_error.clone(),
+        }
+    }
+}
+
+/// Most recent failure caused by an upstream source, rather than by the data it returned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct UpstreamError {
+    pub scrape_kind: &'static str,
+    pub search: String,
+    pub failure: &'static str,
+    pub reason: String,
+    #[schema(value_type = String)]
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct JobStatus {
+    /// Searches currently being scraped, sorted
+    pub in_flight: Vec<String>,
+    pub success: u64,
+    pub failure: u64,
+    pub timeout: u64,
+    /// Mean latency of every completed scrape, null if there haven't been any
+    pub average_latency_ms: Option<u64>,
+}
+
+/// Response for the /scraper/status api route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ScraperStatus {
+    /// Messages waiting to be handled by the scraper
+    pub queue_depth: usize,
+    pub callsign: JobStatus,
+    pub photo: JobStatus,
+    pub last_upstream_error: Option<UpstreamError>,
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_status '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::S;
+
+    #[test]
+    fn scraper_status_empty() {
+        let result = Metrics::default().status(0, vec![], vec![]);
+        let empty = JobStatus {
+            in_flight: vec![],
+            success: 0,
+            failure: 0,
+            timeout: 0,
+            average_latency_ms: None,
+        };
+        assert_eq!(result.queue_depth, 0);
+        assert_eq!(result.callsign, empty);
+        assert_eq!(result.photo, empty);
+        assert!(result.last_upstream_error.is_none());
+    }
+
+    #[test]
+    fn scraper_status_record() {
+        let metrics = Metrics::default();
+        let timeout = FailedScrape::new(ScrapeFailure::Timeout, "callsign_html");
+        let not_found = FailedScrape::new(ScrapeFailure::NotFound, "callsign_html");
+
+        metrics.record(
+            ScrapeKind::Callsign,
+            "ANA460",
+            Duration::from_millis(100),
+            Ok(()),
+        );
+        metrics.record(
+            ScrapeKind::Callsign,
+            "ANA461",
+            Duration::from_millis(200),
+            Err(&not_found),
+        );
+        metrics.record(
+            ScrapeKind::Callsign,
+            "ANA462",
+            Duration::from_millis(600),
+            Err(&timeout),
+        );
+        metrics.record(
+            ScrapeKind::Photo,
+            "393C00",
+            Duration::from_millis(50),
+            Ok(()),
+        );
+
+        let result = metrics.status(3, vec![S!("ANA463")], vec![]);
+        assert_eq!(result.queue_depth, 3);
+        assert_eq!(
+            result.callsign,
+            JobStatus {
+                in_flight: vec![S!("ANA463")],
+                success: 1,
+                failure: 1,
+                timeout: 1,
+                average_latency_ms: Some(300),
+            }
+        );
+        assert_eq!(
+            result.photo,
+            JobStatus {
+                in_flight: vec![],
+                success: 1,
+                failure: 0,
+                timeout: 0,
+                average_latency_ms: Some(50),
+            }
+        );
+
+        let result = result.last_upstream_error.unwrap();
+        assert_eq!(result.scrape_kind, "callsign");
+        assert_eq!(result.search, "ANA462");
+        assert_eq!(result.failure, "timeout");
+        assert_eq!(result.reason, "callsign_html");
+    }
+
+    #[test]
+    fn scraper_status_last_upstream_error() {
+        let metrics = Metrics::default();
+
+        // Not found is the upstream working as intended
+        let not_found = FailedScrape::new(ScrapeFailure::NotFound, "photo_json");
+        metrics.record(ScrapeKind::Photo, "393C00", Duration::ZERO, Err(&not_found));
+        assert!(
+            metrics
+                .status(0, vec![], vec![])
+                .last_upstream_error
+                .is_none()
+        );
+
+        let request = FailedScrape::new(ScrapeFailure::Request, "photo_json");
+        metrics.record(ScrapeKind::Photo, "393C00", Duration::ZERO, Err(&request));
+        let circuit_open = FailedScrape::new(ScrapeFailure::CircuitOpen, "callsign_html");
+        metrics.record(
+            ScrapeKind::Callsign,
+            "ANA460",
+            Duration::ZERO,
+            Err(&circuit_open),
+        );
+        // A later success doesn't clear the last error
+        metrics.record(ScrapeKind::Photo, "393C01", Duration::ZERO, Ok(()));
+
+        let result = metrics
+            .status(0, vec![], vec![])
+            .last_upstream_error
+            .unwrap();
+        assert_eq!(result.scrape_kind, "callsign");
+        assert_eq!(result.failure, "circuit_open");
+    }
+}
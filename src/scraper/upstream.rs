@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, StatusCode};
+use tokio::sync::Semaphore;
+
+use super::{FailedScrape, SCRAPE_TIMEOUT, ScrapeFailure};
+
+/// Max number of in-flight upstream requests, across every host
+const MAX_CONCURRENT: usize = 4;
+
+/// Requests that can be made to a single host in a burst
+const BUCKET_CAPACITY: f64 = 5.0;
+
+/// Tokens added to each host's bucket per second, once the burst has been used
+const BUCKET_REFILL_PER_SEC: f64 = 1.0;
+
+/// Consecutive failures to a single host before its circuit is opened
+const BREAKER_THRESHOLD: u32 = 5;
+
+/// How long an opened circuit stays open, before a single trial request is allowed
+const BREAKER_COOL_OFF: Duration = Duration::from_secs(120);
+
+/// Per-host token bucket, requests wait for a token rather than being rejected
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    const fn new(now: Instant) -> Self {
+        Self {
+            tokens: BUCKET_CAPACITY,
+            updated: now,
+        }
+    }
+
+    /// Take a token, else return how long until one is available
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = elapsed
+            .mul_add(BUCKET_REFILL_PER_SEC, self.tokens)
+            .min(BUCKET_CAPACITY);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / BUCKET_REFILL_PER_SEC,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A single trial request has been allowed, if it never completes, e.g. dropped by a timeout, then another is allowed after the cool-off
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+/// Per-host circuit breaker, state changes are logged
+#[derive(Debug)]
+struct CircuitBreaker {
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    const fn new() -> Self {
+        Self {
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    /// Would a request be rejected, without allowing a trial request, so that a request can be rejected before waiting for a token
+    fn is_open(&self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => false,
+            CircuitState::Open { until } => now < until,
+            CircuitState::HalfOpen { since } => now < since + BREAKER_COOL_OFF,
+        }
+    }
+
+    /// Can a request be made to the host
+    fn allow(&mut self, host: &str, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => true,
+            CircuitState::Open { until } if now >= until => {
+                tracing::info!("{host}: circuit half-open, allowing a trial request");
+                self.state = CircuitState::HalfOpen { since: now };
+                true
+            }
+            CircuitState::HalfOpen { since } if now >= since + BREAKER_COOL_OFF => {
+                self.state = CircuitState::HalfOpen { since: now };
+                true
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => false,
+        }
+    }
+
+    fn success(&mut self, host: &str) {
+        if !matches!(self.state, CircuitState::Closed { .. }) {
+            tracing::info!("{host}: circuit closed");
+        }
+        self.state = CircuitState::Closed { failures: 0 };
+    }
+
+    fn failure(&mut self, host: &str, now: Instant) {
+        let failures = match self.state {
+            CircuitState::Closed { failures } => failures.saturating_add(1),
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => BREAKER_THRESHOLD,
+        };
+        if failures >= BREAKER_THRESHOLD {
+            tracing::warn!(
+                "{host}: circuit open after {failures} consecutive failures, cooling off for {}s",
+                BREAKER_COOL_OFF.as_secs()
+            );
+            self.state = CircuitState::Open {
+                until: now + BREAKER_COOL_OFF,
+            };
+        } else {
+            self.state = CircuitState::Closed { failures };
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Host {
+    bucket: TokenBucket,
+    breaker: CircuitBreaker,
+}
+
+/// Shared by every source, a single reqwest client, with a per-host token bucket and circuit breaker, and a global concurrency limit
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    client: Client,
+    permits: Arc<Semaphore>,
+    hosts: Arc<Mutex<HashMap<String, Host>>>,
+}
+
+impl Upstream {
+    /// Build a reqwest client, with a request timeout, and compression enabled.
+    /// The timeout starts when the request is sent, so doesn't include waiting for a token or a permit
+    pub fn new() -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: Client::builder()
+                .connect_timeout(SCRAPE_TIMEOUT)
+                .timeout(SCRAPE_TIMEOUT)
+                .gzip(true)
+                .brotli(true)
+                .build()?,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT)),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Run a closure against the given host, creating it if this is the first request
+    fn with_host<T>(&self, host: &str, f: impl FnOnce(&mut Host) -> T) -> T {
+        let mut hosts = self
+            .hosts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let host = hosts.entry(host.to_owned()).or_insert_with(|| Host {
+            bucket: TokenBucket::new(Instant::now()),
+            breaker: CircuitBreaker::new(),
+        });
+        f(host)
+    }
+
+    /// Send a get request to the given url, waiting for a token from the host's bucket, and a concurrency permit,
+    /// and read the body, as text, while holding the permit.
+    /// The circuit is checked before waiting, and again once the token and permit are held, as it may have opened while waiting.
+    /// Request errors, timeouts, 429, and 5xx responses, count as failures for the host's circuit breaker
+    pub async fn get(&self, url: &str) -> Result<(StatusCode, String), FailedScrape> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(ToOwned::to_owned))
+            .ok_or_else(|| FailedScrape::new(ScrapeFailure::Request, "invalid url"))?;
+
+        let circuit_open =
+            || FailedScrape::new(ScrapeFailure::CircuitOpen, format!("{host}: circuit open"));
+        if self.with_host(&host, |i| i.breaker.is_open(Instant::now())) {
+            return Err(circuit_open());
+        }
+        while let Err(wait) = self.with_host(&host, |i| i.bucket.take(Instant::now())) {
+            tokio::time::sleep(wait).await;
+        }
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| FailedScrape::new(ScrapeFailure::Request, e.to_string()))?;
+        if !self.with_host(&host, |i| i.breaker.allow(&host, Instant::now())) {
+            return Err(circuit_open());
+        }
+
+        let response = match self.client.get(url).send().await {
+            Ok(response)
+                if response.status() == StatusCode::TOO_MANY_REQUESTS
+                    || response.status().is_server_error() =>
+            {
+                let reason = format!("{host}: upstream status {}", response.status());
+                tracing::error!("{reason}");
+                self.with_host(&host, |i| i.breaker.failure(&host, Instant::now()));
+                return Err(FailedScrape::new(ScrapeFailure::Request, reason));
+            }
+            Ok(response) => response,
+            Err(e) => return Err(self.request_error(&host, &e)),
+        };
+        let status = response.status();
+        match response.text().await {
+            Ok(text) => {
+                self.with_host(&host, |i| i.breaker.success(&host));
+                Ok((status, text))
+            }
+            Err(e) => Err(self.request_error(&host, &e)),
+        }
+    }
+
+    /// Count a failed, or timed out, request, or body, against the host's circuit breaker
+    fn request_error(&self, host: &str, e: &reqwest::Error) -> FailedScrape {
+        tracing::error!("{e:?}");
+        self.with_host(host, |i| i.breaker.failure(host, Instant::now()));
+        if e.is_timeout() {
+            FailedScrape::new(ScrapeFailure::Timeout, format!("{host}: request timeout"))
+        } else {
+            FailedScrape::new(ScrapeFailure::Request, e.to_string())
+        }
+    }
+}
+
+/// Run tests with
+///
+/// cargo watch -q -c -w src/ -x 'test scraper_upstream '
+#[cfg(test)]
+#[allow(clippy::pedantic, clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "example.com";
+
+    #[test]
+    fn scraper_upstream_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(now);
+
+        for _ in 0..5 {
+            assert!(bucket.take(now).is_ok());
+        }
+        assert_eq!(bucket.take(now), Err(Duration::from_secs(1)));
+
+        // Half a token has been refilled
+        let result = bucket.take(now + Duration::from_millis(500));
+        assert_eq!(result, Err(Duration::from_millis(500)));
+
+        assert!(bucket.take(now + Duration::from_secs(1)).is_ok());
+        assert!(bucket.take(now + Duration::from_secs(1)).is_err());
+
+        // Never refills above capacity
+        let later = now + Duration::from_secs(600);
+        for _ in 0..5 {
+            assert!(bucket.take(later).is_ok());
+        }
+        assert!(bucket.take(later).is_err());
+    }
+
+    #[test]
+    fn scraper_upstream_circuit_breaker_opens() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+
+        for _ in 0..4 {
+            breaker.failure(HOST, now);
+            assert!(breaker.allow(HOST, now));
+        }
+        // A success resets the count
+        breaker.success(HOST);
+        assert_eq!(breaker.state, CircuitState::Closed { failures: 0 });
+
+        for _ in 0..5 {
+            assert!(breaker.allow(HOST, now));
+            breaker.failure(HOST, now);
+        }
+        assert_eq!(
+            breaker.state,
+            CircuitState::Open {
+                until: now + BREAKER_COOL_OFF
+            }
+        );
+        assert!(!breaker.allow(HOST, now));
+        assert!(!breaker.allow(HOST, now + BREAKER_COOL_OFF - Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn scraper_upstream_circuit_breaker_is_open() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        assert!(!breaker.is_open(now));
+        for _ in 0..5 {
+            breaker.failure(HOST, now);
+        }
+        assert!(breaker.is_open(now));
+
+        // Doesn't take the trial request
+        let after = now + BREAKER_COOL_OFF;
+        assert!(!breaker.is_open(after));
+        assert!(matches!(breaker.state, CircuitState::Open { .. }));
+        assert!(breaker.allow(HOST, after));
+        assert!(breaker.is_open(after));
+    }
+
+    #[tokio::test]
+    /// A request that was waiting for a permit when the circuit opened isn't sent
+    async fn scraper_upstream_circuit_opened_while_queued() {
+        let upstream = Upstream::new().unwrap();
+        let permits = upstream
+            .permits
+            .acquire_many(MAX_CONCURRENT as u32)
+            .await
+            .unwrap();
+
+        let queued = {
+            let upstream = upstream.clone();
+            tokio::spawn(async move { upstream.get("http://127.0.0.1:1/").await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        upstream.with_host("127.0.0.1", |i| {
+            for _ in 0..5 {
+                i.breaker.failure("127.0.0.1", Instant::now());
+            }
+        });
+        drop(permits);
+
+        assert_eq!(
+            queued.await.unwrap().unwrap_err(),
+            FailedScrape::new(ScrapeFailure::CircuitOpen, "127.0.0.1: circuit open")
+        );
+    }
+
+    #[test]
+    fn scraper_upstream_circuit_breaker_half_open() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        for _ in 0..5 {
+            breaker.failure(HOST, now);
+        }
+
+        // Single trial request after the cool-off
+        let after = now + BREAKER_COOL_OFF;
+        assert!(breaker.allow(HOST, after));
+        assert_eq!(breaker.state, CircuitState::HalfOpen { since: after });
+        assert!(!breaker.allow(HOST, after));
+
+        // Failed trial re-opens the circuit
+        breaker.failure(HOST, after);
+        assert_eq!(
+            breaker.state,
+            CircuitState::Open {
+                until: after + BREAKER_COOL_OFF
+            }
+        );
+
+        // Successful trial closes it
+        let after = after + BREAKER_COOL_OFF;
+        assert!(breaker.allow(HOST, after));
+        breaker.success(HOST);
+        assert_eq!(breaker.state, CircuitState::Closed { failures: 0 });
+        assert!(breaker.allow(HOST, after));
+    }
+
+    #[test]
+    fn scraper_upstream_circuit_breaker_abandoned_trial() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+        for _ in 0..5 {
+            breaker.failure(HOST, now);
+        }
+        let after = now + BREAKER_COOL_OFF;
+        assert!(breaker.allow(HOST, after));
+
+        // Trial never completed, so another is allowed after the cool-off
+        assert!(!breaker.allow(HOST, after + Duration::from_secs(1)));
+        assert!(breaker.allow(HOST, after + BREAKER_COOL_OFF));
+    }
+
+    #[tokio::test]
+    /// Takes SCRAPE_TIMEOUT, a host that never responds is a timeout, and a failure for its circuit breaker
+    async fn scraper_upstream_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let upstream = Upstream::new().unwrap();
+        let result = upstream.get(&format!("http://{addr}/")).await;
+        assert_eq!(
+            result.unwrap_err(),
+            FailedScrape::new(ScrapeFailure::Timeout, "127.0.0.1: request timeout")
+        );
+        let state = upstream.with_host("127.0.0.1", |i| i.breaker.state);
+        assert_eq!(state, CircuitState::Closed { failures: 1 });
+    }
+
+    #[tokio::test]
+    async fn scraper_upstream_invalid_url() {
+        let upstream = Upstream::new().unwrap();
+        assert_eq!(
+            upstream.get("not a url").await.unwrap_err(),
+            FailedScrape::new(ScrapeFailure::Request, "invalid url")
+        );
+    }
+}